@@ -0,0 +1,170 @@
+use axum::{
+    body::Body,
+    extract::{Path, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    Json,
+};
+use futures::stream::{Stream, StreamExt};
+use serde_json::json;
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+
+use crate::api::error::ApiError;
+use crate::api::extract::ApiJson;
+use crate::api::routes::AppState;
+use crate::api::types::{ModelDeleted, PullModelRequest};
+use crate::backend::InferenceEngine;
+use crate::downloader::downloader::{DownloadError, Downloader};
+use crate::downloader::huggingface::HuggingFaceDownloader;
+use crate::downloader::progress::{ChannelObserver, DownloadEvent};
+
+/// Minimum time between two progress events of a file in a pull stream
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Pull a model from Hugging Face. Progress is streamed as NDJSON, or as
+/// server-sent events when the client accepts `text/event-stream`.
+pub async fn pull_model<E: InferenceEngine + 'static>(
+    State(state): State<AppState<E>>,
+    headers: HeaderMap,
+    ApiJson(req): ApiJson<PullModelRequest>,
+) -> Response {
+    if req.model.trim().is_empty() {
+        return ApiError::invalid_value("model", "Model name must not be empty").into_response();
+    }
+    // Lowercase names keep caching and registry entries consistent, as in `puma pull`
+    let name = req.model.to_lowercase();
+
+    if !req.stream {
+        if let Err(e) = HuggingFaceDownloader::new().download_model(&name).await {
+            return download_error(&name, e).into_response();
+        }
+        return match state.registry.get_model(&name) {
+            Ok(Some(model)) => Json(model).into_response(),
+            Ok(None) => ApiError::server_error(format!(
+                "Model '{}' was downloaded but is not registered",
+                name
+            ))
+            .into_response(),
+            Err(e) => ApiError::server_error(format!("Failed to get model: {}", e)).into_response(),
+        };
+    }
+
+    let events = pull_events(name).map(|event| match event {
+        Ok(event) => json!(event),
+        Err(e) => json!({ "status": "error", "error": e.message }),
+    });
+    let sse = headers
+        .get(header::ACCEPT)
+        .and_then(|accept| accept.to_str().ok())
+        .is_some_and(|accept| accept.contains("text/event-stream"));
+    if sse {
+        let events =
+            events.map(|event| Ok::<_, Infallible>(Event::default().data(event.to_string())));
+        Sse::new(events)
+            .keep_alive(KeepAlive::default())
+            .into_response()
+    } else {
+        let lines = events.map(|event| Ok::<_, Infallible>(format!("{}\n", event)));
+        (
+            [(header::CONTENT_TYPE, "application/x-ndjson")],
+            Body::from_stream(lines),
+        )
+            .into_response()
+    }
+}
+
+/// Download a model in the background, yielding its progress events until
+/// `success` or the error ending the download. The download goes on when the
+/// client disconnects, so the model is still registered.
+pub fn pull_events(name: String) -> impl Stream<Item = Result<DownloadEvent, ApiError>> {
+    let (events_tx, mut events) = mpsc::unbounded_channel();
+    let (tx, rx) = mpsc::channel(16);
+
+    tokio::spawn(async move {
+        let downloader = HuggingFaceDownloader::new()
+            .with_observer(Arc::new(ChannelObserver::new(events_tx, PROGRESS_INTERVAL)));
+        let model = name.clone();
+        let download = tokio::spawn(async move { downloader.download_model(&model).await });
+
+        // Events end once the download is over and the downloader dropped
+        while let Some(event) = events.recv().await {
+            if tx.send(Ok(event)).await.is_err() {
+                break;
+            }
+        }
+
+        let error = match download.await {
+            Ok(Ok(())) => return,
+            Ok(Err(e)) => download_error(&name, e),
+            Err(e) => ApiError::server_error(format!("Failed to pull model: {}", e)),
+        };
+        let _ = tx.send(Err(error)).await;
+    });
+
+    ReceiverStream::new(rx)
+}
+
+fn download_error(name: &str, error: DownloadError) -> ApiError {
+    match error {
+        DownloadError::ModelNotFound(_) => ApiError::model_not_found(name),
+        e => ApiError::server_error(format!("Failed to pull model: {}", e)),
+    }
+}
+
+/// Get everything the registry knows about a model
+pub async fn get_model<E: InferenceEngine + 'static>(
+    State(state): State<AppState<E>>,
+    Path(name): Path<String>,
+) -> Response {
+    match state.registry.get_model(&name) {
+        Ok(Some(model)) => Json(model).into_response(),
+        Ok(None) => ApiError::model_not_found(&name).into_response(),
+        Err(e) => ApiError::server_error(format!("Failed to get model: {}", e)).into_response(),
+    }
+}
+
+/// Delete a model and its files, refusing the model being served
+pub async fn delete_model<E: InferenceEngine + 'static>(
+    State(state): State<AppState<E>>,
+    Path(name): Path<String>,
+) -> Response {
+    let model = match state.registry.get_model(&name) {
+        Ok(Some(model)) => model,
+        Ok(None) => return ApiError::model_not_found(&name).into_response(),
+        Err(e) => {
+            return ApiError::server_error(format!("Failed to get model: {}", e)).into_response()
+        }
+    };
+
+    // The registry matches names ignoring case, compare the registered name
+    if state.readiness.is_serving(&model.name) {
+        return ApiError::new(
+            StatusCode::CONFLICT,
+            "invalid_request_error",
+            format!(
+                "Model '{}' is being served and cannot be deleted",
+                model.name
+            ),
+        )
+        .with_code("model_in_use")
+        .with_param("model")
+        .into_response();
+    }
+
+    match state.registry.remove_model(&model.name) {
+        Ok(()) => Json(ModelDeleted {
+            id: model.name,
+            object: "model".to_string(),
+            deleted: true,
+        })
+        .into_response(),
+        Err(e) => ApiError::server_error(format!("Failed to delete model: {}", e)).into_response(),
+    }
+}
@@ -0,0 +1,280 @@
+use axum::{
+    body::{Body, Bytes, HttpBody},
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+use chrono::SecondsFormat;
+use hyper::body::{Frame, SizeHint};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{ready, Context, Poll};
+use std::time::Instant;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tokio::sync::oneshot;
+use tracing::warn;
+
+use crate::api::auth::ApiKeyIdentity;
+use crate::api::images::MAX_IMAGE_REQUEST_BYTES;
+use crate::api::usage::{GenerationUsage, UsageObserver, UsageRecorder};
+use crate::storage::{AuditEntry, AuditStorage, SqliteStorage};
+
+/// Records every API request in the audit log of the database. Entries are
+/// written by a thread of their own, so requests never wait on the database.
+pub struct AuditLog {
+    writer: UnboundedSender<AuditMessage>,
+    /// Also keep the full request and response bodies
+    capture_bodies: bool,
+}
+
+enum AuditMessage {
+    Entry(Box<AuditEntry>),
+    /// Answered once the entries sent before are written
+    Flush(oneshot::Sender<()>),
+}
+
+impl AuditLog {
+    pub fn new(storage: Arc<SqliteStorage>) -> Self {
+        let (writer, entries) = mpsc::unbounded_channel();
+        std::thread::Builder::new()
+            .name("audit-log".to_string())
+            .spawn(move || write_entries(&storage, entries))
+            .expect("Failed to start the audit log writer");
+        Self {
+            writer,
+            capture_bodies: false,
+        }
+    }
+
+    pub fn with_bodies(mut self, capture_bodies: bool) -> Self {
+        self.capture_bodies = capture_bodies;
+        self
+    }
+
+    /// Wait until the entries of finished requests are written
+    pub async fn flush(&self) {
+        let (done, written) = oneshot::channel();
+        if self.writer.send(AuditMessage::Flush(done)).is_ok() {
+            let _ = written.await;
+        }
+    }
+}
+
+/// Write entries until every sender is dropped
+fn write_entries(storage: &SqliteStorage, mut messages: UnboundedReceiver<AuditMessage>) {
+    while let Some(message) = messages.blocking_recv() {
+        match message {
+            AuditMessage::Entry(entry) => {
+                if let Err(e) = storage.record_request(*entry) {
+                    warn!("Failed to write audit log: {}", e);
+                }
+            }
+            AuditMessage::Flush(done) => {
+                let _ = done.send(());
+            }
+        }
+    }
+}
+
+/// The entry of a request in flight. It collects the usage of the request's
+/// generations and is queued for writing once the last reference is dropped,
+/// which for streamed responses is when the stream ends.
+struct RequestAudit {
+    writer: UnboundedSender<AuditMessage>,
+    received: Instant,
+    entry: Mutex<AuditEntry>,
+}
+
+/// Largest body kept in an entry, the largest body any route accepts. Longer
+/// bodies are truncated.
+const MAX_CAPTURED_BYTES: usize = MAX_IMAGE_REQUEST_BYTES;
+
+#[derive(Clone, Copy)]
+enum BodyKind {
+    Request,
+    Response,
+}
+
+impl RequestAudit {
+    /// Keep a chunk of a body, up to `MAX_CAPTURED_BYTES` of it
+    fn capture(&self, kind: BodyKind, chunk: &[u8]) {
+        let mut entry = self.entry.lock().unwrap();
+        let body = match kind {
+            BodyKind::Request => &mut entry.request_body,
+            BodyKind::Response => &mut entry.response_body,
+        }
+        .get_or_insert_with(String::new);
+        let len = chunk
+            .len()
+            .min(MAX_CAPTURED_BYTES.saturating_sub(body.len()));
+        body.push_str(&String::from_utf8_lossy(&chunk[..len]));
+    }
+}
+
+/// Body passing its frames through as they are polled, keeping the request's
+/// entry alive until it ends. Nothing is buffered, so the body limits of the
+/// routes still apply to captured requests.
+struct AuditedBody {
+    inner: Body,
+    audit: Arc<RequestAudit>,
+    /// Body to copy the data into, none when bodies are not captured
+    capture: Option<BodyKind>,
+}
+
+impl HttpBody for AuditedBody {
+    type Data = Bytes;
+    type Error = axum::Error;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Bytes>, axum::Error>>> {
+        let frame = ready!(Pin::new(&mut self.inner).poll_frame(cx));
+        if let (Some(kind), Some(Ok(frame))) = (self.capture, &frame) {
+            if let Some(data) = frame.data_ref() {
+                self.audit.capture(kind, data);
+            }
+        }
+        Poll::Ready(frame)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+impl UsageObserver for RequestAudit {
+    fn observe(&self, usage: &GenerationUsage) {
+        let mut entry = self.entry.lock().unwrap();
+        entry.model = Some(usage.model.clone());
+        entry.prompt_tokens += usage.prompt_tokens as u64;
+        entry.completion_tokens += usage.completion_tokens as u64;
+        entry.finish_reason = Some(usage.finish_reason.clone());
+    }
+}
+
+impl Drop for RequestAudit {
+    fn drop(&mut self) {
+        let mut entry = std::mem::take(self.entry.get_mut().unwrap());
+        entry.latency_ms = self.received.elapsed().as_secs_f64() * 1000.0;
+        if self
+            .writer
+            .send(AuditMessage::Entry(Box::new(entry)))
+            .is_err()
+        {
+            warn!("Failed to write audit log: the writer has stopped");
+        }
+    }
+}
+
+/// Probes and scrapes are not API requests, they would flood the log
+fn is_audited(path: &str) -> bool {
+    !(path == "/metrics" || path == "/health" || path.starts_with("/health/"))
+}
+
+/// Middleware recording requests in the audit log. It runs outside of
+/// authentication so rejected requests are recorded too.
+pub async fn record_requests(
+    State(log): State<Arc<AuditLog>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if !is_audited(request.uri().path()) {
+        return next.run(request).await;
+    }
+
+    let audit = Arc::new(RequestAudit {
+        writer: log.writer.clone(),
+        received: Instant::now(),
+        entry: Mutex::new(AuditEntry {
+            timestamp: chrono::Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+            method: request.method().to_string(),
+            path: request.uri().path().to_string(),
+            ..Default::default()
+        }),
+    });
+
+    let mut request = if log.capture_bodies {
+        let (parts, body) = request.into_parts();
+        let body = AuditedBody {
+            inner: body,
+            audit: audit.clone(),
+            capture: Some(BodyKind::Request),
+        };
+        Request::from_parts(parts, Body::new(body))
+    } else {
+        request
+    };
+    UsageRecorder::observe(&mut request, audit.clone());
+
+    let response = next.run(request).await;
+    {
+        let mut entry = audit.entry.lock().unwrap();
+        entry.status = response.status().as_u16();
+        if let Some(identity) = response.extensions().get::<ApiKeyIdentity>() {
+            entry.key_id = Some(identity.id.clone());
+            entry.key_name = Some(identity.name.clone());
+        }
+    }
+
+    // Streamed responses keep the entry alive until they end
+    let (parts, body) = response.into_parts();
+    if body.size_hint().exact().is_some() && !log.capture_bodies {
+        return Response::from_parts(parts, body);
+    }
+    let body = AuditedBody {
+        inner: body,
+        audit,
+        capture: log.capture_bodies.then_some(BodyKind::Response),
+    };
+    Response::from_parts(parts, Body::new(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::to_bytes;
+
+    #[test]
+    fn test_is_audited() {
+        assert!(is_audited("/v1/chat/completions"));
+        assert!(is_audited("/healthz"));
+        assert!(!is_audited("/health"));
+        assert!(!is_audited("/health/ready"));
+        assert!(!is_audited("/metrics"));
+    }
+
+    #[tokio::test]
+    async fn test_captured_body_truncated() {
+        let (writer, _entries) = mpsc::unbounded_channel();
+        let audit = Arc::new(RequestAudit {
+            writer,
+            received: Instant::now(),
+            entry: Mutex::new(AuditEntry::default()),
+        });
+
+        // The body passes through whole, the entry keeps its beginning
+        let body = AuditedBody {
+            inner: Body::from(vec![b'a'; MAX_CAPTURED_BYTES + 10]),
+            audit: audit.clone(),
+            capture: Some(BodyKind::Request),
+        };
+        assert_eq!(
+            body.size_hint().exact(),
+            Some(MAX_CAPTURED_BYTES as u64 + 10)
+        );
+        let bytes = to_bytes(Body::new(body), usize::MAX).await.unwrap();
+        assert_eq!(bytes.len(), MAX_CAPTURED_BYTES + 10);
+
+        let entry = audit.entry.lock().unwrap();
+        assert_eq!(
+            entry.request_body.as_ref().unwrap().len(),
+            MAX_CAPTURED_BYTES
+        );
+        assert_eq!(entry.response_body, None);
+    }
+}
@@ -0,0 +1,259 @@
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderMap, HeaderValue},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::api::error::ApiError;
+use crate::api::ratelimit::RateLimits;
+use crate::storage::{ApiKeyStorage, SqliteStorage};
+
+/// Prefix of generated keys, makes them easy to spot in configs and logs
+const KEY_PREFIX: &str = "puma-";
+
+/// Length of the key prefix kept in the database to tell keys apart
+const STORED_PREFIX_LEN: usize = 12;
+
+/// The key that authenticated a request, added to the request extensions
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApiKeyIdentity {
+    pub id: String,
+    pub name: String,
+    /// Limits of the key itself, the server defaults fill the unset ones
+    pub limits: RateLimits,
+}
+
+/// Keys accepted by the server: keys configured at startup, and keys stored
+/// hashed in the database (managed with `puma key`)
+pub struct ApiKeys {
+    /// Identities of configured keys, by key hash
+    configured: HashMap<String, ApiKeyIdentity>,
+    storage: Option<Arc<SqliteStorage>>,
+    /// Leave the health endpoints open for load balancers and orchestrators
+    public_health: bool,
+}
+
+impl ApiKeys {
+    pub fn new(keys: impl IntoIterator<Item = String>) -> Self {
+        let configured = keys
+            .into_iter()
+            .map(|key| hash_api_key(&key))
+            .map(|hash| {
+                let id = format!("configured-{}", &hash[..8]);
+                let identity = ApiKeyIdentity {
+                    id: id.clone(),
+                    name: id,
+                    limits: RateLimits::default(),
+                };
+                (hash, identity)
+            })
+            .collect();
+        Self {
+            configured,
+            storage: None,
+            public_health: false,
+        }
+    }
+
+    /// Also accept the active keys of the database
+    pub fn with_storage(mut self, storage: Arc<SqliteStorage>) -> Self {
+        self.storage = Some(storage);
+        self
+    }
+
+    pub fn with_public_health(mut self, public_health: bool) -> Self {
+        self.public_health = public_health;
+        self
+    }
+
+    /// Number of keys configured at startup, database keys not included
+    pub fn configured_count(&self) -> usize {
+        self.configured.len()
+    }
+
+    /// Look up the identity of a key, `None` if the key is unknown or revoked
+    pub fn authenticate(&self, key: &str) -> io::Result<Option<ApiKeyIdentity>> {
+        let hash = hash_api_key(key);
+        if let Some(identity) = self.configured.get(&hash) {
+            return Ok(Some(identity.clone()));
+        }
+
+        match &self.storage {
+            Some(storage) => Ok(storage.find_api_key(&hash)?.map(|key| ApiKeyIdentity {
+                id: key.id,
+                name: key.name,
+                limits: RateLimits {
+                    requests_per_minute: key.requests_per_minute,
+                    tokens_per_minute: key.tokens_per_minute,
+                    daily_tokens: key.daily_token_quota,
+                },
+            })),
+            None => Ok(None),
+        }
+    }
+
+    fn is_public(&self, path: &str) -> bool {
+        self.public_health && (path == "/health" || path.starts_with("/health/"))
+    }
+}
+
+/// Middleware rejecting requests without a valid bearer token. The
+/// `x-api-key` header is accepted too, as sent by Anthropic clients. The
+/// identity of the key is added to the response too, for outer middleware.
+pub async fn require_api_key(
+    State(keys): State<Arc<ApiKeys>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    if keys.is_public(request.uri().path()) {
+        return next.run(request).await;
+    }
+
+    let Some(key) = request_key(request.headers()) else {
+        return unauthorized(ApiError::unauthorized(
+            "You didn't provide an API key. You need to provide your API key in an Authorization header using Bearer auth (i.e. Authorization: Bearer YOUR_KEY).",
+        ));
+    };
+
+    match keys.authenticate(&key) {
+        Ok(Some(identity)) => {
+            request.extensions_mut().insert(identity.clone());
+            let mut response = next.run(request).await;
+            response.extensions_mut().insert(identity);
+            response
+        }
+        Ok(None) => unauthorized(
+            ApiError::unauthorized(format!(
+                "Incorrect API key provided: {}.",
+                mask_api_key(&key)
+            ))
+            .with_code("invalid_api_key"),
+        ),
+        Err(e) => ApiError::server_error(format!("Failed to check API key: {}", e)).into_response(),
+    }
+}
+
+fn unauthorized(error: ApiError) -> Response {
+    let mut response = error.into_response();
+    response
+        .headers_mut()
+        .insert(header::WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+    response
+}
+
+/// The key sent with a request, if any
+fn request_key(headers: &HeaderMap) -> Option<String> {
+    if let Some(value) = headers.get(header::AUTHORIZATION) {
+        let value = value.to_str().ok()?.trim();
+        let (scheme, key) = value.split_once(' ')?;
+        return scheme
+            .eq_ignore_ascii_case("bearer")
+            .then(|| key.trim().to_string())
+            .filter(|key| !key.is_empty());
+    }
+
+    headers
+        .get("x-api-key")
+        .and_then(|value| value.to_str().ok())
+        .map(|key| key.trim().to_string())
+        .filter(|key| !key.is_empty())
+}
+
+/// Hide all but the start and end of a key, for error messages
+fn mask_api_key(key: &str) -> String {
+    let chars: Vec<char> = key.chars().collect();
+    if chars.len() < 12 {
+        return "*".repeat(chars.len());
+    }
+    let start: String = chars[..3].iter().collect();
+    let end: String = chars[chars.len() - 4..].iter().collect();
+    format!("{}{}{}", start, "*".repeat(chars.len() - 7), end)
+}
+
+/// SHA-256 hash of a key, the only form in which keys are stored
+pub fn hash_api_key(key: &str) -> String {
+    format!("{:x}", Sha256::digest(key.as_bytes()))
+}
+
+/// Generate a new random key
+pub fn generate_api_key() -> String {
+    format!(
+        "{}{}{}",
+        KEY_PREFIX,
+        Uuid::new_v4().simple(),
+        Uuid::new_v4().simple()
+    )
+}
+
+/// Start of a key, kept to tell stored keys apart
+pub fn api_key_prefix(key: &str) -> String {
+    key.chars().take(STORED_PREFIX_LEN).collect()
+}
+
+/// Read keys from a file, one per line. Blank lines and `#` comments are skipped.
+pub fn load_keys_file(path: &Path) -> io::Result<Vec<String>> {
+    Ok(fs::read_to_string(path)?
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_key() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(request_key(&headers), None);
+
+        headers.insert("x-api-key", HeaderValue::from_static("secret"));
+        assert_eq!(request_key(&headers).as_deref(), Some("secret"));
+
+        headers.insert(
+            header::AUTHORIZATION,
+            HeaderValue::from_static("bearer token"),
+        );
+        assert_eq!(request_key(&headers).as_deref(), Some("token"));
+
+        // A malformed Authorization header is not overridden by x-api-key
+        headers.insert(header::AUTHORIZATION, HeaderValue::from_static("Basic abc"));
+        assert_eq!(request_key(&headers), None);
+    }
+
+    #[test]
+    fn test_mask_api_key() {
+        assert_eq!(mask_api_key("short"), "*****");
+        assert_eq!(
+            mask_api_key("puma-0123456789abcdef"),
+            "pum**************cdef"
+        );
+    }
+
+    #[test]
+    fn test_generate_api_key() {
+        let key = generate_api_key();
+        assert!(key.starts_with(KEY_PREFIX));
+        assert_eq!(key.len(), KEY_PREFIX.len() + 64);
+        assert_ne!(key, generate_api_key());
+        assert_eq!(api_key_prefix(&key).len(), STORED_PREFIX_LEN);
+    }
+
+    #[test]
+    fn test_load_keys_file() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("keys");
+        fs::write(&path, "# team keys\nkey-one\n\n  key-two  \n").unwrap();
+        assert_eq!(load_keys_file(&path).unwrap(), vec!["key-one", "key-two"]);
+    }
+}
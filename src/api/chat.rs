@@ -11,73 +11,94 @@ use std::sync::Arc;
 use tokio_stream::wrappers::ReceiverStream;
 use uuid::Uuid;
 
+use crate::api::context::fit_chat_messages;
+use crate::api::error::ApiError;
+use crate::api::extract::ApiJson;
+use crate::api::images::load_request_images;
+use crate::api::models::resolve_model;
+use crate::api::params::validate_sampling;
+use crate::api::reasoning::{split_reasoning, thinking_budget, ReasoningParser};
 use crate::api::routes::AppState;
+use crate::api::timings::RequestTimer;
 use crate::api::types::{
     ChatChoice, ChatChoiceDelta, ChatCompletionChunk, ChatCompletionRequest,
-    ChatCompletionResponse, ChatMessage, ChatMessageDelta, ErrorResponse, Usage,
+    ChatCompletionResponse, ChatMessage, ChatMessageDelta, CompletionTokensDetails, ErrorResponse,
+    PromptTokensDetails, Usage,
 };
-use crate::backend::InferenceEngine;
+use crate::backend::{GenerateRequest, InferenceEngine};
 
 /// Main handler for chat completions
 pub async fn chat_completions<E: InferenceEngine + 'static>(
     State(state): State<AppState<E>>,
-    Json(req): Json<ChatCompletionRequest>,
+    timer: RequestTimer,
+    ApiJson(mut req): ApiJson<ChatCompletionRequest>,
 ) -> Response {
-    let engine = state.engine;
-    let registry = state.registry;
+    let engine = state.engine.clone();
 
     // Validate request
     if req.messages.is_empty() {
-        return (
-            axum::http::StatusCode::BAD_REQUEST,
-            Json(ErrorResponse::new(
-                "messages cannot be empty".to_string(),
-                "invalid_request_error".to_string(),
-            )),
-        )
+        return ApiError::invalid_request("messages cannot be empty")
+            .with_param("messages")
             .into_response();
     }
+    if let Err(e) = validate_sampling(req.temperature, req.top_p, req.max_tokens, req.n) {
+        return e.into_response();
+    }
 
     // Validate model exists
-    match registry.get_model(&req.model) {
-        Ok(None) => {
-            return (
-                axum::http::StatusCode::NOT_FOUND,
-                Json(ErrorResponse::new(
-                    format!("Model '{}' not found", req.model),
-                    "model_not_found".to_string(),
-                )),
-            )
-                .into_response();
-        }
-        Err(e) => {
-            return (
-                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse::new(
-                    format!("Failed to check model: {}", e),
-                    "internal_error".to_string(),
-                )),
-            )
-                .into_response();
-        }
-        Ok(Some(_)) => {
-            // Model exists, continue
-        }
+    let resolved = match resolve_model(&state, &req.model).await {
+        Ok(resolved) => resolved,
+        Err(e) => return e.into_response(),
+    };
+
+    // Make sure the prompt and completion fit in the context window
+    let mut max_tokens = req.max_tokens.unwrap_or(100);
+    if let Err(e) = fit_chat_messages(
+        engine.as_ref(),
+        &resolved.base,
+        &mut req.messages,
+        &mut max_tokens,
+        req.truncation_strategy,
+    )
+    .await
+    {
+        return e.into_response();
     }
 
+    // Decode image inputs for vision models
+    let images = match load_request_images(
+        state.image_dir.as_deref(),
+        &resolved.base,
+        &req.model,
+        &req.messages,
+    ) {
+        Ok(images) => images,
+        Err(e) => return e.into_response(),
+    };
+
+    // Image embeddings are not part of the prompt text, so their KV state is not cached
+    let revision = images
+        .is_empty()
+        .then(|| resolved.base.metadata.cache.revision.clone());
+    let gen_req = GenerateRequest::new(
+        &resolved.base.name,
+        &format_chat_messages(&req.messages),
+        max_tokens,
+        req.temperature.unwrap_or(0.7),
+    )
+    .with_adapter(resolved.adapter.map(|a| a.name))
+    .with_revision(revision)
+    .with_images(images)
+    .with_thinking_budget(thinking_budget(&req));
+
     if req.stream {
-        chat_completions_stream(engine, req).await.into_response()
+        chat_completions_stream(engine, req, gen_req, timer)
+            .await
+            .into_response()
     } else {
-        match chat_completions_non_stream(engine, req).await {
+        match chat_completions_non_stream(engine, req, gen_req, timer).await {
             Ok(response) => Json(response).into_response(),
-            Err(err) => (
-                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse::new(
-                    err.to_string(),
-                    "internal_error".to_string(),
-                )),
-            )
-                .into_response(),
+            Err(err) => err.into_response(),
         }
     }
 }
@@ -86,22 +107,38 @@ pub async fn chat_completions<E: InferenceEngine + 'static>(
 async fn chat_completions_non_stream<E: InferenceEngine>(
     engine: Arc<E>,
     req: ChatCompletionRequest,
-) -> Result<ChatCompletionResponse, Box<dyn std::error::Error>> {
+    gen_req: GenerateRequest,
+    mut timer: RequestTimer,
+) -> Result<ChatCompletionResponse, ApiError> {
     let id = format!("chatcmpl-{}", Uuid::new_v4());
     let created = chrono::Utc::now().timestamp();
 
-    // Convert messages to prompt
-    let prompt = format_chat_messages(&req.messages);
-
     // Generate
-    let response = engine
-        .generate(
-            &req.model,
-            &prompt,
-            req.max_tokens.unwrap_or(100),
-            req.temperature.unwrap_or(0.7),
-        )
-        .await?;
+    timer.generation_started();
+    let response = engine.generate(&gen_req).await?;
+    let timings = timer.generate_timings(
+        response.prompt_tokens,
+        response.completion_tokens,
+        response.prompt_duration,
+        response.decode_duration,
+    );
+    timer.finish(
+        &timings,
+        "/v1/chat/completions",
+        &req.model,
+        response.prompt_tokens,
+        response.completion_tokens,
+        "stop",
+    );
+
+    // Split the reasoning of thinking models out of the answer
+    let (reasoning_content, content) = split_reasoning(&response.text);
+    let completion_tokens_details = match &reasoning_content {
+        Some(reasoning) => Some(CompletionTokensDetails {
+            reasoning_tokens: engine.tokenize(&gen_req.model, reasoning).await?.len(),
+        }),
+        None => None,
+    };
 
     Ok(ChatCompletionResponse {
         id,
@@ -112,7 +149,8 @@ async fn chat_completions_non_stream<E: InferenceEngine>(
             index: 0,
             message: ChatMessage {
                 role: "assistant".to_string(),
-                content: response.text,
+                content: content.into(),
+                reasoning_content,
             },
             finish_reason: "stop".to_string(),
         }],
@@ -120,6 +158,9 @@ async fn chat_completions_non_stream<E: InferenceEngine>(
             prompt_tokens: response.prompt_tokens,
             completion_tokens: response.completion_tokens,
             total_tokens: response.prompt_tokens + response.completion_tokens,
+            prompt_tokens_details: PromptTokensDetails::cached(response.cached_tokens),
+            completion_tokens_details,
+            timings: Some(timings),
         },
     })
 }
@@ -128,17 +169,18 @@ async fn chat_completions_non_stream<E: InferenceEngine>(
 async fn chat_completions_stream<E: InferenceEngine + 'static>(
     engine: Arc<E>,
     req: ChatCompletionRequest,
+    gen_req: GenerateRequest,
+    mut timer: RequestTimer,
 ) -> Sse<impl futures::Stream<Item = Result<Event, std::convert::Infallible>>> {
     let id = format!("chatcmpl-{}", Uuid::new_v4());
     let created = chrono::Utc::now().timestamp();
     let model = req.model.clone();
+    let include_usage = req.stream_options.is_some_and(|o| o.include_usage);
 
     let (tx, rx) = tokio::sync::mpsc::channel(100);
 
     // Spawn task to generate tokens
     tokio::spawn(async move {
-        let prompt = format_chat_messages(&req.messages);
-
         // Send initial chunk with role
         let initial_chunk = ChatCompletionChunk {
             id: id.clone(),
@@ -150,9 +192,11 @@ async fn chat_completions_stream<E: InferenceEngine + 'static>(
                 delta: ChatMessageDelta {
                     role: Some("assistant".to_string()),
                     content: None,
+                    reasoning_content: None,
                 },
                 finish_reason: None,
             }],
+            usage: None,
         };
 
         if tx
@@ -166,49 +210,88 @@ async fn chat_completions_stream<E: InferenceEngine + 'static>(
         }
 
         // Stream tokens
-        match engine
-            .generate_stream(
-                &model,
-                &prompt,
-                req.max_tokens.unwrap_or(100),
-                req.temperature.unwrap_or(0.7),
-            )
-            .await
-        {
-            Ok(mut stream) => {
-                while let Some(token) = stream.next().await {
-                    let chunk = ChatCompletionChunk {
-                        id: id.clone(),
-                        object: "chat.completion.chunk".to_string(),
-                        created,
-                        model: model.clone(),
-                        choices: vec![ChatChoiceDelta {
-                            index: 0,
-                            delta: ChatMessageDelta {
-                                role: None,
-                                content: Some(token),
-                            },
-                            finish_reason: None,
-                        }],
-                    };
-
-                    if tx
-                        .send(Ok(
-                            Event::default().data(serde_json::to_string(&chunk).unwrap())
-                        ))
-                        .await
-                        .is_err()
-                    {
-                        break;
-                    }
-                }
-            }
+        timer.generation_started();
+        let mut completion_tokens = 0;
+        let mut reasoning = String::new();
+        let mut stream = match engine.generate_stream(&gen_req).await {
+            Ok(stream) => stream,
             Err(e) => {
+                // The status is already sent, the error goes in the stream as OpenAI does
                 tracing::error!("Error generating stream: {}", e);
+                let error = ErrorResponse::from(ApiError::from(e));
+                if tx
+                    .send(Ok(
+                        Event::default().data(serde_json::to_string(&error).unwrap())
+                    ))
+                    .await
+                    .is_ok()
+                {
+                    let _ = tx.send(Ok(Event::default().data("[DONE]"))).await;
+                }
+                return;
+            }
+        };
+        // The engine counts image tokens and the prompt cache, unlike the tokenizer
+        let prompt_tokens = stream.prompt_tokens;
+        let cached_tokens = stream.cached_tokens;
+        let mut parser = ReasoningParser::new();
+        let mut finished = false;
+        while !finished {
+            // Split reasoning out of each token, flushing the parser at the end
+            let delta = match stream.tokens.next().await {
+                Some(token) => {
+                    timer.first_token();
+                    completion_tokens += 1;
+                    parser.push(&token)
+                }
+                None => {
+                    finished = true;
+                    parser.finish()
+                }
+            };
+            if delta.reasoning.is_empty() && delta.content.is_empty() {
+                continue;
+            }
+            reasoning.push_str(&delta.reasoning);
+
+            let chunk = ChatCompletionChunk {
+                id: id.clone(),
+                object: "chat.completion.chunk".to_string(),
+                created,
+                model: model.clone(),
+                choices: vec![ChatChoiceDelta {
+                    index: 0,
+                    delta: ChatMessageDelta {
+                        role: None,
+                        content: (!delta.content.is_empty()).then_some(delta.content),
+                        reasoning_content: (!delta.reasoning.is_empty()).then_some(delta.reasoning),
+                    },
+                    finish_reason: None,
+                }],
+                usage: None,
+            };
+
+            if tx
+                .send(Ok(
+                    Event::default().data(serde_json::to_string(&chunk).unwrap())
+                ))
+                .await
+                .is_err()
+            {
                 return;
             }
         }
 
+        let timings = timer.stream_timings(prompt_tokens, completion_tokens);
+        timer.finish(
+            &timings,
+            "/v1/chat/completions",
+            &model,
+            prompt_tokens,
+            completion_tokens,
+            "stop",
+        );
+
         // Send final chunk
         let final_chunk = ChatCompletionChunk {
             id: id.clone(),
@@ -220,16 +303,62 @@ async fn chat_completions_stream<E: InferenceEngine + 'static>(
                 delta: ChatMessageDelta {
                     role: None,
                     content: None,
+                    reasoning_content: None,
                 },
                 finish_reason: Some("stop".to_string()),
             }],
+            usage: None,
         };
 
-        let _ = tx
+        if tx
             .send(Ok(
                 Event::default().data(serde_json::to_string(&final_chunk).unwrap())
             ))
-            .await;
+            .await
+            .is_err()
+        {
+            return;
+        }
+
+        // Report usage in an extra chunk without choices, as OpenAI does
+        if include_usage {
+            let completion_tokens_details = if reasoning.is_empty() {
+                None
+            } else {
+                engine
+                    .tokenize(&gen_req.model, &reasoning)
+                    .await
+                    .ok()
+                    .map(|tokens| CompletionTokensDetails {
+                        reasoning_tokens: tokens.len(),
+                    })
+            };
+            let usage_chunk = ChatCompletionChunk {
+                id: id.clone(),
+                object: "chat.completion.chunk".to_string(),
+                created,
+                model: model.clone(),
+                choices: vec![],
+                usage: Some(Usage {
+                    prompt_tokens,
+                    completion_tokens,
+                    total_tokens: prompt_tokens + completion_tokens,
+                    prompt_tokens_details: PromptTokensDetails::cached(cached_tokens),
+                    completion_tokens_details,
+                    timings: Some(timings),
+                }),
+            };
+            if tx
+                .send(Ok(
+                    Event::default().data(serde_json::to_string(&usage_chunk).unwrap())
+                ))
+                .await
+                .is_err()
+            {
+                return;
+            }
+        }
+
         let _ = tx.send(Ok(Event::default().data("[DONE]"))).await;
     });
 
@@ -237,16 +366,19 @@ async fn chat_completions_stream<E: InferenceEngine + 'static>(
 }
 
 /// Format chat messages into a prompt
-fn format_chat_messages(messages: &[ChatMessage]) -> String {
+pub fn format_chat_messages(messages: &[ChatMessage]) -> String {
     messages
         .iter()
         .map(|m| {
+            let content = m.content.to_prompt_text();
             if m.role == "system" {
-                format!("System: {}", m.content)
+                format!("System: {}", content)
             } else if m.role == "user" {
-                format!("User: {}", m.content)
+                format!("User: {}", content)
+            } else if m.role == "tool" {
+                format!("Tool: {}", content)
             } else {
-                format!("Assistant: {}", m.content)
+                format!("Assistant: {}", content)
             }
         })
         .collect::<Vec<_>>()
@@ -1,113 +1,175 @@
 use axum::{extract::State, response::IntoResponse, Json};
+use std::path::Path;
 use uuid::Uuid;
 
+use crate::api::context::fit_prompt;
+use crate::api::error::ApiError;
+use crate::api::extract::ApiJson;
+use crate::api::models::resolve_model;
+use crate::api::params::validate_sampling;
 use crate::api::routes::AppState;
+use crate::api::timings::RequestTimer;
 use crate::api::types::{
-    CompletionChoice, CompletionRequest, CompletionResponse, ErrorResponse, Usage,
+    CompletionChoice, CompletionRequest, CompletionResponse, PromptTokensDetails, Usage,
 };
-use crate::backend::InferenceEngine;
+use crate::backend::{GenerateRequest, InferenceEngine};
+
+/// Upper bound on best_of, every candidate is a full generation
+const MAX_BEST_OF: usize = 20;
 
 /// Handler for legacy text completions
 pub async fn completions<E: InferenceEngine + 'static>(
     State(state): State<AppState<E>>,
-    Json(req): Json<CompletionRequest>,
+    mut timer: RequestTimer,
+    ApiJson(req): ApiJson<CompletionRequest>,
 ) -> impl IntoResponse {
-    let engine = state.engine;
-    let registry = state.registry;
+    let engine = state.engine.clone();
 
     // Validate request
     let prompt = req.prompt.to_string();
     if prompt.is_empty() {
-        return (
-            axum::http::StatusCode::BAD_REQUEST,
-            Json(ErrorResponse::new(
-                "prompt cannot be empty".to_string(),
-                "invalid_request_error".to_string(),
-            )),
-        )
+        return ApiError::invalid_request("prompt cannot be empty")
+            .with_param("prompt")
             .into_response();
     }
 
     // TODO: Implement streaming support for /v1/completions
     if req.stream {
-        return (
-            axum::http::StatusCode::BAD_REQUEST,
-            Json(ErrorResponse::new(
-                "Streaming not supported for /v1/completions endpoint".to_string(),
-                "invalid_request_error".to_string(),
-            )),
-        )
+        return ApiError::invalid_request("Streaming not supported for /v1/completions endpoint")
+            .with_param("stream")
             .into_response();
     }
 
+    if let Err(e) = validate_sampling(req.temperature, req.top_p, req.max_tokens, req.n) {
+        return e.into_response();
+    }
+
+    // Validate candidate counts
+    let n = req.n.unwrap_or(1);
+    let best_of = req.best_of.unwrap_or(n);
+    if best_of < n || best_of > MAX_BEST_OF {
+        return ApiError::invalid_value(
+            "best_of",
+            format!("best_of must be between n and {}", MAX_BEST_OF),
+        )
+        .into_response();
+    }
+
     // Validate model exists
-    match registry.get_model(&req.model) {
-        Ok(None) => {
-            return (
-                axum::http::StatusCode::NOT_FOUND,
-                Json(ErrorResponse::new(
-                    format!("Model '{}' not found", req.model),
-                    "model_not_found".to_string(),
-                )),
-            )
-                .into_response();
-        }
-        Err(e) => {
-            return (
-                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse::new(
-                    format!("Failed to check model: {}", e),
-                    "internal_error".to_string(),
-                )),
-            )
+    let resolved = match resolve_model(&state, &req.model).await {
+        Ok(resolved) => resolved,
+        Err(e) => return e.into_response(),
+    };
+
+    // A suffix turns the prompt into a fill-in-the-middle prompt
+    let model_prompt = match &req.suffix {
+        Some(suffix) => match state.fim_tokens.get(
+            Path::new(&resolved.base.metadata.cache.path),
+            &resolved.base.metadata.cache.revision,
+        ) {
+            Some(tokens) => tokens.format(&prompt, suffix),
+            None => {
+                return ApiError::invalid_request(format!(
+                    "Model '{}' has no fill-in-the-middle tokens, suffix is not supported",
+                    req.model
+                ))
+                .with_param("suffix")
                 .into_response();
-        }
-        Ok(Some(_)) => {
-            // Model exists, continue
-        }
-    }
+            }
+        },
+        None => prompt.clone(),
+    };
 
     let id = format!("cmpl-{}", Uuid::new_v4());
     let created = chrono::Utc::now().timestamp();
 
-    // Generate
-    match engine
-        .generate(
-            &req.model,
-            &prompt,
-            req.max_tokens.unwrap_or(100),
-            req.temperature.unwrap_or(0.7),
-        )
-        .await
+    // Make sure the prompt and completion fit in the context window
+    let mut max_tokens = req.max_tokens.unwrap_or(100);
+    if let Err(e) = fit_prompt(
+        engine.as_ref(),
+        &resolved.base,
+        &model_prompt,
+        &mut max_tokens,
+        req.truncation_strategy,
+    )
+    .await
     {
-        Ok(response) => {
-            let completion = CompletionResponse {
-                id,
-                object: "text_completion".to_string(),
-                created,
-                model: req.model,
-                choices: vec![CompletionChoice {
-                    text: response.text,
-                    index: 0,
-                    logprobs: None,
-                    finish_reason: "stop".to_string(),
-                }],
-                usage: Usage {
-                    prompt_tokens: response.prompt_tokens,
-                    completion_tokens: response.completion_tokens,
-                    total_tokens: response.prompt_tokens + response.completion_tokens,
-                },
-            };
-
-            Json(completion).into_response()
+        return e.into_response();
+    }
+
+    let gen_req = GenerateRequest::new(
+        &resolved.base.name,
+        &model_prompt,
+        max_tokens,
+        req.temperature.unwrap_or(0.7),
+    )
+    .with_adapter(resolved.adapter.map(|a| a.name))
+    .with_revision(Some(resolved.base.metadata.cache.revision.clone()));
+
+    // Generate best_of candidates and keep the n most likely ones
+    timer.generation_started();
+    let mut candidates = Vec::with_capacity(best_of);
+    for _ in 0..best_of {
+        match engine.generate(&gen_req).await {
+            Ok(response) => candidates.push(response),
+            Err(e) => return ApiError::from(e).into_response(),
         }
-        Err(e) => (
-            axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse::new(
-                e.to_string(),
-                "internal_error".to_string(),
-            )),
-        )
-            .into_response(),
     }
+
+    // The prompt is shared, every candidate counts towards completion tokens
+    let prompt_tokens = candidates[0].prompt_tokens;
+    let prompt_duration = candidates[0].prompt_duration;
+    let cached_tokens = candidates[0].cached_tokens;
+    let completion_tokens = candidates.iter().map(|c| c.completion_tokens).sum();
+    let decode_duration = candidates.iter().map(|c| c.decode_duration).sum();
+    let timings = timer.generate_timings(
+        prompt_tokens,
+        completion_tokens,
+        prompt_duration,
+        decode_duration,
+    );
+    timer.finish(
+        &timings,
+        "/v1/completions",
+        &req.model,
+        prompt_tokens,
+        completion_tokens,
+        "stop",
+    );
+
+    // Rank by log-probability per token, the sum would favor short candidates
+    candidates.sort_by(|a, b| b.mean_logprob().total_cmp(&a.mean_logprob()));
+    let choices = candidates
+        .into_iter()
+        .take(n)
+        .enumerate()
+        .map(|(index, candidate)| CompletionChoice {
+            text: if req.echo {
+                format!("{}{}", prompt, candidate.text)
+            } else {
+                candidate.text
+            },
+            index,
+            logprobs: None,
+            finish_reason: "stop".to_string(),
+        })
+        .collect();
+
+    let completion = CompletionResponse {
+        id,
+        object: "text_completion".to_string(),
+        created,
+        model: req.model,
+        choices,
+        usage: Usage {
+            prompt_tokens,
+            completion_tokens,
+            total_tokens: prompt_tokens + completion_tokens,
+            prompt_tokens_details: PromptTokensDetails::cached(cached_tokens),
+            completion_tokens_details: None,
+            timings: Some(timings),
+        },
+    };
+
+    Json(completion).into_response()
 }
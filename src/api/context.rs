@@ -0,0 +1,139 @@
+use axum::response::{IntoResponse, Response};
+use std::io;
+
+use crate::api::chat::format_chat_messages;
+use crate::api::error::ApiError;
+use crate::api::types::{ChatMessage, TruncationStrategy};
+use crate::backend::InferenceEngine;
+use crate::registry::model_registry::ModelInfo;
+
+/// Error returned when a request cannot be fit into the context window
+#[derive(Debug)]
+pub enum ContextError {
+    Exceeded {
+        context_window: u32,
+        prompt_tokens: usize,
+        max_tokens: usize,
+    },
+    Tokenizer(io::Error),
+}
+
+impl From<ContextError> for ApiError {
+    fn from(error: ContextError) -> Self {
+        match error {
+            ContextError::Exceeded {
+                context_window,
+                prompt_tokens,
+                max_tokens,
+            } => ApiError::invalid_request(format!(
+                "This model's maximum context length is {} tokens. However, you requested {} tokens ({} in the prompt, {} in the completion). Please reduce the length of the prompt or completion, or set a truncation_strategy.",
+                context_window,
+                prompt_tokens.saturating_add(max_tokens),
+                prompt_tokens,
+                max_tokens
+            ))
+            .with_code("context_length_exceeded"),
+            ContextError::Tokenizer(e) => ApiError::server_error(format!("Failed to tokenize: {}", e)),
+        }
+    }
+}
+
+impl IntoResponse for ContextError {
+    fn into_response(self) -> Response {
+        ApiError::from(self).into_response()
+    }
+}
+
+async fn count_tokens<E: InferenceEngine>(
+    engine: &E,
+    model: &ModelInfo,
+    text: &str,
+) -> Result<usize, ContextError> {
+    engine
+        .tokenize(&model.name, text)
+        .await
+        .map(|tokens| tokens.len())
+        .map_err(ContextError::Tokenizer)
+}
+
+/// Whether the prompt and completion fit in the context window. The sum
+/// saturates, so a huge `max_tokens` is rejected instead of wrapping around.
+fn fits(prompt_tokens: usize, max_tokens: usize, context_window: u32) -> bool {
+    prompt_tokens.saturating_add(max_tokens) <= context_window as usize
+}
+
+/// Make sure chat messages plus max_tokens fit in the model's context window,
+/// dropping messages or clamping max_tokens if the strategy allows it.
+/// Models without a known context window are not checked.
+pub async fn fit_chat_messages<E: InferenceEngine>(
+    engine: &E,
+    model: &ModelInfo,
+    messages: &mut Vec<ChatMessage>,
+    max_tokens: &mut usize,
+    strategy: TruncationStrategy,
+) -> Result<(), ContextError> {
+    let Some(context_window) = model.metadata.context_window else {
+        return Ok(());
+    };
+
+    loop {
+        let prompt_tokens = count_tokens(engine, model, &format_chat_messages(messages)).await?;
+        if fits(prompt_tokens, *max_tokens, context_window) {
+            return Ok(());
+        }
+
+        match strategy {
+            TruncationStrategy::DropOldest => {
+                // System messages and the latest message are always kept
+                let last = messages.len().saturating_sub(1);
+                if let Some(index) = messages[..last].iter().position(|m| m.role != "system") {
+                    messages.remove(index);
+                    continue;
+                }
+            }
+            TruncationStrategy::ClampMaxTokens => {
+                if prompt_tokens < context_window as usize {
+                    *max_tokens = context_window as usize - prompt_tokens;
+                    return Ok(());
+                }
+            }
+            TruncationStrategy::Disabled => {}
+        }
+
+        return Err(ContextError::Exceeded {
+            context_window,
+            prompt_tokens,
+            max_tokens: *max_tokens,
+        });
+    }
+}
+
+/// Make sure a raw prompt plus max_tokens fits in the model's context window.
+/// Only `clamp_max_tokens` can make an oversized prompt fit.
+pub async fn fit_prompt<E: InferenceEngine>(
+    engine: &E,
+    model: &ModelInfo,
+    prompt: &str,
+    max_tokens: &mut usize,
+    strategy: TruncationStrategy,
+) -> Result<(), ContextError> {
+    let Some(context_window) = model.metadata.context_window else {
+        return Ok(());
+    };
+
+    let prompt_tokens = count_tokens(engine, model, prompt).await?;
+    if fits(prompt_tokens, *max_tokens, context_window) {
+        return Ok(());
+    }
+
+    if strategy == TruncationStrategy::ClampMaxTokens && prompt_tokens < context_window as usize {
+        *max_tokens = context_window as usize - prompt_tokens;
+        return Ok(());
+    }
+
+    Err(ContextError::Exceeded {
+        context_window,
+        prompt_tokens,
+        max_tokens: *max_tokens,
+    })
+}
@@ -0,0 +1,175 @@
+use axum::http::{HeaderName, HeaderValue, Method};
+use std::time::Duration;
+use tower_http::cors::{AllowHeaders, AllowMethods, AllowOrigin, CorsLayer};
+
+/// Methods allowed in cross-origin requests unless configured
+pub const DEFAULT_METHODS: &[&str] = &["GET", "POST", "DELETE"];
+
+/// Headers allowed in cross-origin requests unless configured
+pub const DEFAULT_HEADERS: &[&str] = &[
+    "authorization",
+    "content-type",
+    "x-api-key",
+    "anthropic-version",
+];
+
+/// Which web pages may call the API from a browser. Without origins no
+/// cross-origin request is allowed, so pages the user visits cannot drive
+/// the local server.
+#[derive(Debug, Clone)]
+pub struct CorsOptions {
+    /// Allowed origins (e.g. `http://localhost:3000`), `*` allows any
+    pub origins: Vec<String>,
+    /// Allowed methods, `*` allows any
+    pub methods: Vec<String>,
+    /// Allowed request headers, `*` allows any
+    pub headers: Vec<String>,
+    /// Allow cookies and HTTP authentication, requires explicit origins
+    pub credentials: bool,
+    /// Time browsers may cache preflight responses
+    pub max_age: Option<Duration>,
+}
+
+impl Default for CorsOptions {
+    fn default() -> Self {
+        Self {
+            origins: Vec::new(),
+            methods: DEFAULT_METHODS.iter().map(|m| m.to_string()).collect(),
+            headers: DEFAULT_HEADERS.iter().map(|h| h.to_string()).collect(),
+            credentials: false,
+            max_age: None,
+        }
+    }
+}
+
+impl CorsOptions {
+    /// Build the CORS layer, failing on invalid values
+    pub fn layer(&self) -> Result<CorsLayer, String> {
+        let wildcard = |values: &[String]| values.iter().any(|value| value == "*");
+        if self.credentials {
+            if wildcard(&self.origins) {
+                return Err("CORS credentials cannot be allowed for any origin ('*')".into());
+            }
+            if wildcard(&self.methods) || wildcard(&self.headers) {
+                return Err("CORS credentials require explicit methods and headers".into());
+            }
+        }
+
+        let origins = if wildcard(&self.origins) {
+            AllowOrigin::any()
+        } else {
+            AllowOrigin::list(
+                self.origins
+                    .iter()
+                    .map(|origin| parse_origin(origin))
+                    .collect::<Result<Vec<_>, _>>()?,
+            )
+        };
+        let methods = if wildcard(&self.methods) {
+            AllowMethods::any()
+        } else {
+            AllowMethods::list(
+                self.methods
+                    .iter()
+                    .map(|method| {
+                        Method::from_bytes(method.to_uppercase().as_bytes())
+                            .map_err(|_| format!("Invalid CORS method '{}'", method))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?,
+            )
+        };
+        let headers = if wildcard(&self.headers) {
+            AllowHeaders::any()
+        } else {
+            AllowHeaders::list(
+                self.headers
+                    .iter()
+                    .map(|header| {
+                        HeaderName::try_from(header.as_str())
+                            .map_err(|_| format!("Invalid CORS header '{}'", header))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?,
+            )
+        };
+
+        let mut layer = CorsLayer::new()
+            .allow_origin(origins)
+            .allow_methods(methods)
+            .allow_headers(headers)
+            .allow_credentials(self.credentials);
+        if let Some(max_age) = self.max_age {
+            layer = layer.max_age(max_age);
+        }
+        Ok(layer)
+    }
+}
+
+/// Origins are compared as sent by browsers: scheme, host and optional port,
+/// without a path
+fn parse_origin(origin: &str) -> Result<HeaderValue, String> {
+    let invalid = || {
+        format!(
+            "Invalid CORS origin '{}', expected e.g. http://localhost:3000",
+            origin
+        )
+    };
+    match origin.split_once("://") {
+        Some((scheme, host)) if !scheme.is_empty() && !host.is_empty() && !host.contains('/') => {
+            HeaderValue::from_str(origin).map_err(|_| invalid())
+        }
+        _ => Err(invalid()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(origins: &[&str]) -> CorsOptions {
+        CorsOptions {
+            origins: origins.iter().map(|o| o.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_parse_origin() {
+        assert!(parse_origin("http://localhost:3000").is_ok());
+        assert!(parse_origin("https://app.example.com").is_ok());
+        assert!(parse_origin("http://localhost:3000/").is_err());
+        assert!(parse_origin("localhost:3000").is_err());
+        assert!(parse_origin("https://").is_err());
+    }
+
+    #[test]
+    fn test_cors_layer_validation() {
+        assert!(CorsOptions::default().layer().is_ok());
+        assert!(options(&["*"]).layer().is_ok());
+        assert!(options(&["http://localhost:3000"]).layer().is_ok());
+        assert!(options(&["localhost"]).layer().is_err());
+
+        let invalid_method = CorsOptions {
+            methods: vec!["GET POST".to_string()],
+            ..options(&["*"])
+        };
+        assert!(invalid_method.layer().is_err());
+
+        // Browsers refuse credentials with wildcards, so does the layer
+        let credentials = CorsOptions {
+            credentials: true,
+            ..options(&["*"])
+        };
+        assert!(credentials.layer().is_err());
+        let credentials = CorsOptions {
+            credentials: true,
+            headers: vec!["*".to_string()],
+            ..options(&["http://localhost:3000"])
+        };
+        assert!(credentials.layer().is_err());
+        let credentials = CorsOptions {
+            credentials: true,
+            ..options(&["http://localhost:3000"])
+        };
+        assert!(credentials.layer().is_ok());
+    }
+}
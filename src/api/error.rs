@@ -0,0 +1,166 @@
+use axum::{
+    http::{Method, StatusCode, Uri},
+    response::{IntoResponse, Response},
+    Json,
+};
+
+use crate::api::types::{ErrorDetail, ErrorResponse};
+
+/// An API error with OpenAI error semantics. SDK clients pick the exception
+/// to raise from the status, and report `type`, `code` and `param`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApiError {
+    pub status: StatusCode,
+    pub message: String,
+    pub error_type: &'static str,
+    pub code: Option<String>,
+    /// The request parameter the error is about
+    pub param: Option<String>,
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode, error_type: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+            error_type,
+            code: None,
+            param: None,
+        }
+    }
+
+    /// 400, the request is malformed or invalid
+    pub fn invalid_request(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, "invalid_request_error", message)
+    }
+
+    /// 400, a parameter has a value out of its valid range
+    pub fn invalid_value(param: &str, message: impl Into<String>) -> Self {
+        Self::invalid_request(message)
+            .with_code("invalid_value")
+            .with_param(param)
+    }
+
+    /// 401, the API key is missing or invalid
+    pub fn unauthorized(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::UNAUTHORIZED, "invalid_request_error", message)
+    }
+
+    /// 404, the requested resource does not exist
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::NOT_FOUND, "invalid_request_error", message)
+    }
+
+    /// 404, the requested model is not in the registry
+    pub fn model_not_found(model: &str) -> Self {
+        Self::not_found(format!("Model '{}' not found", model))
+            .with_code("model_not_found")
+            .with_param("model")
+    }
+
+    /// 500, the server failed to handle a valid request
+    pub fn server_error(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, "server_error", message)
+    }
+
+    /// 503, the server cannot handle requests yet
+    pub fn service_unavailable(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::SERVICE_UNAVAILABLE, "server_error", message)
+    }
+
+    pub fn with_code(mut self, code: &str) -> Self {
+        self.code = Some(code.to_string());
+        self
+    }
+
+    pub fn with_param(mut self, param: &str) -> Self {
+        self.param = Some(param.to_string());
+        self
+    }
+}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+/// Engine failures caused by the request, such as an image the engine can't
+/// decode or an adapter that isn't loaded, are client errors. Anything else
+/// is a server error.
+impl From<std::io::Error> for ApiError {
+    fn from(error: std::io::Error) -> Self {
+        match error.kind() {
+            std::io::ErrorKind::InvalidInput => Self::invalid_request(error.to_string()),
+            std::io::ErrorKind::NotFound => Self::not_found(error.to_string()),
+            _ => Self::server_error(error.to_string()),
+        }
+    }
+}
+
+/// The error body, also sent as the last event of a failed stream
+impl From<ApiError> for ErrorResponse {
+    fn from(error: ApiError) -> Self {
+        ErrorResponse {
+            error: ErrorDetail {
+                message: error.message,
+                r#type: error.error_type.to_string(),
+                param: error.param,
+                code: error.code,
+            },
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.status, Json(ErrorResponse::from(self))).into_response()
+    }
+}
+
+/// Fallback for unknown routes, so they also answer with an error body
+pub async fn unknown_route(method: Method, uri: Uri) -> ApiError {
+    ApiError::not_found(format!("Invalid URL ({} {})", method, uri.path()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_model_not_found() {
+        let error = ApiError::model_not_found("gpt-5");
+        assert_eq!(error.status, StatusCode::NOT_FOUND);
+        assert_eq!(error.error_type, "invalid_request_error");
+        assert_eq!(error.code.as_deref(), Some("model_not_found"));
+        assert_eq!(error.param.as_deref(), Some("model"));
+    }
+
+    #[test]
+    fn test_io_error_is_server_error() {
+        let error = ApiError::from(std::io::Error::other("engine crashed"));
+        assert_eq!(error.status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(error.error_type, "server_error");
+        assert_eq!(error.message, "engine crashed");
+    }
+
+    #[test]
+    fn test_io_error_caused_by_request() {
+        let error = ApiError::from(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "Cannot encode image of type 'text/plain'",
+        ));
+        assert_eq!(error.status, StatusCode::BAD_REQUEST);
+        assert_eq!(error.error_type, "invalid_request_error");
+
+        let error = ApiError::from(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "Adapter 'sql-lora' is not loaded",
+        ));
+        assert_eq!(error.status, StatusCode::NOT_FOUND);
+        assert_eq!(error.error_type, "invalid_request_error");
+        assert_eq!(error.message, "Adapter 'sql-lora' is not loaded");
+    }
+}
@@ -0,0 +1,160 @@
+use axum::{
+    async_trait,
+    body::Bytes,
+    extract::{FromRequest, Request},
+    http::{header, HeaderMap, StatusCode},
+};
+use serde::de::DeserializeOwned;
+
+use crate::api::error::ApiError;
+
+/// JSON body extractor that rejects malformed bodies with an `ApiError`,
+/// naming the offending parameter. Bodies must be sent as JSON: browsers send
+/// other content types cross-origin without a CORS preflight.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ApiJson<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequest<S> for ApiJson<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        if !json_content_type(req.headers()) {
+            return Err(ApiError::new(
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                "invalid_request_error",
+                "Expected request with `Content-Type: application/json`",
+            ));
+        }
+        let bytes = Bytes::from_request(req, state)
+            .await
+            .map_err(|e| ApiError::new(e.status(), "invalid_request_error", e.body_text()))?;
+        parse_json(&bytes).map(ApiJson)
+    }
+}
+
+/// Whether the request declares a JSON body, `application/json` or `+json`
+fn json_content_type(headers: &HeaderMap) -> bool {
+    let Some(content_type) = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+    else {
+        return false;
+    };
+    let essence = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    match essence.split_once('/') {
+        Some(("application", subtype)) => subtype == "json" || subtype.ends_with("+json"),
+        _ => false,
+    }
+}
+
+/// Deserialize a request body, tracking the path of the value that failed
+pub fn parse_json<T: DeserializeOwned>(body: &[u8]) -> Result<T, ApiError> {
+    let deserializer = &mut serde_json::Deserializer::from_slice(body);
+    serde_path_to_error::deserialize(deserializer).map_err(|e| {
+        let path = e.path().to_string();
+        let inner = e.into_inner();
+        if !inner.is_data() {
+            return ApiError::invalid_request(format!(
+                "We could not parse the JSON body of your request: {}",
+                inner
+            ));
+        }
+
+        // Missing fields are reported on their parent, name the field itself
+        let message = inner.to_string();
+        let missing = missing_field(&message);
+        let param = match (path.as_str(), missing) {
+            (".", None) => None,
+            (".", Some(field)) => Some(field.to_string()),
+            (path, None) => Some(path.to_string()),
+            (path, Some(field)) => Some(format!("{}.{}", path, field)),
+        };
+
+        let error = ApiError::invalid_request(format!("Invalid request body: {}", message));
+        match param {
+            Some(param) => error.with_param(&param),
+            None => error,
+        }
+    })
+}
+
+fn missing_field(message: &str) -> Option<&str> {
+    message.strip_prefix("missing field `")?.split('`').next()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct Message {
+        role: String,
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct Body {
+        model: String,
+        #[serde(default)]
+        messages: Vec<Message>,
+        #[serde(default)]
+        max_tokens: Option<usize>,
+    }
+
+    fn headers(content_type: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::CONTENT_TYPE, content_type.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_json_content_type() {
+        assert!(json_content_type(&headers("application/json")));
+        assert!(json_content_type(&headers(
+            "application/json; charset=utf-8"
+        )));
+        assert!(json_content_type(&headers("Application/JSON")));
+        assert!(json_content_type(&headers("application/vnd.api+json")));
+        // Sent cross-origin by browsers without a preflight
+        assert!(!json_content_type(&headers("text/plain")));
+        assert!(!json_content_type(&headers(
+            "application/x-www-form-urlencoded"
+        )));
+        assert!(!json_content_type(&headers("multipart/form-data")));
+        assert!(!json_content_type(&HeaderMap::new()));
+    }
+
+    #[test]
+    fn test_parse_json_syntax_error() {
+        let error = parse_json::<Body>(b"{\"model\": ").unwrap_err();
+        assert_eq!(error.error_type, "invalid_request_error");
+        assert!(error
+            .message
+            .starts_with("We could not parse the JSON body"));
+        assert!(error.param.is_none());
+    }
+
+    #[test]
+    fn test_parse_json_param() {
+        let error = parse_json::<Body>(b"{}").unwrap_err();
+        assert_eq!(error.param.as_deref(), Some("model"));
+
+        let error = parse_json::<Body>(b"{\"model\": \"m\", \"max_tokens\": -1}").unwrap_err();
+        assert_eq!(error.param.as_deref(), Some("max_tokens"));
+
+        let error = parse_json::<Body>(b"{\"model\": \"m\", \"messages\": [{}]}").unwrap_err();
+        assert_eq!(error.param.as_deref(), Some("messages[0].role"));
+    }
+}
@@ -0,0 +1,257 @@
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::RwLock;
+use tracing::{error, info};
+
+use crate::api::routes::AppState;
+use crate::backend::{GenerateRequest, InferenceEngine};
+use crate::registry::model_registry::ModelInfo;
+
+/// Phase of model loading reported by the readiness probe
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReadinessPhase {
+    Starting,
+    Loading,
+    WarmingUp,
+    Ready,
+    Failed,
+    /// Shutting down, in-flight requests finish but no new ones are served
+    Draining,
+}
+
+/// Readiness status of the served model
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReadinessStatus {
+    pub status: ReadinessPhase,
+    /// Load progress in [0, 1]
+    pub progress: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Shared readiness state, updated while the model loads and warms up
+#[derive(Debug)]
+pub struct Readiness {
+    status: RwLock<ReadinessStatus>,
+}
+
+impl Readiness {
+    /// Readiness of a server that still has to load its model
+    pub fn new() -> Self {
+        Self {
+            status: RwLock::new(ReadinessStatus {
+                status: ReadinessPhase::Starting,
+                progress: 0.0,
+                model: None,
+                error: None,
+            }),
+        }
+    }
+
+    /// Readiness of a server with nothing to load
+    pub fn ready() -> Self {
+        let readiness = Self::new();
+        readiness.set_phase(ReadinessPhase::Ready, 1.0);
+        readiness
+    }
+
+    pub fn status(&self) -> ReadinessStatus {
+        self.status.read().unwrap().clone()
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.status.read().unwrap().status == ReadinessPhase::Ready
+    }
+
+    /// Whether `model` is the served model, names are matched ignoring case
+    /// as in the registry
+    pub fn is_serving(&self, model: &str) -> bool {
+        self.status
+            .read()
+            .unwrap()
+            .model
+            .as_deref()
+            .is_some_and(|served| served.eq_ignore_ascii_case(model))
+    }
+
+    pub fn set_model(&self, model: &str) {
+        self.status.write().unwrap().model = Some(model.to_string());
+    }
+
+    pub fn set_phase(&self, phase: ReadinessPhase, progress: f32) {
+        let mut status = self.status.write().unwrap();
+        // A model finishing its warmup doesn't undo a shutdown
+        if status.status == ReadinessPhase::Draining {
+            return;
+        }
+        status.status = phase;
+        status.progress = progress.clamp(0.0, 1.0);
+    }
+
+    /// Stop serving new requests, for shutdown
+    pub fn drain(&self) {
+        self.status.write().unwrap().status = ReadinessPhase::Draining;
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.status.read().unwrap().status == ReadinessPhase::Draining
+    }
+
+    pub fn fail(&self, message: String) {
+        let mut status = self.status.write().unwrap();
+        status.status = ReadinessPhase::Failed;
+        status.error = Some(message);
+    }
+}
+
+impl Default for Readiness {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Load the model and run one short generation so the first real request
+/// doesn't pay for lazy initialization. Progress and failures are recorded
+/// in `readiness`.
+pub async fn warm_up<E: InferenceEngine>(engine: &E, model: &ModelInfo, readiness: &Readiness) {
+    readiness.set_model(&model.name);
+    readiness.set_phase(ReadinessPhase::Loading, 0.0);
+    info!("Loading model {}", model.name);
+
+    // Loading accounts for most of the progress, warmup for the rest
+    let progress = |fraction: f32| readiness.set_phase(ReadinessPhase::Loading, fraction * 0.9);
+    if let Err(e) = engine
+        .load_model(&model.name, &model.metadata.cache.path, &progress)
+        .await
+    {
+        error!("Failed to load model {}: {}", model.name, e);
+        readiness.fail(format!("Failed to load model: {}", e));
+        return;
+    }
+
+    readiness.set_phase(ReadinessPhase::WarmingUp, 0.9);
+    info!("Warming up model {}", model.name);
+    if let Err(e) = engine
+        .generate(&GenerateRequest::new(&model.name, "Hello", 1, 0.0))
+        .await
+    {
+        error!("Failed to warm up model {}: {}", model.name, e);
+        readiness.fail(format!("Failed to warm up model: {}", e));
+        return;
+    }
+
+    readiness.set_phase(ReadinessPhase::Ready, 1.0);
+    info!("Model {} is ready", model.name);
+}
+
+/// Health check response
+#[derive(Serialize)]
+pub struct HealthResponse {
+    status: String,
+}
+
+/// Health check endpoint
+pub async fn health_check() -> Json<HealthResponse> {
+    Json(HealthResponse {
+        status: "ok".to_string(),
+    })
+}
+
+/// Liveness probe, ok as long as the server is up
+pub async fn live() -> Json<HealthResponse> {
+    health_check().await
+}
+
+/// Readiness probe, 503 until the model is loaded and warmed up
+pub async fn ready<E: InferenceEngine + 'static>(State(state): State<AppState<E>>) -> Response {
+    let status = state.readiness.status();
+    let code = if status.status == ReadinessPhase::Ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    (code, Json(status)).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::mock::MockEngine;
+    use crate::registry::model_registry::{CacheInfo, ModelMetadata};
+    use tempfile::TempDir;
+
+    fn create_test_model(name: &str, path: &str) -> ModelInfo {
+        ModelInfo {
+            uuid: "abc123".to_string(),
+            name: name.to_string(),
+            provider: "huggingface".to_string(),
+            author: None,
+            task: Some("text-generation".to_string()),
+            model_series: Some("gpt2".to_string()),
+            license: None,
+            created_at: "2025-01-01T00:00:00Z".to_string(),
+            updated_at: "2025-01-01T00:00:00Z".to_string(),
+            metadata: ModelMetadata {
+                cache: CacheInfo {
+                    revision: "abc123".to_string(),
+                    size: 1000,
+                    path: path.to_string(),
+                },
+                context_window: Some(2048),
+                safetensors: None,
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn test_warm_up_ready() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::write(temp_dir.path().join("model.safetensors"), "weights").unwrap();
+        let model = create_test_model("test/model", temp_dir.path().to_str().unwrap());
+
+        let readiness = Readiness::new();
+        assert!(!readiness.is_ready());
+
+        warm_up(&MockEngine::new(), &model, &readiness).await;
+
+        let status = readiness.status();
+        assert_eq!(status.status, ReadinessPhase::Ready);
+        assert_eq!(status.progress, 1.0);
+        assert_eq!(status.model.as_deref(), Some("test/model"));
+    }
+
+    #[tokio::test]
+    async fn test_warm_up_failure() {
+        let model = create_test_model("test/model", "/nonexistent/model/path");
+
+        let readiness = Readiness::new();
+        warm_up(&MockEngine::new(), &model, &readiness).await;
+
+        let status = readiness.status();
+        assert_eq!(status.status, ReadinessPhase::Failed);
+        assert!(status.error.unwrap().contains("Failed to load model"));
+    }
+
+    #[tokio::test]
+    async fn test_drain_outlasts_warm_up() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::write(temp_dir.path().join("model.safetensors"), "weights").unwrap();
+        let model = create_test_model("test/model", temp_dir.path().to_str().unwrap());
+
+        let readiness = Readiness::new();
+        readiness.drain();
+        warm_up(&MockEngine::new(), &model, &readiness).await;
+
+        assert!(readiness.is_draining());
+        assert!(!readiness.is_ready());
+    }
+}
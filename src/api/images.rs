@@ -0,0 +1,216 @@
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use crate::api::error::ApiError;
+use crate::api::types::ChatMessage;
+use crate::backend::vision::{sniff_mime_type, supports_vision, ImageInput};
+use crate::registry::model_registry::ModelInfo;
+
+/// Maximum size of a single decoded image
+const MAX_IMAGE_BYTES: usize = 20 * 1024 * 1024;
+
+/// Body size limit of requests that may carry images: an image at the size
+/// cap, base64-encoded, and room for the rest of the request
+pub const MAX_IMAGE_REQUEST_BYTES: usize = MAX_IMAGE_BYTES / 3 * 4 + 4 * 1024 * 1024;
+
+/// Load an image from a base64 `data:` URL, or a local file path under
+/// `image_dir`. Local paths are refused when no image directory is set.
+pub fn load_image(url: &str, image_dir: Option<&Path>) -> Result<ImageInput, String> {
+    let data = if let Some(rest) = url.strip_prefix("data:") {
+        let (header, payload) = rest
+            .split_once(',')
+            .ok_or_else(|| "Invalid data URL: missing ','".to_string())?;
+        if !header.ends_with(";base64") {
+            return Err("Only base64-encoded data URLs are supported".to_string());
+        }
+        if payload.len() / 4 * 3 > MAX_IMAGE_BYTES + 3 {
+            return Err(too_large());
+        }
+        STANDARD
+            .decode(payload.trim())
+            .map_err(|e| format!("Invalid base64 image data: {}", e))?
+    } else if url.starts_with("http://") || url.starts_with("https://") {
+        return Err(
+            "Remote image URLs are not supported, use a data: URL or a local file path".to_string(),
+        );
+    } else {
+        let path = url.strip_prefix("file://").unwrap_or(url);
+        read_local_image(Path::new(path), image_dir)?
+    };
+
+    if data.len() > MAX_IMAGE_BYTES {
+        return Err(too_large());
+    }
+
+    let mime_type = sniff_mime_type(&data)
+        .ok_or_else(|| "Unsupported image format, expected PNG, JPEG, GIF or WebP".to_string())?;
+
+    Ok(ImageInput {
+        data,
+        mime_type: mime_type.to_string(),
+    })
+}
+
+/// Read an image file inside `image_dir`, relative paths resolve against it.
+/// Errors don't tell clients whether a path exists outside the directory.
+fn read_local_image(path: &Path, image_dir: Option<&Path>) -> Result<Vec<u8>, String> {
+    let image_dir = image_dir.ok_or_else(|| {
+        "Local image paths are not allowed on this server, use a data: URL".to_string()
+    })?;
+    let unreadable = || "Image file not found in the image directory".to_string();
+
+    // Resolve symlinks and `..` before checking the file is in the directory
+    let image_dir = image_dir.canonicalize().map_err(|_| unreadable())?;
+    let path = image_dir
+        .join(path)
+        .canonicalize()
+        .map_err(|_| unreadable())?;
+    if !path.starts_with(&image_dir) {
+        return Err(unreadable());
+    }
+
+    let file = File::open(&path).map_err(|_| unreadable())?;
+    let metadata = file.metadata().map_err(|_| unreadable())?;
+    if !metadata.is_file() {
+        return Err(unreadable());
+    }
+    if metadata.len() > MAX_IMAGE_BYTES as u64 {
+        return Err(too_large());
+    }
+
+    // The file may grow after the size check, never read more than the cap
+    let mut data = Vec::with_capacity(metadata.len() as usize);
+    file.take(MAX_IMAGE_BYTES as u64 + 1)
+        .read_to_end(&mut data)
+        .map_err(|_| unreadable())?;
+    Ok(data)
+}
+
+fn too_large() -> String {
+    format!("Image is too large (max {} bytes)", MAX_IMAGE_BYTES)
+}
+
+/// Load all images referenced by chat messages, in prompt order
+pub fn load_message_images(
+    messages: &[ChatMessage],
+    image_dir: Option<&Path>,
+) -> Result<Vec<ImageInput>, String> {
+    messages
+        .iter()
+        .flat_map(|m| m.content.image_urls())
+        .map(|url| load_image(url, image_dir))
+        .collect()
+}
+
+/// Load the images of a request, rejecting them if the model has no vision encoder
+pub fn load_request_images(
+    image_dir: Option<&Path>,
+    model: &ModelInfo,
+    model_name: &str,
+    messages: &[ChatMessage],
+) -> Result<Vec<ImageInput>, ApiError> {
+    let has_images = messages.iter().any(|m| !m.content.image_urls().is_empty());
+    if has_images
+        && !(model.task.as_deref() == Some("image-text-to-text")
+            && supports_vision(model.model_series.as_deref()))
+    {
+        return Err(ApiError::invalid_request(format!(
+            "Model '{}' does not support image inputs",
+            model_name
+        ))
+        .with_code("model_not_supported")
+        .with_param("messages"));
+    }
+
+    load_message_images(messages, image_dir)
+        .map_err(|message| ApiError::invalid_request(message).with_param("messages"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PNG_HEADER: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+
+    #[test]
+    fn test_load_image_data_url() {
+        let url = format!("data:image/png;base64,{}", STANDARD.encode(PNG_HEADER));
+        let image = load_image(&url, None).unwrap();
+        assert_eq!(image.mime_type, "image/png");
+        assert_eq!(image.data, PNG_HEADER);
+    }
+
+    #[test]
+    fn test_load_image_file_path() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("image.png");
+        std::fs::write(&path, PNG_HEADER).unwrap();
+        let image_dir = Some(temp_dir.path());
+
+        let image = load_image(path.to_str().unwrap(), image_dir).unwrap();
+        assert_eq!(image.mime_type, "image/png");
+
+        let image = load_image(&format!("file://{}", path.display()), image_dir).unwrap();
+        assert_eq!(image.data, PNG_HEADER);
+
+        // Relative to the image directory
+        let image = load_image("image.png", image_dir).unwrap();
+        assert_eq!(image.data, PNG_HEADER);
+
+        // Without an image directory, local files are not read
+        assert!(load_image(path.to_str().unwrap(), None)
+            .unwrap_err()
+            .contains("not allowed"));
+    }
+
+    #[test]
+    fn test_load_image_outside_image_dir() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let image_dir = temp_dir.path().join("images");
+        std::fs::create_dir(&image_dir).unwrap();
+        let outside = temp_dir.path().join("secret.png");
+        std::fs::write(&outside, PNG_HEADER).unwrap();
+        std::os::unix::fs::symlink(&outside, image_dir.join("link.png")).unwrap();
+
+        let image_dir = Some(image_dir.as_path());
+        for url in [
+            outside.to_str().unwrap(),
+            "../secret.png",
+            "link.png",
+            "/etc/passwd",
+            "/dev/zero",
+            "missing.png",
+            ".",
+        ] {
+            // The same error whether or not the file exists, without the path
+            let error = load_image(url, image_dir).unwrap_err();
+            assert_eq!(error, "Image file not found in the image directory");
+        }
+    }
+
+    #[test]
+    fn test_load_image_too_large() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let file = File::create(temp_dir.path().join("huge.png")).unwrap();
+        file.set_len(MAX_IMAGE_BYTES as u64 + 1).unwrap();
+
+        let error = load_image("huge.png", Some(temp_dir.path())).unwrap_err();
+        assert!(error.contains("too large"));
+    }
+
+    #[test]
+    fn test_load_image_invalid() {
+        assert!(load_image("data:image/png,not-base64", None).is_err());
+        assert!(load_image("data:image/png;base64,!!!", None).is_err());
+        assert!(load_image("https://example.com/cat.png", None).is_err());
+        assert!(load_image("/nonexistent/cat.png", None).is_err());
+
+        // Valid base64 but not an image
+        let url = format!("data:image/png;base64,{}", STANDARD.encode("hello"));
+        assert!(load_image(&url, None)
+            .unwrap_err()
+            .contains("Unsupported image format"));
+    }
+}
@@ -0,0 +1,531 @@
+use axum::{
+    extract::State,
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    Json,
+};
+use futures::stream::StreamExt;
+use serde_json::json;
+use std::convert::Infallible;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use uuid::Uuid;
+
+use crate::api::chat::format_chat_messages;
+use crate::api::context::fit_chat_messages;
+use crate::api::error::ApiError;
+use crate::api::extract::ApiJson;
+use crate::api::images::load_request_images;
+use crate::api::models::resolve_model;
+use crate::api::reasoning::ReasoningParser;
+use crate::api::routes::AppState;
+use crate::api::timings::RequestTimer;
+use crate::api::tools::{format_tool_call, parse_output, ParsedToolCall};
+use crate::api::types::{
+    ChatMessage, ContentBlock, ContentPart, ImageUrl, MessageContent, MessagesRequest,
+    MessagesResponse, MessagesUsage, TruncationStrategy,
+};
+use crate::backend::{GenerateRequest, InferenceEngine, ToolDefinition};
+
+/// Handler for the Anthropic Messages API
+pub async fn messages<E: InferenceEngine + 'static>(
+    State(state): State<AppState<E>>,
+    timer: RequestTimer,
+    ApiJson(req): ApiJson<MessagesRequest>,
+) -> Response {
+    let engine = state.engine.clone();
+
+    // Validate request
+    if req.messages.is_empty() {
+        return ApiError::invalid_request("messages cannot be empty").into_response();
+    }
+    if let Some(message) = req
+        .messages
+        .iter()
+        .find(|m| m.role != "user" && m.role != "assistant")
+    {
+        return ApiError::invalid_request(format!(
+            "Unexpected role '{}', messages must be from 'user' or 'assistant'",
+            message.role
+        ))
+        .into_response();
+    }
+
+    // Validate model exists
+    let resolved = match resolve_model(&state, &req.model).await {
+        Ok(resolved) => resolved,
+        Err(e) => return e.into_response(),
+    };
+
+    // Make sure the prompt and completion fit in the context window
+    let mut messages = to_chat_messages(&req);
+    let mut max_tokens = req.max_tokens;
+    if let Err(e) = fit_chat_messages(
+        engine.as_ref(),
+        &resolved.base,
+        &mut messages,
+        &mut max_tokens,
+        TruncationStrategy::Disabled,
+    )
+    .await
+    {
+        return e.into_response();
+    }
+
+    let images = match load_request_images(
+        state.image_dir.as_deref(),
+        &resolved.base,
+        &req.model,
+        &messages,
+    ) {
+        Ok(images) => images,
+        Err(e) => return e.into_response(),
+    };
+
+    // Image embeddings are not part of the prompt text, so their KV state is not cached
+    let revision = images
+        .is_empty()
+        .then(|| resolved.base.metadata.cache.revision.clone());
+    let gen_req = GenerateRequest::new(
+        &resolved.base.name,
+        &format_chat_messages(&messages),
+        max_tokens,
+        req.temperature.unwrap_or(1.0),
+    )
+    .with_adapter(resolved.adapter.map(|a| a.name))
+    .with_revision(revision)
+    .with_images(images)
+    .with_thinking_budget(req.thinking.and_then(|t| t.budget()))
+    .with_tools(
+        req.tools
+            .iter()
+            .map(|tool| ToolDefinition {
+                name: tool.name.clone(),
+                description: tool.description.clone(),
+                parameters: tool.input_schema.clone(),
+            })
+            .collect(),
+    );
+
+    if req.stream {
+        stream_message(engine, req.model, gen_req, timer)
+            .await
+            .into_response()
+    } else {
+        match generate_message(engine.as_ref(), req.model, &gen_req, timer).await {
+            Ok(response) => Json(response).into_response(),
+            Err(e) => ApiError::from(e).into_response(),
+        }
+    }
+}
+
+/// Convert the system prompt and messages to chat messages. Tool results become
+/// tool messages, tool uses are replayed the way the model emits them.
+fn to_chat_messages(req: &MessagesRequest) -> Vec<ChatMessage> {
+    let mut messages: Vec<ChatMessage> = req
+        .system
+        .iter()
+        .map(|system| chat_message("system", MessageContent::Text(system.text())))
+        .collect();
+
+    for message in &req.messages {
+        let mut parts = Vec::new();
+        for block in message.content.clone().into_blocks() {
+            match block {
+                ContentBlock::Text { text } => parts.push(ContentPart::Text { text }),
+                ContentBlock::Image { source } => parts.push(ContentPart::ImageUrl {
+                    image_url: ImageUrl {
+                        url: source.to_url(),
+                        detail: None,
+                    },
+                }),
+                ContentBlock::ToolUse { name, input, .. } => parts.push(ContentPart::Text {
+                    text: format_tool_call(&name, &input.to_string()),
+                }),
+                ContentBlock::ToolResult { content, .. } => {
+                    if !parts.is_empty() {
+                        let content = MessageContent::Parts(std::mem::take(&mut parts));
+                        messages.push(chat_message(&message.role, content));
+                    }
+                    let output = content.map(|c| c.text()).unwrap_or_default();
+                    messages.push(chat_message("tool", MessageContent::Text(output)));
+                }
+                ContentBlock::Thinking { .. } => {}
+            }
+        }
+        if !parts.is_empty() {
+            messages.push(chat_message(&message.role, MessageContent::Parts(parts)));
+        }
+    }
+
+    messages
+}
+
+fn chat_message(role: &str, content: MessageContent) -> ChatMessage {
+    ChatMessage {
+        role: role.to_string(),
+        content,
+        reasoning_content: None,
+    }
+}
+
+fn new_id(prefix: &str) -> String {
+    format!("{}_{}", prefix, Uuid::new_v4().simple())
+}
+
+fn tool_use_block(call: ParsedToolCall) -> ContentBlock {
+    ContentBlock::ToolUse {
+        id: new_id("toolu"),
+        name: call.name,
+        input: serde_json::from_str(&call.arguments).unwrap_or_else(|_| json!({})),
+    }
+}
+
+fn stop_reason(tool_use: bool, completion_tokens: usize, max_tokens: usize) -> &'static str {
+    if tool_use {
+        "tool_use"
+    } else if completion_tokens >= max_tokens {
+        "max_tokens"
+    } else {
+        "end_turn"
+    }
+}
+
+/// Non-streaming message
+async fn generate_message<E: InferenceEngine>(
+    engine: &E,
+    model: String,
+    gen_req: &GenerateRequest,
+    mut timer: RequestTimer,
+) -> Result<MessagesResponse, std::io::Error> {
+    timer.generation_started();
+    let response = engine.generate(gen_req).await?;
+    let timings = timer.generate_timings(
+        response.prompt_tokens,
+        response.completion_tokens,
+        response.prompt_duration,
+        response.decode_duration,
+    );
+    let parsed = parse_output(&response.text, !gen_req.tools.is_empty());
+    let tool_use = !parsed.tool_calls.is_empty();
+    let stop_reason = stop_reason(tool_use, response.completion_tokens, gen_req.max_tokens);
+    timer.finish(
+        &timings,
+        "/v1/messages",
+        &model,
+        response.prompt_tokens,
+        response.completion_tokens,
+        stop_reason,
+    );
+
+    let mut content = Vec::new();
+    if let Some(thinking) = parsed.reasoning {
+        content.push(ContentBlock::Thinking {
+            thinking,
+            signature: String::new(),
+        });
+    }
+    if !parsed.text.is_empty() || !tool_use {
+        content.push(ContentBlock::Text { text: parsed.text });
+    }
+    content.extend(parsed.tool_calls.into_iter().map(tool_use_block));
+
+    Ok(MessagesResponse {
+        id: new_id("msg"),
+        object: "message".to_string(),
+        role: "assistant".to_string(),
+        content,
+        model,
+        stop_reason: Some(stop_reason.to_string()),
+        stop_sequence: None,
+        usage: MessagesUsage {
+            input_tokens: response.prompt_tokens,
+            output_tokens: response.completion_tokens,
+            cache_read_input_tokens: response.cached_tokens,
+        },
+    })
+}
+
+/// The client closed the stream
+struct Disconnected;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BlockKind {
+    Thinking,
+    Text,
+}
+
+/// Emits content block events of a streamed message
+struct BlockStream {
+    tx: mpsc::Sender<Result<Event, Infallible>>,
+    /// Index of the next or currently open block
+    index: usize,
+    open: Option<BlockKind>,
+    has_text: bool,
+    has_tool_use: bool,
+}
+
+impl BlockStream {
+    async fn send(
+        &self,
+        event_type: &str,
+        mut data: serde_json::Value,
+    ) -> Result<(), Disconnected> {
+        data["type"] = json!(event_type);
+        self.tx
+            .send(Ok(Event::default()
+                .event(event_type)
+                .data(data.to_string())))
+            .await
+            .map_err(|_| Disconnected)
+    }
+
+    async fn delta(&mut self, kind: BlockKind, text: &str) -> Result<(), Disconnected> {
+        if self.open != Some(kind) {
+            self.close_block().await?;
+            let block = match kind {
+                BlockKind::Thinking => json!({"type": "thinking", "thinking": "", "signature": ""}),
+                BlockKind::Text => json!({"type": "text", "text": ""}),
+            };
+            self.send(
+                "content_block_start",
+                json!({"index": self.index, "content_block": block}),
+            )
+            .await?;
+            self.open = Some(kind);
+            self.has_text |= kind == BlockKind::Text;
+        }
+
+        let delta = match kind {
+            BlockKind::Thinking => json!({"type": "thinking_delta", "thinking": text}),
+            BlockKind::Text => json!({"type": "text_delta", "text": text}),
+        };
+        self.send(
+            "content_block_delta",
+            json!({"index": self.index, "delta": delta}),
+        )
+        .await
+    }
+
+    /// Emit a complete tool use block
+    async fn tool_use(&mut self, call: ParsedToolCall) -> Result<(), Disconnected> {
+        self.close_block().await?;
+
+        let arguments = call.arguments.clone();
+        let ContentBlock::ToolUse { id, name, .. } = tool_use_block(call) else {
+            unreachable!("tool_use_block returns a tool use");
+        };
+        self.send(
+            "content_block_start",
+            json!({
+                "index": self.index,
+                "content_block": {"type": "tool_use", "id": id, "name": name, "input": {}},
+            }),
+        )
+        .await?;
+        self.send(
+            "content_block_delta",
+            json!({
+                "index": self.index,
+                "delta": {"type": "input_json_delta", "partial_json": arguments},
+            }),
+        )
+        .await?;
+        self.send("content_block_stop", json!({"index": self.index}))
+            .await?;
+        self.index += 1;
+        self.has_tool_use = true;
+        Ok(())
+    }
+
+    /// Finish the block currently receiving deltas, if any
+    async fn close_block(&mut self) -> Result<(), Disconnected> {
+        if self.open.take().is_some() {
+            self.send("content_block_stop", json!({"index": self.index}))
+                .await?;
+            self.index += 1;
+        }
+        Ok(())
+    }
+
+    /// Close the last block, answering with an empty text block if nothing was said
+    async fn finish(&mut self) -> Result<(), Disconnected> {
+        if !self.has_text && !self.has_tool_use {
+            self.delta(BlockKind::Text, "").await?;
+        }
+        self.close_block().await
+    }
+}
+
+/// Streaming message with `message_start`, content block and `message_stop` events
+async fn stream_message<E: InferenceEngine + 'static>(
+    engine: Arc<E>,
+    model: String,
+    gen_req: GenerateRequest,
+    mut timer: RequestTimer,
+) -> Sse<impl futures::Stream<Item = Result<Event, Infallible>>> {
+    let (tx, rx) = mpsc::channel(100);
+
+    tokio::spawn(async move {
+        let mut blocks = BlockStream {
+            tx,
+            index: 0,
+            open: None,
+            has_text: false,
+            has_tool_use: false,
+        };
+
+        // message_start reports the input tokens before generation starts
+        let prompt_tokens = match engine.tokenize(&gen_req.model, &gen_req.prompt).await {
+            Ok(tokens) => tokens.len(),
+            Err(e) => {
+                send_error(&blocks, &e).await;
+                return;
+            }
+        };
+        let message = MessagesResponse {
+            id: new_id("msg"),
+            object: "message".to_string(),
+            role: "assistant".to_string(),
+            content: vec![],
+            model: model.clone(),
+            stop_reason: None,
+            stop_sequence: None,
+            usage: MessagesUsage {
+                input_tokens: prompt_tokens,
+                output_tokens: 0,
+                cache_read_input_tokens: 0,
+            },
+        };
+        if blocks
+            .send("message_start", json!({"message": message}))
+            .await
+            .is_err()
+            || blocks.send("ping", json!({})).await.is_err()
+        {
+            return;
+        }
+
+        timer.generation_started();
+        let result = if gen_req.tools.is_empty() {
+            stream_blocks(engine.as_ref(), &gen_req, &mut blocks, &mut timer).await
+        } else {
+            // Tool calls can only be parsed from the complete output
+            generate_blocks(engine.as_ref(), &gen_req, &mut blocks).await
+        };
+
+        let completion_tokens = match result {
+            Ok(Ok(completion_tokens)) => completion_tokens,
+            Ok(Err(Disconnected)) => return,
+            Err(e) => {
+                tracing::error!("Error generating stream: {}", e);
+                send_error(&blocks, &e).await;
+                return;
+            }
+        };
+
+        let stop_reason = stop_reason(blocks.has_tool_use, completion_tokens, gen_req.max_tokens);
+        timer.finish(
+            &timer.stream_timings(prompt_tokens, completion_tokens),
+            "/v1/messages",
+            &model,
+            prompt_tokens,
+            completion_tokens,
+            stop_reason,
+        );
+        if blocks
+            .send(
+                "message_delta",
+                json!({
+                    "delta": {"stop_reason": stop_reason, "stop_sequence": null},
+                    "usage": {"output_tokens": completion_tokens},
+                }),
+            )
+            .await
+            .is_err()
+        {
+            return;
+        }
+        let _ = blocks.send("message_stop", json!({})).await;
+    });
+
+    Sse::new(ReceiverStream::new(rx)).keep_alive(KeepAlive::default())
+}
+
+async fn send_error(blocks: &BlockStream, error: &std::io::Error) {
+    let _ = blocks
+        .send(
+            "error",
+            json!({"error": {"type": "api_error", "message": error.to_string()}}),
+        )
+        .await;
+}
+
+/// Stream engine tokens as thinking and text deltas, returns the completion tokens
+async fn stream_blocks<E: InferenceEngine>(
+    engine: &E,
+    gen_req: &GenerateRequest,
+    blocks: &mut BlockStream,
+    timer: &mut RequestTimer,
+) -> Result<Result<usize, Disconnected>, std::io::Error> {
+    let mut stream = engine.generate_stream(gen_req).await?;
+    let mut parser = ReasoningParser::new();
+    let mut completion_tokens = 0;
+
+    let mut finished = false;
+    while !finished {
+        // Split reasoning out of each token, flushing the parser at the end
+        let delta = match stream.tokens.next().await {
+            Some(token) => {
+                timer.first_token();
+                completion_tokens += 1;
+                parser.push(&token)
+            }
+            None => {
+                finished = true;
+                parser.finish()
+            }
+        };
+        if !delta.reasoning.is_empty() {
+            if let Err(e) = blocks.delta(BlockKind::Thinking, &delta.reasoning).await {
+                return Ok(Err(e));
+            }
+        }
+        if !delta.content.is_empty() {
+            if let Err(e) = blocks.delta(BlockKind::Text, &delta.content).await {
+                return Ok(Err(e));
+            }
+        }
+    }
+
+    Ok(blocks.finish().await.map(|()| completion_tokens))
+}
+
+/// Generate the complete output and emit its blocks, returns the completion tokens
+async fn generate_blocks<E: InferenceEngine>(
+    engine: &E,
+    gen_req: &GenerateRequest,
+    blocks: &mut BlockStream,
+) -> Result<Result<usize, Disconnected>, std::io::Error> {
+    let response = engine.generate(gen_req).await?;
+    let parsed = parse_output(&response.text, true);
+
+    let emitted = async {
+        if let Some(reasoning) = &parsed.reasoning {
+            blocks.delta(BlockKind::Thinking, reasoning).await?;
+        }
+        if !parsed.text.is_empty() {
+            blocks.delta(BlockKind::Text, &parsed.text).await?;
+        }
+        for call in parsed.tool_calls {
+            blocks.tool_use(call).await?;
+        }
+        blocks.finish().await
+    }
+    .await;
+
+    Ok(emitted.map(|()| response.completion_tokens))
+}
@@ -0,0 +1,514 @@
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::{header, HeaderValue},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use sysinfo::{ProcessRefreshKind, ProcessesToUpdate, System};
+
+use crate::api::health::ReadinessPhase;
+use crate::api::queue::QueueStats;
+use crate::api::routes::AppState;
+use crate::api::usage::{GenerationUsage, UsageObserver, UsageRecorder};
+use crate::backend::InferenceEngine;
+
+/// Content type of the Prometheus text exposition format
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Route label of requests that matched no route, so unknown paths don't
+/// each get their own series
+const UNMATCHED_ROUTE: &str = "unmatched";
+
+const REQUEST_DURATION_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0,
+];
+const TIME_TO_FIRST_TOKEN_BUCKETS: &[f64] =
+    &[0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0];
+const INTER_TOKEN_LATENCY_BUCKETS: &[f64] =
+    &[0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0];
+const QUEUE_WAIT_BUCKETS: &[f64] = &[0.001, 0.01, 0.05, 0.1, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0];
+
+/// Cumulative histogram with fixed bucket bounds, in seconds
+#[derive(Debug, Clone)]
+struct Histogram {
+    bounds: &'static [f64],
+    counts: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn new(bounds: &'static [f64]) -> Self {
+        Self {
+            bounds,
+            counts: vec![0; bounds.len()],
+            sum: 0.0,
+            count: 0,
+        }
+    }
+
+    fn observe(&mut self, value: f64) {
+        for (bound, count) in self.bounds.iter().zip(&mut self.counts) {
+            if value <= *bound {
+                *count += 1;
+            }
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+}
+
+/// Labels of a request series
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct RequestLabels {
+    route: String,
+    method: String,
+    status: u16,
+}
+
+#[derive(Debug, Default)]
+struct Families {
+    requests: BTreeMap<RequestLabels, Histogram>,
+    prompt_tokens: BTreeMap<String, u64>,
+    generation_tokens: BTreeMap<String, u64>,
+    time_to_first_token: BTreeMap<String, Histogram>,
+    inter_token_latency: BTreeMap<String, Histogram>,
+    queue_wait: BTreeMap<String, Histogram>,
+}
+
+/// Server metrics, rendered in the Prometheus text format on `/metrics`
+pub struct Metrics {
+    families: Mutex<Families>,
+    /// Kept across scrapes, CPU usage is measured between two refreshes
+    system: Mutex<System>,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self {
+            families: Mutex::new(Families::default()),
+            system: Mutex::new(System::new()),
+        }
+    }
+
+    fn observe_request(&self, labels: RequestLabels, seconds: f64) {
+        self.families
+            .lock()
+            .unwrap()
+            .requests
+            .entry(labels)
+            .or_insert_with(|| Histogram::new(REQUEST_DURATION_BUCKETS))
+            .observe(seconds);
+    }
+
+    /// Requests answered since startup
+    pub fn requests_total(&self) -> u64 {
+        let families = self.families.lock().unwrap();
+        families
+            .requests
+            .values()
+            .map(|histogram| histogram.count)
+            .sum()
+    }
+
+    /// Render every metric, with the gauges read at scrape time
+    pub fn render(&self, queue: QueueStats, loaded_model: Option<&str>) -> String {
+        let mut out = String::new();
+        self.render_requests(&mut out);
+        self.render_generations(&mut out);
+        render_queue(&mut out, queue);
+
+        family(
+            &mut out,
+            "puma_model_loaded",
+            "gauge",
+            "Models loaded and ready to serve",
+        );
+        if let Some(model) = loaded_model {
+            sample(&mut out, "puma_model_loaded", &[("model", model)], 1.0);
+        }
+
+        self.render_process(&mut out);
+        out
+    }
+
+    fn render_requests(&self, out: &mut String) {
+        let families = self.families.lock().unwrap();
+
+        family(
+            out,
+            "puma_http_requests_total",
+            "counter",
+            "HTTP requests by route, method and status",
+        );
+        for (labels, histogram) in &families.requests {
+            let status = labels.status.to_string();
+            let labels = [
+                ("route", labels.route.as_str()),
+                ("method", labels.method.as_str()),
+                ("status", status.as_str()),
+            ];
+            sample(
+                out,
+                "puma_http_requests_total",
+                &labels,
+                histogram.count as f64,
+            );
+        }
+
+        family(
+            out,
+            "puma_http_request_duration_seconds",
+            "histogram",
+            "Time to answer HTTP requests, up to the response head for streamed responses",
+        );
+        for (labels, histogram) in &families.requests {
+            let status = labels.status.to_string();
+            let labels = [
+                ("route", labels.route.as_str()),
+                ("method", labels.method.as_str()),
+                ("status", status.as_str()),
+            ];
+            render_histogram(
+                out,
+                "puma_http_request_duration_seconds",
+                &labels,
+                histogram,
+            );
+        }
+    }
+
+    fn render_generations(&self, out: &mut String) {
+        let families = self.families.lock().unwrap();
+
+        let counters = [
+            (
+                "puma_prompt_tokens_total",
+                "Prompt tokens processed per model",
+                &families.prompt_tokens,
+            ),
+            (
+                "puma_generation_tokens_total",
+                "Completion tokens generated per model",
+                &families.generation_tokens,
+            ),
+        ];
+        for (name, help, values) in counters {
+            family(out, name, "counter", help);
+            for (model, value) in values {
+                sample(out, name, &[("model", model)], *value as f64);
+            }
+        }
+
+        let histograms = [
+            (
+                "puma_time_to_first_token_seconds",
+                "Time from the start of generation to the first token",
+                &families.time_to_first_token,
+            ),
+            (
+                "puma_inter_token_latency_seconds",
+                "Mean time between completion tokens, one sample per generation",
+                &families.inter_token_latency,
+            ),
+            (
+                "puma_queue_wait_seconds",
+                "Time generation requests waited before generation started",
+                &families.queue_wait,
+            ),
+        ];
+        for (name, help, values) in histograms {
+            family(out, name, "histogram", help);
+            for (model, histogram) in values {
+                render_histogram(out, name, &[("model", model)], histogram);
+            }
+        }
+    }
+
+    fn render_process(&self, out: &mut String) {
+        let Ok(pid) = sysinfo::get_current_pid() else {
+            return;
+        };
+        let mut system = self.system.lock().unwrap();
+        system.refresh_processes_specifics(
+            ProcessesToUpdate::Some(&[pid]),
+            true,
+            ProcessRefreshKind::new().with_cpu().with_memory(),
+        );
+        let Some(process) = system.process(pid) else {
+            return;
+        };
+
+        let gauges = [
+            (
+                "process_resident_memory_bytes",
+                "Resident memory size in bytes",
+                process.memory() as f64,
+            ),
+            (
+                "process_virtual_memory_bytes",
+                "Virtual memory size in bytes",
+                process.virtual_memory() as f64,
+            ),
+            (
+                "process_cpu_usage_percent",
+                "CPU usage since the previous scrape, 100 per fully used core",
+                process.cpu_usage() as f64,
+            ),
+            (
+                "process_start_time_seconds",
+                "Start time of the process since the Unix epoch in seconds",
+                process.start_time() as f64,
+            ),
+        ];
+        for (name, help, value) in gauges {
+            family(out, name, "gauge", help);
+            sample(out, name, &[], value);
+        }
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UsageObserver for Metrics {
+    fn observe(&self, usage: &GenerationUsage) {
+        let timings = &usage.timings;
+        let mut families = self.families.lock().unwrap();
+        *families
+            .prompt_tokens
+            .entry(usage.model.clone())
+            .or_default() += usage.prompt_tokens as u64;
+        *families
+            .generation_tokens
+            .entry(usage.model.clone())
+            .or_default() += usage.completion_tokens as u64;
+
+        if usage.completion_tokens > 0 {
+            families
+                .time_to_first_token
+                .entry(usage.model.clone())
+                .or_insert_with(|| Histogram::new(TIME_TO_FIRST_TOKEN_BUCKETS))
+                .observe(timings.time_to_first_token_ms / 1000.0);
+        }
+        if timings.decode_tokens_per_second > 0.0 {
+            families
+                .inter_token_latency
+                .entry(usage.model.clone())
+                .or_insert_with(|| Histogram::new(INTER_TOKEN_LATENCY_BUCKETS))
+                .observe(1.0 / timings.decode_tokens_per_second);
+        }
+        families
+            .queue_wait
+            .entry(usage.model.clone())
+            .or_insert_with(|| Histogram::new(QUEUE_WAIT_BUCKETS))
+            .observe(timings.queue_time_ms / 1000.0);
+    }
+}
+
+/// Middleware counting requests and their latency, and collecting the usage
+/// of the generations they run
+pub async fn track_requests(
+    State(metrics): State<Arc<Metrics>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let started = Instant::now();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| UNMATCHED_ROUTE.to_string());
+    let method = request.method().to_string();
+    UsageRecorder::observe(&mut request, metrics.clone());
+
+    let response = next.run(request).await;
+    let labels = RequestLabels {
+        route,
+        method,
+        status: response.status().as_u16(),
+    };
+    metrics.observe_request(labels, started.elapsed().as_secs_f64());
+    response
+}
+
+/// Prometheus scrape endpoint
+pub async fn metrics<E: InferenceEngine + 'static>(State(state): State<AppState<E>>) -> Response {
+    let status = state.readiness.status();
+    let loaded_model = status
+        .model
+        .filter(|_| status.status == ReadinessPhase::Ready);
+    let body = state
+        .metrics
+        .render(state.queue.stats(), loaded_model.as_deref());
+
+    let mut response = body.into_response();
+    response
+        .headers_mut()
+        .insert(header::CONTENT_TYPE, HeaderValue::from_static(CONTENT_TYPE));
+    response
+}
+
+fn render_queue(out: &mut String, queue: QueueStats) {
+    let gauges = [
+        (
+            "puma_queue_depth",
+            "Generation requests waiting for a slot",
+            queue.queued,
+        ),
+        (
+            "puma_queue_in_flight",
+            "Generations holding a slot",
+            queue.in_flight,
+        ),
+    ];
+    for (name, help, value) in gauges {
+        family(out, name, "gauge", help);
+        sample(out, name, &[], value as f64);
+    }
+
+    family(
+        out,
+        "puma_queue_rejected_total",
+        "counter",
+        "Generation requests rejected because the queue was full",
+    );
+    sample(out, "puma_queue_rejected_total", &[], queue.rejected as f64);
+}
+
+fn family(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+fn sample(out: &mut String, name: &str, labels: &[(&str, &str)], value: f64) {
+    let _ = writeln!(out, "{}{} {}", name, format_labels(labels), value);
+}
+
+fn render_histogram(out: &mut String, name: &str, labels: &[(&str, &str)], histogram: &Histogram) {
+    let bucket = format!("{}_bucket", name);
+    for (bound, count) in histogram.bounds.iter().zip(&histogram.counts) {
+        let le = bound.to_string();
+        let mut labels = labels.to_vec();
+        labels.push(("le", &le));
+        sample(out, &bucket, &labels, *count as f64);
+    }
+    let mut labels_inf = labels.to_vec();
+    labels_inf.push(("le", "+Inf"));
+    sample(out, &bucket, &labels_inf, histogram.count as f64);
+    sample(out, &format!("{}_sum", name), labels, histogram.sum);
+    sample(
+        out,
+        &format!("{}_count", name),
+        labels,
+        histogram.count as f64,
+    );
+}
+
+fn format_labels(labels: &[(&str, &str)]) -> String {
+    if labels.is_empty() {
+        return String::new();
+    }
+    let labels: Vec<String> = labels
+        .iter()
+        .map(|(name, value)| format!("{}=\"{}\"", name, escape_label(value)))
+        .collect();
+    format!("{{{}}}", labels.join(","))
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::types::Timings;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    fn queue_stats() -> QueueStats {
+        QueueStats {
+            in_flight: 1,
+            queued: 2,
+            max_concurrency: 1,
+            max_queue_depth: 8,
+            rejected: 3,
+        }
+    }
+
+    #[test]
+    fn test_histogram_buckets_are_cumulative() {
+        let mut histogram = Histogram::new(&[0.1, 1.0]);
+        histogram.observe(0.05);
+        histogram.observe(0.5);
+        histogram.observe(5.0);
+        assert_eq!(histogram.counts, vec![1, 2]);
+        assert_eq!(histogram.count, 3);
+
+        let mut out = String::new();
+        render_histogram(&mut out, "latency", &[("model", "m")], &histogram);
+        assert!(out.contains("latency_bucket{model=\"m\",le=\"0.1\"} 1\n"));
+        assert!(out.contains("latency_bucket{model=\"m\",le=\"+Inf\"} 3\n"));
+        assert!(out.contains("latency_sum{model=\"m\"} 5.55\n"));
+    }
+
+    #[test]
+    fn test_render_generation_usage() {
+        let metrics = Metrics::new();
+        metrics.observe(&GenerationUsage {
+            model: "test-model".to_string(),
+            prompt_tokens: 10,
+            completion_tokens: 20,
+            finish_reason: "stop".to_string(),
+            timings: Timings {
+                time_to_first_token_ms: 30.0,
+                decode_tokens_per_second: 100.0,
+                ..Default::default()
+            },
+        });
+
+        let out = metrics.render(queue_stats(), Some("test-model"));
+        assert!(out.contains("puma_prompt_tokens_total{model=\"test-model\"} 10\n"));
+        assert!(out.contains("puma_generation_tokens_total{model=\"test-model\"} 20\n"));
+        assert!(out.contains(
+            "puma_time_to_first_token_seconds_bucket{model=\"test-model\",le=\"0.05\"} 1\n"
+        ));
+        assert!(out.contains("puma_inter_token_latency_seconds_sum{model=\"test-model\"} 0.01\n"));
+        assert!(out.contains("puma_queue_depth 2\n"));
+        assert!(out.contains("puma_queue_rejected_total 3\n"));
+        assert!(out.contains("puma_model_loaded{model=\"test-model\"} 1\n"));
+
+        let start: f64 = out
+            .lines()
+            .find_map(|line| line.strip_prefix("process_start_time_seconds "))
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!(
+            start > 0.0
+                && start
+                    <= SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .unwrap()
+                        .as_secs_f64()
+        );
+    }
+
+    #[test]
+    fn test_escape_label() {
+        assert_eq!(
+            format_labels(&[("model", "a\"b\\c\nd")]),
+            "{model=\"a\\\"b\\\\c\\nd\"}"
+        );
+    }
+}
@@ -1,8 +1,32 @@
+pub mod admin;
+pub mod audit;
+pub mod auth;
 pub mod chat;
 pub mod completions;
+pub mod context;
+pub mod cors;
+pub mod error;
+pub mod extract;
+pub mod health;
+pub mod images;
+pub mod messages;
+pub mod metrics;
 pub mod models;
+pub mod ollama;
+pub mod params;
+pub mod queue;
+pub mod ratelimit;
+pub mod reasoning;
+pub mod rerank;
+pub mod responses;
 pub mod routes;
+pub mod server;
+pub mod timings;
+pub mod tls;
+pub mod tokenize;
+pub mod tools;
 pub mod types;
+pub mod usage;
 
 #[cfg(test)]
 mod tests;
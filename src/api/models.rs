@@ -4,77 +4,130 @@ use axum::{
     Json,
 };
 
+use crate::api::error::ApiError;
 use crate::api::routes::AppState;
-use crate::api::types::{ErrorResponse, Model, ModelList};
+use crate::api::types::{Model, ModelList};
 use crate::backend::InferenceEngine;
+use crate::registry::model_registry::{AdapterInfo, ModelInfo, ResolvedModel};
+
+/// Resolve a requested model name against the registry.
+/// If the name refers to a LoRA adapter, the adapter is loaded on the engine
+/// on top of its base model before returning.
+pub async fn resolve_model<E: InferenceEngine>(
+    state: &AppState<E>,
+    name: &str,
+) -> Result<ResolvedModel, ApiError> {
+    // Refuse inference until the served model is loaded and warmed up,
+    // and once the server is shutting down
+    if state.readiness.is_draining() {
+        return Err(ApiError::service_unavailable(
+            "The server is shutting down, retry on another instance",
+        )
+        .with_code("server_shutting_down"));
+    }
+    if !state.readiness.is_ready() {
+        return Err(ApiError::service_unavailable(
+            "Model is still loading, retry when /health/ready reports ready",
+        )
+        .with_code("model_not_ready"));
+    }
+
+    let resolved = match state.registry.resolve_model(name) {
+        Ok(Some(resolved)) => resolved,
+        Ok(None) => return Err(ApiError::model_not_found(name)),
+        Err(e) => {
+            return Err(ApiError::server_error(format!(
+                "Failed to check model: {}",
+                e
+            )))
+        }
+    };
+
+    if let Some(adapter) = &resolved.adapter {
+        if let Err(e) = state
+            .engine
+            .load_adapter(&resolved.base.name, &adapter.name, &adapter.path)
+            .await
+        {
+            return Err(ApiError::server_error(format!(
+                "Failed to load adapter '{}': {}",
+                adapter.name, e
+            )));
+        }
+    }
+
+    Ok(resolved)
+}
 
 /// List all available models
 pub async fn list_models<E: InferenceEngine + 'static>(
     State(state): State<AppState<E>>,
 ) -> impl IntoResponse {
     let registry = state.registry;
-    match registry.load_models(None) {
-        Ok(models) => {
+    match registry
+        .load_models(None)
+        .and_then(|models| Ok((models, registry.load_adapters(None)?)))
+    {
+        Ok((models, adapters)) => {
+            // Adapters are served as models of their own, pointing at their base
+            let data = models
+                .into_iter()
+                .map(model_entry)
+                .chain(adapters.into_iter().map(adapter_entry))
+                .collect();
+
             let model_list = ModelList {
                 object: "list".to_string(),
-                data: models
-                    .into_iter()
-                    .map(|m| Model {
-                        id: m.name.clone(),
-                        object: "model".to_string(),
-                        created: chrono::DateTime::parse_from_rfc3339(&m.created_at)
-                            .map(|dt| dt.timestamp())
-                            .unwrap_or(0),
-                        owned_by: m.author.unwrap_or_else(|| "puma".to_string()),
-                    })
-                    .collect(),
+                data,
             };
             Json(model_list).into_response()
         }
-        Err(e) => (
-            axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse::new(
-                format!("Failed to load models: {}", e),
-                "internal_error".to_string(),
-            )),
-        )
-            .into_response(),
+        Err(e) => ApiError::server_error(format!("Failed to load models: {}", e)).into_response(),
     }
 }
 
-/// Get a specific model by ID
+/// Get a specific model or adapter by ID
 pub async fn get_model<E: InferenceEngine + 'static>(
     State(state): State<AppState<E>>,
     Path(model_id): Path<String>,
 ) -> impl IntoResponse {
     let registry = state.registry;
-    match registry.get_model(&model_id) {
-        Ok(Some(model)) => {
-            let model_info = Model {
-                id: model.name.clone(),
-                object: "model".to_string(),
-                created: chrono::DateTime::parse_from_rfc3339(&model.created_at)
-                    .map(|dt| dt.timestamp())
-                    .unwrap_or(0),
-                owned_by: model.author.unwrap_or_else(|| "puma".to_string()),
-            };
-            Json(model_info).into_response()
-        }
-        Ok(None) => (
-            axum::http::StatusCode::NOT_FOUND,
-            Json(ErrorResponse::new(
-                format!("Model '{}' not found", model_id),
-                "model_not_found".to_string(),
-            )),
-        )
-            .into_response(),
-        Err(e) => (
-            axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse::new(
-                format!("Failed to get model: {}", e),
-                "internal_error".to_string(),
-            )),
-        )
-            .into_response(),
+    let entry = match registry.get_model(&model_id) {
+        Ok(Some(model)) => Ok(Some(model_entry(model))),
+        Ok(None) => registry
+            .get_adapter(&model_id)
+            .map(|adapter| adapter.map(adapter_entry)),
+        Err(e) => Err(e),
+    };
+    match entry {
+        Ok(Some(entry)) => Json(entry).into_response(),
+        Ok(None) => ApiError::model_not_found(&model_id).into_response(),
+        Err(e) => ApiError::server_error(format!("Failed to get model: {}", e)).into_response(),
     }
 }
+
+fn model_entry(model: ModelInfo) -> Model {
+    Model {
+        id: model.name,
+        object: "model".to_string(),
+        created: created_timestamp(&model.created_at),
+        owned_by: model.author.unwrap_or_else(|| "puma".to_string()),
+        parent: None,
+    }
+}
+
+fn adapter_entry(adapter: AdapterInfo) -> Model {
+    Model {
+        id: adapter.name,
+        object: "model".to_string(),
+        created: created_timestamp(&adapter.created_at),
+        owned_by: "puma".to_string(),
+        parent: Some(adapter.base_model),
+    }
+}
+
+fn created_timestamp(created_at: &str) -> i64 {
+    chrono::DateTime::parse_from_rfc3339(created_at)
+        .map(|dt| dt.timestamp())
+        .unwrap_or(0)
+}
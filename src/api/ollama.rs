@@ -0,0 +1,889 @@
+use axum::{
+    body::{to_bytes, Body},
+    extract::{DefaultBodyLimit, State},
+    handler::Handler,
+    http::{header, StatusCode},
+    middleware,
+    response::{IntoResponse, Response},
+    routing::{delete, get, post},
+    Json, Router,
+};
+use futures::stream::StreamExt;
+use serde_json::{json, Value};
+use std::convert::Infallible;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+
+use crate::api::admin::pull_events;
+use crate::api::chat::format_chat_messages;
+use crate::api::context::{fit_chat_messages, fit_prompt};
+use crate::api::error::ApiError;
+use crate::api::extract::ApiJson;
+use crate::api::health::ReadinessPhase;
+use crate::api::images::{load_request_images, MAX_IMAGE_REQUEST_BYTES};
+use crate::api::models::resolve_model;
+use crate::api::queue::{self, RequestQueue};
+use crate::api::reasoning::{effort_budget, ReasoningParser};
+use crate::api::routes::AppState;
+use crate::api::timings::RequestTimer;
+use crate::api::tools::{format_tool_call, parse_output};
+use crate::api::types::{
+    ChatMessage, ContentPart, ImageUrl, MessageContent, OllamaChatRequest, OllamaChatResponse,
+    OllamaFunctionCall, OllamaGenerateRequest, OllamaGenerateResponse, OllamaMessage, OllamaModel,
+    OllamaModelDetails, OllamaModelList, OllamaModelRequest, OllamaOptions, OllamaPullRequest,
+    OllamaRunningModel, OllamaRunningModelList, OllamaShowResponse, OllamaStats, OllamaStatus,
+    OllamaToolCall, ReasoningEffort, Timings, TruncationStrategy,
+};
+use crate::backend::vision::supports_vision;
+use crate::backend::{GenerateRequest, InferenceEngine, ToolDefinition};
+use crate::downloader::downloader::{DownloadError, Downloader};
+use crate::downloader::huggingface::HuggingFaceDownloader;
+use crate::downloader::progress::DownloadEvent;
+use crate::registry::model_registry::ModelInfo;
+use crate::utils::format::format_parameters;
+
+/// Tokens to generate when `num_predict` is not set
+const DEFAULT_NUM_PREDICT: usize = 100;
+
+/// Routes of the Ollama API, answering errors in Ollama's format
+pub fn router<E: InferenceEngine + Clone + 'static>(
+    queue: Arc<RequestQueue>,
+    admin: bool,
+) -> Router<AppState<E>> {
+    let queued = || middleware::from_fn_with_state(queue.clone(), queue::queued);
+    // Requests with image inputs exceed axum's default 2MB body limit
+    let images = || DefaultBodyLimit::max(MAX_IMAGE_REQUEST_BYTES);
+    let router = Router::new()
+        .route(
+            "/api/generate",
+            post(generate::<E>.layer(images())).layer(queued()),
+        )
+        .route("/api/chat", post(chat::<E>.layer(images())).layer(queued()))
+        .route("/api/tags", get(tags::<E>))
+        .route("/api/show", post(show::<E>))
+        .route("/api/ps", get(ps::<E>))
+        .route("/api/version", get(version));
+
+    // Pulling and deleting models is gated like the `/admin` endpoints
+    let router = if admin {
+        router
+            .route("/api/pull", post(pull))
+            .route("/api/delete", delete(delete_model::<E>))
+    } else {
+        router
+    };
+
+    router.layer(middleware::map_response(ollama_errors))
+}
+
+/// Rewrite error bodies, including those of the shared OpenAI helpers,
+/// to Ollama's `{"error": "..."}` format
+async fn ollama_errors(response: Response) -> Response {
+    let status = response.status();
+    if status.is_success() {
+        return response;
+    }
+
+    let Ok(body) = to_bytes(response.into_body(), usize::MAX).await else {
+        return status.into_response();
+    };
+    let message = match serde_json::from_slice::<Value>(&body) {
+        Ok(Value::Object(json)) => match json.get("error") {
+            Some(Value::String(message)) => message.clone(),
+            Some(error) => error["message"].as_str().unwrap_or_default().to_string(),
+            None => String::from_utf8_lossy(&body).to_string(),
+        },
+        _ => String::from_utf8_lossy(&body).trim().to_string(),
+    };
+    error(status, message)
+}
+
+fn error(status: StatusCode, message: String) -> Response {
+    (status, Json(json!({ "error": message }))).into_response()
+}
+
+/// Ollama clients tag model names, `:latest` is the only tag PUMA knows
+fn model_name(name: &str) -> &str {
+    name.strip_suffix(":latest").unwrap_or(name)
+}
+
+fn now() -> String {
+    chrono::Utc::now().to_rfc3339()
+}
+
+/// Handler for raw and templated prompt generation
+pub async fn generate<E: InferenceEngine + 'static>(
+    State(state): State<AppState<E>>,
+    timer: RequestTimer,
+    ApiJson(req): ApiJson<OllamaGenerateRequest>,
+) -> Response {
+    let engine = state.engine.clone();
+
+    let resolved = match resolve_model(&state, model_name(&req.model)).await {
+        Ok(resolved) => resolved,
+        Err(e) => return e.into_response(),
+    };
+
+    // An empty prompt only loads the model, which is loaded once the server is ready
+    if req.prompt.is_empty() && req.images.is_empty() {
+        return Json(OllamaGenerateResponse {
+            model: req.model,
+            created_at: now(),
+            response: String::new(),
+            thinking: None,
+            done: true,
+            done_reason: Some("load".to_string()),
+            stats: None,
+        })
+        .into_response();
+    }
+
+    let mut messages: Vec<ChatMessage> = req
+        .system
+        .iter()
+        .map(|system| chat_message("system", MessageContent::Text(system.clone())))
+        .collect();
+    messages.push(chat_message("user", content(&req.prompt, &req.images)));
+    let images = match load_request_images(
+        state.image_dir.as_deref(),
+        &resolved.base,
+        &req.model,
+        &messages,
+    ) {
+        Ok(images) => images,
+        Err(e) => return e.into_response(),
+    };
+
+    let prompt = match &req.suffix {
+        Some(suffix) => match state.fim_tokens.get(
+            Path::new(&resolved.base.metadata.cache.path),
+            &resolved.base.metadata.cache.revision,
+        ) {
+            Some(tokens) => tokens.format(&req.prompt, suffix),
+            None => {
+                return error(
+                    StatusCode::BAD_REQUEST,
+                    format!("model '{}' does not support insert", req.model),
+                )
+            }
+        },
+        None if req.raw => req.prompt.clone(),
+        None => format_chat_messages(&messages),
+    };
+
+    // Make sure the prompt and completion fit in the context window
+    let (mut max_tokens, strategy) = max_tokens(&req.options, &resolved.base);
+    if let Err(e) = fit_prompt(
+        engine.as_ref(),
+        &resolved.base,
+        &prompt,
+        &mut max_tokens,
+        strategy,
+    )
+    .await
+    {
+        return e.into_response();
+    }
+
+    // Image embeddings are not part of the prompt text, so their KV state is not cached
+    let revision = images
+        .is_empty()
+        .then(|| resolved.base.metadata.cache.revision.clone());
+    let gen_req = GenerateRequest::new(
+        &resolved.base.name,
+        &prompt,
+        max_tokens,
+        req.options.temperature.unwrap_or(0.8),
+    )
+    .with_adapter(resolved.adapter.map(|a| a.name))
+    .with_revision(revision)
+    .with_images(images)
+    .with_thinking_budget(thinking_budget(req.think));
+
+    let target = Target {
+        route: "/api/generate",
+        model: req.model,
+        stream: req.stream,
+    };
+    respond(engine, gen_req, timer, target, generate_line).await
+}
+
+/// Handler for chat, streamed as NDJSON
+pub async fn chat<E: InferenceEngine + 'static>(
+    State(state): State<AppState<E>>,
+    timer: RequestTimer,
+    ApiJson(req): ApiJson<OllamaChatRequest>,
+) -> Response {
+    let engine = state.engine.clone();
+
+    let resolved = match resolve_model(&state, model_name(&req.model)).await {
+        Ok(resolved) => resolved,
+        Err(e) => return e.into_response(),
+    };
+
+    // Empty messages only load the model, which is loaded once the server is ready
+    if req.messages.is_empty() {
+        let mut line = chat_line(&req.model, Line::default());
+        line["done"] = json!(true);
+        line["done_reason"] = json!("load");
+        return Json(line).into_response();
+    }
+
+    let mut messages: Vec<ChatMessage> = req.messages.iter().map(to_chat_message).collect();
+
+    // Make sure the prompt and completion fit in the context window
+    let (mut max_tokens, strategy) = max_tokens(&req.options, &resolved.base);
+    if let Err(e) = fit_chat_messages(
+        engine.as_ref(),
+        &resolved.base,
+        &mut messages,
+        &mut max_tokens,
+        strategy,
+    )
+    .await
+    {
+        return e.into_response();
+    }
+
+    let images = match load_request_images(
+        state.image_dir.as_deref(),
+        &resolved.base,
+        &req.model,
+        &messages,
+    ) {
+        Ok(images) => images,
+        Err(e) => return e.into_response(),
+    };
+
+    // Image embeddings are not part of the prompt text, so their KV state is not cached
+    let revision = images
+        .is_empty()
+        .then(|| resolved.base.metadata.cache.revision.clone());
+    let gen_req = GenerateRequest::new(
+        &resolved.base.name,
+        &format_chat_messages(&messages),
+        max_tokens,
+        req.options.temperature.unwrap_or(0.8),
+    )
+    .with_adapter(resolved.adapter.map(|a| a.name))
+    .with_revision(revision)
+    .with_images(images)
+    .with_thinking_budget(thinking_budget(req.think))
+    .with_tools(
+        req.tools
+            .iter()
+            .map(|tool| ToolDefinition {
+                name: tool.function.name.clone(),
+                description: tool.function.description.clone(),
+                parameters: tool.function.parameters.clone(),
+            })
+            .collect(),
+    );
+
+    let target = Target {
+        route: "/api/chat",
+        model: req.model,
+        stream: req.stream,
+    };
+    respond(engine, gen_req, timer, target, chat_line).await
+}
+
+fn chat_message(role: &str, content: MessageContent) -> ChatMessage {
+    ChatMessage {
+        role: role.to_string(),
+        content,
+        reasoning_content: None,
+    }
+}
+
+/// Message content with base64 encoded images as data URLs
+fn content(text: &str, images: &[String]) -> MessageContent {
+    if images.is_empty() {
+        return MessageContent::Text(text.to_string());
+    }
+
+    let mut parts: Vec<ContentPart> = images
+        .iter()
+        .map(|image| ContentPart::ImageUrl {
+            image_url: ImageUrl {
+                url: format!("data:;base64,{}", image),
+                detail: None,
+            },
+        })
+        .collect();
+    parts.push(ContentPart::Text {
+        text: text.to_string(),
+    });
+    MessageContent::Parts(parts)
+}
+
+/// Convert a chat message, tool calls are replayed the way the model emits them
+fn to_chat_message(message: &OllamaMessage) -> ChatMessage {
+    let mut text = message.content.clone();
+    for call in &message.tool_calls {
+        if !text.is_empty() {
+            text.push('\n');
+        }
+        text.push_str(&format_tool_call(
+            &call.function.name,
+            &call.function.arguments.to_string(),
+        ));
+    }
+    chat_message(&message.role, content(&text, &message.images))
+}
+
+/// Tokens to generate and how to fit them in the context window
+fn max_tokens(options: &OllamaOptions, model: &ModelInfo) -> (usize, TruncationStrategy) {
+    match options.num_predict {
+        Some(n) if n >= 0 => (n as usize, TruncationStrategy::Disabled),
+        // Generate up to the end of the context window
+        Some(_) => (
+            model
+                .metadata
+                .context_window
+                .map_or(DEFAULT_NUM_PREDICT, |window| window as usize),
+            TruncationStrategy::ClampMaxTokens,
+        ),
+        None => (DEFAULT_NUM_PREDICT, TruncationStrategy::Disabled),
+    }
+}
+
+fn thinking_budget(think: Option<bool>) -> Option<usize> {
+    think
+        .unwrap_or_default()
+        .then(|| effort_budget(ReasoningEffort::Medium))
+}
+
+/// Where the output of a generation goes
+struct Target {
+    route: &'static str,
+    model: String,
+    stream: bool,
+}
+
+/// One response line, the only one when not streaming
+#[derive(Default)]
+struct Line {
+    content: String,
+    thinking: Option<String>,
+    tool_calls: Vec<OllamaToolCall>,
+    done: Option<(&'static str, OllamaStats)>,
+}
+
+/// Renders a line as a generate or chat response
+type Render = fn(&str, Line) -> Value;
+
+fn generate_line(model: &str, line: Line) -> Value {
+    let (done_reason, stats) = line.done.unzip();
+    serde_json::to_value(OllamaGenerateResponse {
+        model: model.to_string(),
+        created_at: now(),
+        response: line.content,
+        thinking: line.thinking,
+        done: stats.is_some(),
+        done_reason: done_reason.map(str::to_string),
+        stats,
+    })
+    .unwrap()
+}
+
+fn chat_line(model: &str, line: Line) -> Value {
+    let (done_reason, stats) = line.done.unzip();
+    serde_json::to_value(OllamaChatResponse {
+        model: model.to_string(),
+        created_at: now(),
+        message: OllamaMessage {
+            role: "assistant".to_string(),
+            content: line.content,
+            thinking: line.thinking,
+            images: vec![],
+            tool_calls: line.tool_calls,
+        },
+        done: stats.is_some(),
+        done_reason: done_reason.map(str::to_string),
+        stats,
+    })
+    .unwrap()
+}
+
+fn done_reason(completion_tokens: usize, max_tokens: usize) -> &'static str {
+    if completion_tokens >= max_tokens {
+        "length"
+    } else {
+        "stop"
+    }
+}
+
+/// Ollama statistics from request timings, the model is loaded before serving
+fn stats(prompt_tokens: usize, completion_tokens: usize, timings: &Timings) -> OllamaStats {
+    let nanos = |ms: f64| (ms * 1_000_000.0) as u64;
+    let eval_duration = if timings.decode_tokens_per_second > 0.0 {
+        (completion_tokens as f64 / timings.decode_tokens_per_second * 1e9) as u64
+    } else {
+        0
+    };
+    OllamaStats {
+        total_duration: nanos(timings.total_time_ms),
+        load_duration: 0,
+        prompt_eval_count: prompt_tokens,
+        prompt_eval_duration: nanos(timings.time_to_first_token_ms),
+        eval_count: completion_tokens,
+        eval_duration,
+    }
+}
+
+fn tool_calls(text: &str, tools_enabled: bool) -> (Option<String>, String, Vec<OllamaToolCall>) {
+    let parsed = parse_output(text, tools_enabled);
+    let calls = parsed
+        .tool_calls
+        .into_iter()
+        .map(|call| OllamaToolCall {
+            function: OllamaFunctionCall {
+                name: call.name,
+                arguments: serde_json::from_str(&call.arguments).unwrap_or_else(|_| json!({})),
+            },
+        })
+        .collect();
+    (parsed.reasoning, parsed.text, calls)
+}
+
+/// Generate and answer with a single JSON object or NDJSON lines
+async fn respond<E: InferenceEngine + 'static>(
+    engine: Arc<E>,
+    gen_req: GenerateRequest,
+    mut timer: RequestTimer,
+    target: Target,
+    render: Render,
+) -> Response {
+    if target.stream {
+        return stream(engine, gen_req, timer, target, render);
+    }
+
+    timer.generation_started();
+    let response = match engine.generate(&gen_req).await {
+        Ok(response) => response,
+        Err(e) => return ApiError::from(e).into_response(),
+    };
+    let timings = timer.generate_timings(
+        response.prompt_tokens,
+        response.completion_tokens,
+        response.prompt_duration,
+        response.decode_duration,
+    );
+    timer.finish(
+        &timings,
+        target.route,
+        &target.model,
+        response.prompt_tokens,
+        response.completion_tokens,
+        done_reason(response.completion_tokens, gen_req.max_tokens),
+    );
+
+    let (thinking, content, tool_calls) = tool_calls(&response.text, !gen_req.tools.is_empty());
+    let line = Line {
+        content,
+        thinking,
+        tool_calls,
+        done: Some((
+            done_reason(response.completion_tokens, gen_req.max_tokens),
+            stats(response.prompt_tokens, response.completion_tokens, &timings),
+        )),
+    };
+    Json(render(&target.model, line)).into_response()
+}
+
+/// Stream lines of newline delimited JSON
+fn stream<E: InferenceEngine + 'static>(
+    engine: Arc<E>,
+    gen_req: GenerateRequest,
+    mut timer: RequestTimer,
+    target: Target,
+    render: Render,
+) -> Response {
+    let (tx, rx) = mpsc::channel::<Result<String, Infallible>>(100);
+
+    tokio::spawn(async move {
+        let send = |value: Value| {
+            let tx = tx.clone();
+            async move { tx.send(Ok(format!("{}\n", value))).await.is_ok() }
+        };
+
+        timer.generation_started();
+        // Token counts come from the engine, which counts images too
+        let (prompt_tokens, completion_tokens) = if gen_req.tools.is_empty() {
+            let mut stream = match engine.generate_stream(&gen_req).await {
+                Ok(stream) => stream,
+                Err(e) => {
+                    send(json!({ "error": e.to_string() })).await;
+                    return;
+                }
+            };
+
+            let mut parser = ReasoningParser::new();
+            let mut completion_tokens = 0;
+            let mut finished = false;
+            while !finished {
+                // Split reasoning out of each token, flushing the parser at the end
+                let delta = match stream.tokens.next().await {
+                    Some(token) => {
+                        timer.first_token();
+                        completion_tokens += 1;
+                        parser.push(&token)
+                    }
+                    None => {
+                        finished = true;
+                        parser.finish()
+                    }
+                };
+                if delta.reasoning.is_empty() && delta.content.is_empty() {
+                    continue;
+                }
+                let line = Line {
+                    content: delta.content,
+                    thinking: (!delta.reasoning.is_empty()).then_some(delta.reasoning),
+                    ..Line::default()
+                };
+                if !send(render(&target.model, line)).await {
+                    return;
+                }
+            }
+            (stream.prompt_tokens, completion_tokens)
+        } else {
+            // Tool calls can only be parsed from the complete output
+            let response = match engine.generate(&gen_req).await {
+                Ok(response) => response,
+                Err(e) => {
+                    send(json!({ "error": e.to_string() })).await;
+                    return;
+                }
+            };
+            timer.first_token();
+            let (thinking, content, tool_calls) = tool_calls(&response.text, true);
+            let line = Line {
+                content,
+                thinking,
+                tool_calls,
+                done: None,
+            };
+            if !send(render(&target.model, line)).await {
+                return;
+            }
+            (response.prompt_tokens, response.completion_tokens)
+        };
+
+        let timings = timer.stream_timings(prompt_tokens, completion_tokens);
+        timer.finish(
+            &timings,
+            target.route,
+            &target.model,
+            prompt_tokens,
+            completion_tokens,
+            done_reason(completion_tokens, gen_req.max_tokens),
+        );
+
+        let line = Line {
+            done: Some((
+                done_reason(completion_tokens, gen_req.max_tokens),
+                stats(prompt_tokens, completion_tokens, &timings),
+            )),
+            ..Line::default()
+        };
+        send(render(&target.model, line)).await;
+    });
+
+    ndjson(rx)
+}
+
+fn ndjson(rx: mpsc::Receiver<Result<String, Infallible>>) -> Response {
+    (
+        [(header::CONTENT_TYPE, "application/x-ndjson")],
+        Body::from_stream(ReceiverStream::new(rx)),
+    )
+        .into_response()
+}
+
+/// Details of a model, or of the base model of an adapter
+fn details(model: &ModelInfo, parent_model: Option<&str>) -> OllamaModelDetails {
+    let family = model.model_series.clone().unwrap_or_default();
+    let safetensors = model.metadata.safetensors.as_ref();
+
+    // The dtype holding most parameters stands in for the quantization level
+    let quantization_level = safetensors
+        .and_then(|s| s.get("parameters"))
+        .and_then(Value::as_object)
+        .and_then(|parameters| {
+            parameters
+                .iter()
+                .max_by_key(|(_, count)| count.as_u64().unwrap_or_default())
+                .map(|(dtype, _)| dtype.clone())
+        })
+        .unwrap_or_default();
+
+    OllamaModelDetails {
+        parent_model: parent_model.unwrap_or_default().to_string(),
+        format: "safetensors".to_string(),
+        families: if family.is_empty() {
+            vec![]
+        } else {
+            vec![family.clone()]
+        },
+        family,
+        parameter_size: safetensors
+            .and_then(|s| s.get("total"))
+            .and_then(Value::as_u64)
+            .map(format_parameters)
+            .unwrap_or_default(),
+        quantization_level,
+    }
+}
+
+/// List local models and adapters
+pub async fn tags<E: InferenceEngine + 'static>(State(state): State<AppState<E>>) -> Response {
+    let registry = state.registry;
+    let (models, adapters) = match registry
+        .load_models(None)
+        .and_then(|models| Ok((models, registry.load_adapters(None)?)))
+    {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            return error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("failed to load models: {}", e),
+            )
+        }
+    };
+
+    let mut list: Vec<OllamaModel> = models
+        .iter()
+        .map(|model| OllamaModel {
+            name: model.name.clone(),
+            model: model.name.clone(),
+            modified_at: model.updated_at.clone(),
+            size: model.metadata.cache.size,
+            digest: model.metadata.cache.revision.clone(),
+            details: details(model, None),
+        })
+        .collect();
+
+    // Adapters are listed as models of their own, pointing at their base
+    list.extend(adapters.into_iter().filter_map(|adapter| {
+        let base = models.iter().find(|m| m.name == adapter.base_model)?;
+        Some(OllamaModel {
+            name: adapter.name.clone(),
+            model: adapter.name,
+            modified_at: adapter.created_at,
+            size: base.metadata.cache.size,
+            digest: base.metadata.cache.revision.clone(),
+            details: details(base, Some(&base.name)),
+        })
+    }));
+
+    Json(OllamaModelList { models: list }).into_response()
+}
+
+/// Show the details and capabilities of a model
+pub async fn show<E: InferenceEngine + 'static>(
+    State(state): State<AppState<E>>,
+    ApiJson(req): ApiJson<OllamaModelRequest>,
+) -> Response {
+    let resolved = match state.registry.resolve_model(model_name(&req.model)) {
+        Ok(Some(resolved)) => resolved,
+        Ok(None) => {
+            return error(
+                StatusCode::NOT_FOUND,
+                format!("model '{}' not found", req.model),
+            )
+        }
+        Err(e) => {
+            return error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("failed to load model: {}", e),
+            )
+        }
+    };
+    let model = &resolved.base;
+    let details = details(
+        model,
+        resolved.adapter.as_ref().map(|a| a.base_model.as_str()),
+    );
+
+    let mut model_info = serde_json::Map::new();
+    model_info.insert("general.architecture".to_string(), json!(details.family));
+    if let Some(total) = model
+        .metadata
+        .safetensors
+        .as_ref()
+        .and_then(|s| s.get("total"))
+    {
+        model_info.insert("general.parameter_count".to_string(), total.clone());
+    }
+    if let Some(context_window) = model.metadata.context_window {
+        model_info.insert(
+            format!("{}.context_length", details.family),
+            json!(context_window),
+        );
+    }
+
+    let mut capabilities = vec!["completion".to_string()];
+    if model.task.as_deref() == Some("image-text-to-text")
+        && supports_vision(model.model_series.as_deref())
+    {
+        capabilities.push("vision".to_string());
+    }
+    if state
+        .fim_tokens
+        .get(
+            Path::new(&model.metadata.cache.path),
+            &model.metadata.cache.revision,
+        )
+        .is_some()
+    {
+        capabilities.push("insert".to_string());
+    }
+
+    Json(OllamaShowResponse {
+        license: model.license.clone().unwrap_or_default(),
+        modelfile: String::new(),
+        parameters: String::new(),
+        template: String::new(),
+        details,
+        model_info,
+        capabilities,
+        modified_at: model.updated_at.clone(),
+    })
+    .into_response()
+}
+
+/// Pull a model from Hugging Face, registering it in the default registry
+pub async fn pull(ApiJson(req): ApiJson<OllamaPullRequest>) -> Response {
+    // Lowercase names keep caching and registry entries consistent, as in `puma pull`
+    let name = model_name(&req.model).to_lowercase();
+
+    if !req.stream {
+        return match HuggingFaceDownloader::new().download_model(&name).await {
+            Ok(()) => Json(OllamaStatus {
+                status: "success".to_string(),
+            })
+            .into_response(),
+            Err(e) => error(download_status(&e), e.to_string()),
+        };
+    }
+
+    // Per-file progress, as Ollama reports each layer it pulls
+    let lines = pull_events(name).filter_map(|event| async move {
+        let line = match event {
+            Ok(DownloadEvent::PullingManifest { .. }) => json!({ "status": "pulling manifest" }),
+            Ok(DownloadEvent::Manifest { .. }) => return None,
+            Ok(DownloadEvent::Downloading {
+                file,
+                completed,
+                total,
+            }) => json!({
+                "status": format!("pulling {}", file),
+                "digest": file,
+                "total": total,
+                "completed": completed,
+            }),
+            Ok(DownloadEvent::Downloaded { file, total }) => json!({
+                "status": format!("pulling {}", file),
+                "digest": file,
+                "total": total,
+                "completed": total,
+            }),
+            Ok(DownloadEvent::Success { .. }) => json!({ "status": "success" }),
+            Err(e) => json!({ "error": e.message }),
+        };
+        Some(Ok::<_, Infallible>(format!("{}\n", line)))
+    });
+
+    (
+        [(header::CONTENT_TYPE, "application/x-ndjson")],
+        Body::from_stream(lines),
+    )
+        .into_response()
+}
+
+fn download_status(error: &DownloadError) -> StatusCode {
+    match error {
+        DownloadError::ModelNotFound(_) => StatusCode::NOT_FOUND,
+        DownloadError::AuthError(_) => StatusCode::UNAUTHORIZED,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// Delete a model and its files, or an adapter registration
+pub async fn delete_model<E: InferenceEngine + 'static>(
+    State(state): State<AppState<E>>,
+    ApiJson(req): ApiJson<OllamaModelRequest>,
+) -> Response {
+    let name = model_name(&req.model);
+    let registry = &state.registry;
+
+    let result = match (registry.get_model(name), registry.get_adapter(name)) {
+        // The registry matches names ignoring case, compare the registered name
+        (Ok(Some(model)), _) if state.readiness.is_serving(&model.name) => {
+            return error(
+                StatusCode::CONFLICT,
+                format!(
+                    "model '{}' is being served and cannot be deleted",
+                    model.name
+                ),
+            )
+        }
+        (Ok(Some(model)), _) => registry.remove_model(&model.name),
+        (Ok(None), Ok(Some(_))) => registry.unregister_adapter(name),
+        (Ok(None), Ok(None)) => {
+            return error(
+                StatusCode::NOT_FOUND,
+                format!("model '{}' not found", req.model),
+            )
+        }
+        (Err(e), _) | (_, Err(e)) => Err(e),
+    };
+
+    match result {
+        Ok(()) => StatusCode::OK.into_response(),
+        Err(e) => error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("failed to delete model: {}", e),
+        ),
+    }
+}
+
+/// List the models loaded in memory, the served model once it is ready
+pub async fn ps<E: InferenceEngine + 'static>(State(state): State<AppState<E>>) -> Response {
+    let status = state.readiness.status();
+    let served = match status.model {
+        Some(name) if status.status == ReadinessPhase::Ready => state.registry.get_model(&name),
+        _ => Ok(None),
+    };
+
+    match served {
+        Ok(served) => Json(OllamaRunningModelList {
+            models: served
+                .into_iter()
+                .map(|model| OllamaRunningModel {
+                    name: model.name.clone(),
+                    model: model.name.clone(),
+                    size: model.metadata.cache.size,
+                    digest: model.metadata.cache.revision.clone(),
+                    details: details(&model, None),
+                    // Apple silicon shares memory between CPU and GPU
+                    size_vram: model.metadata.cache.size,
+                })
+                .collect(),
+        })
+        .into_response(),
+        Err(e) => error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("failed to load model: {}", e),
+        ),
+    }
+}
+
+/// Version of the server, clients probe it to detect an Ollama API
+pub async fn version() -> Json<Value> {
+    Json(json!({ "version": env!("CARGO_PKG_VERSION") }))
+}
@@ -0,0 +1,34 @@
+use crate::api::error::ApiError;
+
+/// Check the sampling parameters of a completion request against the ranges
+/// OpenAI accepts, reporting the first one out of range
+pub fn validate_sampling(
+    temperature: Option<f32>,
+    top_p: Option<f32>,
+    max_tokens: Option<usize>,
+    n: Option<usize>,
+) -> Result<(), ApiError> {
+    if let Some(temperature) = temperature.filter(|t| !(0.0..=2.0).contains(t)) {
+        return Err(out_of_range("temperature", temperature, "between 0 and 2"));
+    }
+    if let Some(top_p) = top_p.filter(|p| !(0.0..=1.0).contains(p)) {
+        return Err(out_of_range("top_p", top_p, "between 0 and 1"));
+    }
+    if max_tokens == Some(0) {
+        return Err(out_of_range("max_tokens", 0, "at least 1"));
+    }
+    if n == Some(0) {
+        return Err(out_of_range("n", 0, "at least 1"));
+    }
+    Ok(())
+}
+
+fn out_of_range(param: &str, value: impl std::fmt::Display, range: &str) -> ApiError {
+    ApiError::invalid_value(
+        param,
+        format!(
+            "Invalid '{}': expected a value {}, but got {} instead.",
+            param, range, value
+        ),
+    )
+}
@@ -0,0 +1,233 @@
+use axum::{
+    body::{Body, HttpBody},
+    extract::{Request, State},
+    http::{HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use futures::stream::StreamExt;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tracing::{debug, info, warn};
+
+use crate::api::error::ApiError;
+use crate::api::timings::RequestReceived;
+
+/// Snapshot of the queue, for logs and metrics
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QueueStats {
+    /// Requests holding a generation slot
+    pub in_flight: usize,
+    /// Requests waiting for a slot
+    pub queued: usize,
+    pub max_concurrency: usize,
+    pub max_queue_depth: usize,
+    /// Requests rejected because the queue was full, since startup
+    pub rejected: u64,
+}
+
+/// Bounds the number of concurrent generations. Requests over capacity wait
+/// in a FIFO queue, and are rejected right away once the queue is full.
+#[derive(Debug)]
+pub struct RequestQueue {
+    slots: Arc<Semaphore>,
+    max_concurrency: usize,
+    max_queue_depth: usize,
+    queued: AtomicUsize,
+    rejected: AtomicU64,
+}
+
+/// A generation slot, released when dropped
+#[derive(Debug)]
+pub struct QueuePermit {
+    _permit: OwnedSemaphorePermit,
+    /// Position in the queue on arrival, 0 if a slot was free
+    pub position: usize,
+}
+
+/// Decrements the queue length when a waiting request leaves the queue,
+/// including when its client disconnects
+struct Waiting<'a>(&'a AtomicUsize);
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl RequestQueue {
+    pub fn new(max_concurrency: usize, max_queue_depth: usize) -> Self {
+        let max_concurrency = max_concurrency.clamp(1, Semaphore::MAX_PERMITS);
+        Self {
+            slots: Arc::new(Semaphore::new(max_concurrency)),
+            max_concurrency,
+            max_queue_depth,
+            queued: AtomicUsize::new(0),
+            rejected: AtomicU64::new(0),
+        }
+    }
+
+    /// A queue that never makes requests wait
+    pub fn unbounded() -> Self {
+        Self::new(Semaphore::MAX_PERMITS, usize::MAX)
+    }
+
+    /// Wait for a generation slot, fails fast if the queue is full
+    pub async fn acquire(&self) -> Result<QueuePermit, ApiError> {
+        if let Ok(permit) = self.slots.clone().try_acquire_owned() {
+            return Ok(QueuePermit {
+                _permit: permit,
+                position: 0,
+            });
+        }
+
+        let position = self.queued.fetch_add(1, Ordering::SeqCst) + 1;
+        let waiting = Waiting(&self.queued);
+        if position > self.max_queue_depth {
+            drop(waiting);
+            self.rejected.fetch_add(1, Ordering::Relaxed);
+            return Err(ApiError::service_unavailable(format!(
+                "The server is overloaded: {} requests in flight and {} queued. Please try again later.",
+                self.max_concurrency, self.max_queue_depth
+            ))
+            .with_code("server_overloaded"));
+        }
+
+        let permit = self
+            .slots
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("queue semaphore is never closed");
+        drop(waiting);
+        Ok(QueuePermit {
+            _permit: permit,
+            position,
+        })
+    }
+
+    pub fn stats(&self) -> QueueStats {
+        QueueStats {
+            in_flight: self.max_concurrency - self.slots.available_permits(),
+            queued: self.queued.load(Ordering::SeqCst),
+            max_concurrency: self.max_concurrency,
+            max_queue_depth: self.max_queue_depth,
+            rejected: self.rejected.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Middleware holding generation requests until a slot is free. The slot is
+/// kept until the response body is sent, which for streamed responses is
+/// when generation ends.
+pub async fn queued(
+    State(queue): State<Arc<RequestQueue>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let received = Instant::now();
+    let route = request.uri().path().to_string();
+    request.extensions_mut().insert(RequestReceived(received));
+
+    let permit = match queue.acquire().await {
+        Ok(permit) => permit,
+        Err(error) => {
+            let stats = queue.stats();
+            warn!(
+                target: "puma::queue",
+                route,
+                in_flight = stats.in_flight,
+                queued = stats.queued,
+                "request rejected, queue is full"
+            );
+            let mut response = error.into_response();
+            response
+                .headers_mut()
+                .insert("retry-after", HeaderValue::from(1));
+            return response;
+        }
+    };
+
+    if permit.position > 0 {
+        info!(
+            target: "puma::queue",
+            route,
+            position = permit.position,
+            wait_ms = received.elapsed().as_secs_f64() * 1000.0,
+            "request dequeued"
+        );
+    } else {
+        debug!(target: "puma::queue", route, "request admitted");
+    }
+
+    let response = next.run(request).await;
+
+    // Complete bodies are already generated, streamed ones still generate
+    if response.body().size_hint().exact().is_some() || response.status() != StatusCode::OK {
+        return response;
+    }
+    let (parts, body) = response.into_parts();
+    let body = body.into_data_stream().map(move |chunk| {
+        let _slot = &permit;
+        chunk
+    });
+    Response::from_parts(parts, Body::from_stream(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_queue_admits_up_to_concurrency() {
+        let queue = Arc::new(RequestQueue::new(1, 1));
+        let first = queue.acquire().await.unwrap();
+        assert_eq!(first.position, 0);
+
+        // The second request waits in the queue, the third is rejected
+        let waiting = tokio::spawn({
+            let queue = queue.clone();
+            async move { queue.acquire().await.map(|permit| permit.position) }
+        });
+        while queue.stats().queued == 0 {
+            tokio::task::yield_now().await;
+        }
+        let error = queue.acquire().await.unwrap_err();
+        assert_eq!(error.status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(error.code.as_deref(), Some("server_overloaded"));
+
+        let stats = queue.stats();
+        assert_eq!((stats.in_flight, stats.queued, stats.rejected), (1, 1, 1));
+
+        drop(first);
+        assert_eq!(waiting.await.unwrap().unwrap(), 1);
+        assert_eq!(queue.stats().queued, 0);
+    }
+
+    #[tokio::test]
+    async fn test_queue_cancelled_wait() {
+        let queue = Arc::new(RequestQueue::new(1, 4));
+        let _first = queue.acquire().await.unwrap();
+
+        // A client disconnecting while queued leaves the queue
+        let waiting = tokio::spawn({
+            let queue = queue.clone();
+            async move { queue.acquire().await.map(|_| ()) }
+        });
+        while queue.stats().queued == 0 {
+            tokio::task::yield_now().await;
+        }
+        waiting.abort();
+        let _ = waiting.await;
+        assert_eq!(queue.stats().queued, 0);
+    }
+
+    #[test]
+    fn test_unbounded_queue() {
+        let stats = RequestQueue::unbounded().stats();
+        assert_eq!(stats.in_flight, 0);
+        assert_eq!(stats.max_queue_depth, usize::MAX);
+    }
+}
@@ -0,0 +1,478 @@
+use axum::{
+    extract::{Request, State},
+    http::{HeaderMap, HeaderName, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::collections::{HashMap, VecDeque};
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+use crate::api::auth::ApiKeyIdentity;
+use crate::api::error::ApiError;
+use crate::api::usage::{GenerationUsage, UsageObserver, UsageRecorder};
+use crate::storage::{ApiKeyStorage, SqliteStorage};
+
+/// Length of the sliding window of per-minute limits
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// Limits of an API key, unset limits are not enforced
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RateLimits {
+    pub requests_per_minute: Option<u64>,
+    pub tokens_per_minute: Option<u64>,
+    /// Prompt and completion tokens per UTC day
+    pub daily_tokens: Option<u64>,
+}
+
+impl RateLimits {
+    /// Fill the unset limits from `defaults`
+    pub fn or(self, defaults: RateLimits) -> Self {
+        Self {
+            requests_per_minute: self.requests_per_minute.or(defaults.requests_per_minute),
+            tokens_per_minute: self.tokens_per_minute.or(defaults.tokens_per_minute),
+            daily_tokens: self.daily_tokens.or(defaults.daily_tokens),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Requests and tokens of a key in the last minute
+#[derive(Debug, Default)]
+struct Window {
+    requests: VecDeque<Instant>,
+    tokens: VecDeque<(Instant, u64)>,
+}
+
+impl Window {
+    fn prune(&mut self, now: Instant) {
+        while self
+            .requests
+            .front()
+            .is_some_and(|t| now.duration_since(*t) >= WINDOW)
+        {
+            self.requests.pop_front();
+        }
+        while self
+            .tokens
+            .front()
+            .is_some_and(|(t, _)| now.duration_since(*t) >= WINDOW)
+        {
+            self.tokens.pop_front();
+        }
+    }
+
+    fn used_tokens(&self) -> u64 {
+        self.tokens.iter().map(|(_, tokens)| tokens).sum()
+    }
+
+    /// Time until the requests drop below `limit`
+    fn requests_retry(&self, now: Instant, limit: u64) -> Duration {
+        let excess = (self.requests.len() as u64 + 1).saturating_sub(limit) as usize;
+        match excess.checked_sub(1).and_then(|i| self.requests.get(i)) {
+            Some(t) => WINDOW.saturating_sub(now.duration_since(*t)),
+            None => Duration::ZERO,
+        }
+    }
+
+    /// Time until the tokens drop below `limit`
+    fn tokens_retry(&self, now: Instant, limit: u64) -> Duration {
+        let mut used = self.used_tokens();
+        for (t, tokens) in &self.tokens {
+            if used < limit {
+                break;
+            }
+            used -= tokens;
+            if used < limit {
+                return WINDOW.saturating_sub(now.duration_since(*t));
+            }
+        }
+        Duration::ZERO
+    }
+
+    /// Time until the window is empty again
+    fn reset(&self, now: Instant) -> Duration {
+        let newest = [
+            self.requests.back().copied(),
+            self.tokens.back().map(|(t, _)| *t),
+        ];
+        newest
+            .into_iter()
+            .flatten()
+            .max()
+            .map(|t| WINDOW.saturating_sub(now.duration_since(t)))
+            .unwrap_or_default()
+    }
+}
+
+/// State of one limit, reported in `x-ratelimit-*` headers
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct LimitState {
+    limit: u64,
+    remaining: u64,
+    /// Time until the limit is back to its initial state
+    reset: Duration,
+    /// Time until the limit allows requests again
+    retry_after: Duration,
+}
+
+impl LimitState {
+    fn exceeded(&self) -> bool {
+        self.remaining == 0
+    }
+}
+
+/// Outcome of checking a request against the limits of its key
+#[derive(Debug, Default)]
+struct Check {
+    requests: Option<LimitState>,
+    tokens: Option<LimitState>,
+    daily_tokens: Option<LimitState>,
+    /// Error and retry delay of a rejected request
+    rejected: Option<(ApiError, Duration)>,
+}
+
+impl Check {
+    /// The error of the first exceeded limit, if any
+    fn exceeded(&self) -> Option<(ApiError, Duration)> {
+        if let Some(state) = self.requests.filter(LimitState::exceeded) {
+            let error = ApiError::new(
+                StatusCode::TOO_MANY_REQUESTS,
+                "requests",
+                format!(
+                    "Rate limit reached for requests per minute (RPM): Limit {}. Please try again in {}.",
+                    state.limit,
+                    format_duration(state.retry_after)
+                ),
+            );
+            return Some((error.with_code("rate_limit_exceeded"), state.retry_after));
+        }
+        if let Some(state) = self.tokens.filter(LimitState::exceeded) {
+            let error = ApiError::new(
+                StatusCode::TOO_MANY_REQUESTS,
+                "tokens",
+                format!(
+                    "Rate limit reached for tokens per minute (TPM): Limit {}. Please try again in {}.",
+                    state.limit,
+                    format_duration(state.retry_after)
+                ),
+            );
+            return Some((error.with_code("rate_limit_exceeded"), state.retry_after));
+        }
+        if let Some(state) = self.daily_tokens.filter(LimitState::exceeded) {
+            let error = ApiError::new(
+                StatusCode::TOO_MANY_REQUESTS,
+                "insufficient_quota",
+                format!(
+                    "You exceeded your daily quota of {} tokens. Please try again in {}.",
+                    state.limit,
+                    format_duration(state.retry_after)
+                ),
+            );
+            return Some((error.with_code("insufficient_quota"), state.retry_after));
+        }
+        None
+    }
+
+    fn add_headers(&self, headers: &mut HeaderMap) {
+        let limits = [
+            ("requests", self.requests),
+            ("tokens", self.tokens),
+            ("tokens-day", self.daily_tokens),
+        ];
+        for (name, state) in limits {
+            let Some(state) = state else { continue };
+            let values = [
+                ("limit", state.limit.to_string()),
+                ("remaining", state.remaining.to_string()),
+                ("reset", format_duration(state.reset)),
+            ];
+            for (field, value) in values {
+                let name = format!("x-ratelimit-{}-{}", field, name);
+                if let (Ok(name), Ok(value)) =
+                    (HeaderName::try_from(name), HeaderValue::try_from(value))
+                {
+                    headers.insert(name, value);
+                }
+            }
+        }
+    }
+}
+
+/// Per-key request and token limits. Per-minute limits are kept in memory
+/// over a sliding window, daily token usage is persisted so quotas survive
+/// restarts.
+pub struct RateLimiter {
+    defaults: RateLimits,
+    storage: Arc<SqliteStorage>,
+    windows: Mutex<HashMap<String, Window>>,
+}
+
+impl RateLimiter {
+    /// Create a limiter applying `defaults` to keys without limits of their own
+    pub fn new(defaults: RateLimits, storage: Arc<SqliteStorage>) -> Self {
+        Self {
+            defaults,
+            storage,
+            windows: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Check a request of a key, counting it unless a limit is exceeded
+    fn check(&self, key_id: &str, limits: RateLimits) -> io::Result<Check> {
+        let now = Instant::now();
+        let daily_tokens = match limits.daily_tokens {
+            Some(limit) => {
+                let used = self.storage.daily_tokens(key_id, &today())?;
+                Some(LimitState {
+                    limit,
+                    remaining: limit.saturating_sub(used),
+                    reset: until_midnight(),
+                    retry_after: until_midnight(),
+                })
+            }
+            None => None,
+        };
+
+        let mut windows = self.windows.lock().unwrap();
+        let window = windows.entry(key_id.to_string()).or_default();
+        window.prune(now);
+
+        let mut check = Check {
+            requests: limits.requests_per_minute.map(|limit| LimitState {
+                limit,
+                remaining: limit.saturating_sub(window.requests.len() as u64),
+                reset: window.reset(now),
+                retry_after: window.requests_retry(now, limit),
+            }),
+            tokens: limits.tokens_per_minute.map(|limit| LimitState {
+                limit,
+                remaining: limit.saturating_sub(window.used_tokens()),
+                reset: window.reset(now),
+                retry_after: window.tokens_retry(now, limit),
+            }),
+            daily_tokens,
+            rejected: None,
+        };
+
+        check.rejected = check.exceeded();
+        if check.rejected.is_none() {
+            window.requests.push_back(now);
+            if let Some(requests) = &mut check.requests {
+                requests.remaining -= 1;
+                requests.reset = WINDOW;
+            }
+        }
+        Ok(check)
+    }
+
+    /// Count the tokens of a finished generation
+    fn add_tokens(&self, key_id: &str, tokens: u64) {
+        self.windows
+            .lock()
+            .unwrap()
+            .entry(key_id.to_string())
+            .or_default()
+            .tokens
+            .push_back((Instant::now(), tokens));
+
+        if let Err(e) = self.storage.add_daily_tokens(key_id, &today(), tokens) {
+            warn!(
+                "Failed to record daily token usage of key {}: {}",
+                key_id, e
+            );
+        }
+    }
+}
+
+/// Counts the tokens of a key's generations towards its limits
+struct KeyUsage {
+    limiter: Arc<RateLimiter>,
+    key_id: String,
+}
+
+impl UsageObserver for KeyUsage {
+    fn observe(&self, usage: &GenerationUsage) {
+        let tokens = usage.prompt_tokens + usage.completion_tokens;
+        self.limiter.add_tokens(&self.key_id, tokens as u64);
+    }
+}
+
+/// Middleware enforcing the limits of the authenticated key. Requests over a
+/// limit get a 429 with `retry-after`, every response of a limited key
+/// carries `x-ratelimit-*` headers.
+pub async fn rate_limit(
+    State(limiter): State<Arc<RateLimiter>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let Some(key) = request.extensions().get::<ApiKeyIdentity>().cloned() else {
+        return next.run(request).await;
+    };
+    let limits = key.limits.or(limiter.defaults);
+    if limits.is_empty() {
+        return next.run(request).await;
+    }
+
+    let check = match limiter.check(&key.id, limits) {
+        Ok(check) => check,
+        Err(e) => {
+            return ApiError::server_error(format!("Failed to check rate limits: {}", e))
+                .into_response()
+        }
+    };
+
+    let mut response = match check.rejected.clone() {
+        Some((error, retry_after)) => {
+            warn!("Rate limit exceeded for key {}: {}", key.name, error);
+            let mut response = error.into_response();
+            let seconds = retry_after.as_secs_f64().ceil().max(1.0) as u64;
+            response
+                .headers_mut()
+                .insert("retry-after", HeaderValue::from(seconds));
+            response
+        }
+        None => {
+            UsageRecorder::observe(
+                &mut request,
+                Arc::new(KeyUsage {
+                    limiter: limiter.clone(),
+                    key_id: key.id.clone(),
+                }),
+            );
+            next.run(request).await
+        }
+    };
+    check.add_headers(response.headers_mut());
+    response
+}
+
+fn today() -> String {
+    chrono::Utc::now().format("%Y-%m-%d").to_string()
+}
+
+/// Time until daily quotas reset, at midnight UTC
+fn until_midnight() -> Duration {
+    let now = chrono::Utc::now();
+    let midnight = (now.date_naive() + chrono::Days::new(1))
+        .and_hms_opt(0, 0, 0)
+        .unwrap()
+        .and_utc();
+    (midnight - now).to_std().unwrap_or_default()
+}
+
+/// Format a duration the way OpenAI reset headers do, e.g. `1m30s` or `250ms`
+fn format_duration(duration: Duration) -> String {
+    let millis = duration.as_millis();
+    if millis < 1000 {
+        return format!("{}ms", millis);
+    }
+
+    let secs = duration.as_secs_f64().ceil() as u64;
+    let (hours, minutes, secs) = (secs / 3600, secs % 3600 / 60, secs % 60);
+    match (hours, minutes) {
+        (0, 0) => format!("{}s", secs),
+        (0, _) => format!("{}m{}s", minutes, secs),
+        _ => format!("{}h{}m{}s", hours, minutes, secs),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn limiter(defaults: RateLimits) -> (RateLimiter, TempDir) {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = SqliteStorage::new(temp_dir.path().join("test.db")).unwrap();
+        (RateLimiter::new(defaults, Arc::new(storage)), temp_dir)
+    }
+
+    #[test]
+    fn test_requests_per_minute() {
+        let limits = RateLimits {
+            requests_per_minute: Some(2),
+            ..Default::default()
+        };
+        let (limiter, _temp_dir) = limiter(limits);
+
+        let check = limiter.check("key_1", limits).unwrap();
+        assert!(check.rejected.is_none());
+        assert_eq!(check.requests.unwrap().remaining, 1);
+        assert!(limiter.check("key_1", limits).unwrap().rejected.is_none());
+
+        let check = limiter.check("key_1", limits).unwrap();
+        let (error, retry_after) = check.rejected.unwrap();
+        assert_eq!(error.status, StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(error.code.as_deref(), Some("rate_limit_exceeded"));
+        assert!(retry_after > Duration::from_secs(55));
+
+        // Keys are limited independently
+        assert!(limiter.check("key_2", limits).unwrap().rejected.is_none());
+    }
+
+    #[test]
+    fn test_tokens_per_minute() {
+        let limits = RateLimits {
+            tokens_per_minute: Some(100),
+            ..Default::default()
+        };
+        let (limiter, _temp_dir) = limiter(limits);
+
+        assert!(limiter.check("key_1", limits).unwrap().rejected.is_none());
+        limiter.add_tokens("key_1", 60);
+        let check = limiter.check("key_1", limits).unwrap();
+        assert_eq!(check.tokens.unwrap().remaining, 40);
+        limiter.add_tokens("key_1", 60);
+
+        let (error, _) = limiter.check("key_1", limits).unwrap().rejected.unwrap();
+        assert_eq!(error.error_type, "tokens");
+    }
+
+    #[test]
+    fn test_daily_tokens_persisted() {
+        let limits = RateLimits {
+            daily_tokens: Some(50),
+            ..Default::default()
+        };
+        let (limiter, temp_dir) = limiter(limits);
+        limiter.add_tokens("key_1", 50);
+
+        // A new limiter, as after a restart, still sees the used quota
+        let storage = SqliteStorage::new(temp_dir.path().join("test.db")).unwrap();
+        let limiter = RateLimiter::new(limits, Arc::new(storage));
+        let (error, retry_after) = limiter.check("key_1", limits).unwrap().rejected.unwrap();
+        assert_eq!(error.code.as_deref(), Some("insufficient_quota"));
+        assert!(retry_after <= Duration::from_secs(24 * 3600));
+    }
+
+    #[test]
+    fn test_rate_limits_or() {
+        let key = RateLimits {
+            requests_per_minute: Some(10),
+            ..Default::default()
+        };
+        let defaults = RateLimits {
+            requests_per_minute: Some(60),
+            tokens_per_minute: Some(1000),
+            daily_tokens: None,
+        };
+        let limits = key.or(defaults);
+        assert_eq!(limits.requests_per_minute, Some(10));
+        assert_eq!(limits.tokens_per_minute, Some(1000));
+        assert!(RateLimits::default().is_empty());
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(Duration::from_millis(250)), "250ms");
+        assert_eq!(format_duration(Duration::from_millis(1500)), "2s");
+        assert_eq!(format_duration(Duration::from_secs(90)), "1m30s");
+        assert_eq!(format_duration(Duration::from_secs(3600)), "1h0m0s");
+    }
+}
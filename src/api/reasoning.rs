@@ -0,0 +1,158 @@
+use crate::api::types::{ChatCompletionRequest, ReasoningEffort};
+
+const THINK_START: &str = "<think>";
+const THINK_END: &str = "</think>";
+
+/// Reasoning and answer text split out of a chunk of generated text
+#[derive(Debug, Default, PartialEq)]
+pub struct ReasoningDelta {
+    pub reasoning: String,
+    pub content: String,
+}
+
+/// Incrementally splits `<think>...</think>` blocks out of generated text.
+/// Tags split across chunks are buffered until they can be resolved.
+#[derive(Debug, Default)]
+pub struct ReasoningParser {
+    in_reasoning: bool,
+    saw_reasoning: bool,
+    content_started: bool,
+    buffer: String,
+}
+
+impl ReasoningParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed the next chunk of generated text
+    pub fn push(&mut self, chunk: &str) -> ReasoningDelta {
+        self.buffer.push_str(chunk);
+        let mut delta = ReasoningDelta::default();
+
+        loop {
+            let tag = if self.in_reasoning {
+                THINK_END
+            } else {
+                THINK_START
+            };
+
+            if let Some(pos) = self.buffer.find(tag) {
+                let text: String = self.buffer.drain(..pos + tag.len()).collect();
+                self.emit(&text[..pos], &mut delta);
+                self.in_reasoning = !self.in_reasoning;
+                self.saw_reasoning = true;
+                continue;
+            }
+
+            // Hold back a trailing partial tag until the next chunk arrives
+            let keep = (1..tag.len())
+                .rev()
+                .find(|&k| self.buffer.ends_with(&tag[..k]))
+                .unwrap_or(0);
+            let text: String = self.buffer.drain(..self.buffer.len() - keep).collect();
+            self.emit(&text, &mut delta);
+            return delta;
+        }
+    }
+
+    /// Flush any buffered text at the end of generation
+    pub fn finish(&mut self) -> ReasoningDelta {
+        let mut delta = ReasoningDelta::default();
+        let text = std::mem::take(&mut self.buffer);
+        self.emit(&text, &mut delta);
+        delta
+    }
+
+    fn emit(&mut self, text: &str, delta: &mut ReasoningDelta) {
+        if self.in_reasoning {
+            delta.reasoning.push_str(text);
+            return;
+        }
+
+        // Drop the whitespace models put between the reasoning and the answer
+        let text = if self.saw_reasoning && !self.content_started {
+            text.trim_start()
+        } else {
+            text
+        };
+        if !text.is_empty() {
+            self.content_started = true;
+            delta.content.push_str(text);
+        }
+    }
+}
+
+/// Split a complete generation into reasoning (if any) and answer
+pub fn split_reasoning(text: &str) -> (Option<String>, String) {
+    let mut parser = ReasoningParser::new();
+    let mut delta = parser.push(text);
+    let rest = parser.finish();
+    delta.reasoning.push_str(&rest.reasoning);
+    delta.content.push_str(&rest.content);
+
+    let reasoning = delta.reasoning.trim().to_string();
+    let reasoning = (!reasoning.is_empty()).then_some(reasoning);
+    (reasoning, delta.content)
+}
+
+/// Thinking token budget requested by a chat completion, if any.
+/// An explicit `thinking_budget` takes precedence over `reasoning_effort`.
+pub fn thinking_budget(req: &ChatCompletionRequest) -> Option<usize> {
+    req.thinking_budget
+        .or(req.reasoning_effort.map(effort_budget))
+}
+
+/// Thinking budget in tokens for a reasoning effort
+pub fn effort_budget(effort: ReasoningEffort) -> usize {
+    match effort {
+        ReasoningEffort::Low => 1024,
+        ReasoningEffort::Medium => 4096,
+        ReasoningEffort::High => 16384,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_reasoning() {
+        let (reasoning, content) = split_reasoning("<think>\nLet me think.\n</think>\n\nHello!");
+        assert_eq!(reasoning.as_deref(), Some("Let me think."));
+        assert_eq!(content, "Hello!");
+    }
+
+    #[test]
+    fn test_split_reasoning_without_think_block() {
+        let (reasoning, content) = split_reasoning("Hello!");
+        assert!(reasoning.is_none());
+        assert_eq!(content, "Hello!");
+    }
+
+    #[test]
+    fn test_parser_tags_split_across_chunks() {
+        let mut parser = ReasoningParser::new();
+        let mut reasoning = String::new();
+        let mut content = String::new();
+
+        for chunk in ["<th", "ink>Step ", "one.</", "think>", " Answer", "."] {
+            let delta = parser.push(chunk);
+            reasoning.push_str(&delta.reasoning);
+            content.push_str(&delta.content);
+        }
+        let delta = parser.finish();
+        reasoning.push_str(&delta.reasoning);
+        content.push_str(&delta.content);
+
+        assert_eq!(reasoning, "Step one.");
+        assert_eq!(content, "Answer.");
+    }
+
+    #[test]
+    fn test_parser_flushes_partial_tag_on_finish() {
+        let mut parser = ReasoningParser::new();
+        assert_eq!(parser.push("a <").content, "a ");
+        assert_eq!(parser.finish().content, "<");
+    }
+}
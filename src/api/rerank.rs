@@ -0,0 +1,92 @@
+use axum::{extract::State, Json};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+use crate::api::error::ApiError;
+use crate::api::extract::ApiJson;
+use crate::api::models::resolve_model;
+use crate::api::routes::AppState;
+use crate::api::timings::RequestTimer;
+use crate::api::types::{
+    RerankRequest, RerankResponse, RerankResult, RerankResultDocument, RerankUsage,
+};
+use crate::backend::InferenceEngine;
+
+/// Registry task of cross-encoder models that can rerank documents
+pub const RERANK_TASK: &str = "text-ranking";
+
+/// Handler for scoring documents against a query. Its tokens count towards
+/// the usage of the request like those of generations.
+pub async fn rerank<E: InferenceEngine + 'static>(
+    State(state): State<AppState<E>>,
+    mut timer: RequestTimer,
+    ApiJson(req): ApiJson<RerankRequest>,
+) -> Result<Json<RerankResponse>, ApiError> {
+    if req.documents.is_empty() {
+        return Err(ApiError::invalid_request("documents cannot be empty").with_param("documents"));
+    }
+
+    let resolved = resolve_model(&state, &req.model).await?;
+
+    if resolved.base.task.as_deref() != Some(RERANK_TASK) {
+        return Err(ApiError::invalid_request(format!(
+            "Model '{}' does not support reranking, expected task '{}'",
+            req.model, RERANK_TASK
+        ))
+        .with_code("model_not_supported")
+        .with_param("model"));
+    }
+
+    let documents: Vec<String> = req.documents.iter().map(|d| d.text().to_string()).collect();
+    timer.generation_started();
+    let started = Instant::now();
+    let scores = state
+        .engine
+        .rerank(&resolved.base.name, &req.query, &documents)
+        .await
+        .map_err(|e| ApiError::server_error(format!("Failed to rerank: {}", e)))?;
+
+    // Each document is scored together with the query
+    let mut total_tokens = 0;
+    let mut query_tokens = None;
+    for text in std::iter::once(&req.query).chain(&documents) {
+        let count = state
+            .engine
+            .tokenize(&resolved.base.name, text)
+            .await
+            .map_err(|e| ApiError::server_error(format!("Failed to tokenize: {}", e)))?
+            .len();
+        match query_tokens {
+            None => query_tokens = Some(count),
+            Some(query_tokens) => total_tokens += query_tokens + count,
+        }
+    }
+
+    // Scoring is all prefill, nothing is decoded
+    let timings = timer.generate_timings(total_tokens, 0, started.elapsed(), Duration::ZERO);
+    timer.finish(&timings, "/v1/rerank", &req.model, total_tokens, 0, "stop");
+
+    let mut results: Vec<RerankResult> = scores
+        .into_iter()
+        .enumerate()
+        .map(|(index, relevance_score)| RerankResult {
+            index,
+            relevance_score,
+            document: req.return_documents.then(|| RerankResultDocument {
+                text: documents[index].clone(),
+            }),
+        })
+        .collect();
+    // Stable sort keeps request order between equally relevant documents
+    results.sort_by(|a, b| b.relevance_score.total_cmp(&a.relevance_score));
+    if let Some(top_n) = req.top_n {
+        results.truncate(top_n);
+    }
+
+    Ok(Json(RerankResponse {
+        id: format!("rerank-{}", Uuid::new_v4()),
+        model: req.model,
+        results,
+        usage: RerankUsage { total_tokens },
+    }))
+}
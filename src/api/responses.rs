@@ -0,0 +1,846 @@
+use axum::{
+    extract::{Path, State},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    Extension, Json,
+};
+use futures::stream::StreamExt;
+use serde_json::json;
+use std::convert::Infallible;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use uuid::Uuid;
+
+use crate::api::auth::ApiKeyIdentity;
+use crate::api::chat::format_chat_messages;
+use crate::api::context::fit_chat_messages;
+use crate::api::error::ApiError;
+use crate::api::extract::ApiJson;
+use crate::api::images::load_request_images;
+use crate::api::models::resolve_model;
+use crate::api::reasoning::{effort_budget, ReasoningParser};
+use crate::api::routes::AppState;
+use crate::api::timings::RequestTimer;
+use crate::api::tools::{format_tool_call, parse_output, ParsedToolCall};
+use crate::api::types::{
+    ChatMessage, InputItem, InputTokensDetails, OutputContent, OutputItem, OutputTokensDetails,
+    ReasoningContent, ResponseDeleted, ResponseObject, ResponseTool, ResponseUsage,
+    ResponsesRequest, Truncation, TruncationStrategy, TypedInputItem,
+};
+use crate::backend::{GenerateRequest, InferenceEngine, ToolDefinition};
+use crate::storage::{ResponseStorage, SqliteStorage, StoredResponse};
+
+/// Maximum number of stored responses followed through `previous_response_id`
+const MAX_CHAIN_LENGTH: usize = 100;
+
+/// Handler for creating a model response
+pub async fn create_response<E: InferenceEngine + 'static>(
+    State(state): State<AppState<E>>,
+    identity: Option<Extension<ApiKeyIdentity>>,
+    timer: RequestTimer,
+    ApiJson(req): ApiJson<ResponsesRequest>,
+) -> Response {
+    let engine = state.engine.clone();
+    let key_id = key_id(identity);
+
+    let resolved = match resolve_model(&state, &req.model).await {
+        Ok(resolved) => resolved,
+        Err(e) => return e.into_response(),
+    };
+
+    // Replay the conversation of previous responses before this turn's input
+    let history = match load_history(
+        &state.storage,
+        req.previous_response_id.as_deref(),
+        key_id.as_deref(),
+    ) {
+        Ok(history) => history,
+        Err(e) => return e.into_response(),
+    };
+    let input = req.input.clone().into_items();
+
+    let mut messages: Vec<ChatMessage> = req
+        .instructions
+        .iter()
+        .map(|instructions| ChatMessage {
+            role: "system".to_string(),
+            content: instructions.clone().into(),
+            reasoning_content: None,
+        })
+        .collect();
+    messages.extend(history.iter().chain(&input).filter_map(item_to_message));
+
+    if messages.is_empty() {
+        return ApiError::invalid_request("input cannot be empty").into_response();
+    }
+
+    // Make sure the prompt and output fit in the context window
+    let mut max_tokens = req.max_output_tokens.unwrap_or(100);
+    let strategy = match req.truncation {
+        Truncation::Auto => TruncationStrategy::DropOldest,
+        Truncation::Disabled => TruncationStrategy::Disabled,
+    };
+    if let Err(e) = fit_chat_messages(
+        engine.as_ref(),
+        &resolved.base,
+        &mut messages,
+        &mut max_tokens,
+        strategy,
+    )
+    .await
+    {
+        return e.into_response();
+    }
+
+    let images = match load_request_images(
+        state.image_dir.as_deref(),
+        &resolved.base,
+        &req.model,
+        &messages,
+    ) {
+        Ok(images) => images,
+        Err(e) => return e.into_response(),
+    };
+
+    // Image embeddings are not part of the prompt text, so their KV state is not cached
+    let revision = images
+        .is_empty()
+        .then(|| resolved.base.metadata.cache.revision.clone());
+    let gen_req = GenerateRequest::new(
+        &resolved.base.name,
+        &format_chat_messages(&messages),
+        max_tokens,
+        req.temperature.unwrap_or(0.7),
+    )
+    .with_adapter(resolved.adapter.map(|a| a.name))
+    .with_revision(revision)
+    .with_images(images)
+    .with_thinking_budget(req.reasoning.and_then(|r| r.effort).map(effort_budget))
+    .with_tools(req.tools.iter().map(tool_definition).collect());
+
+    let turn = Turn {
+        id: format!("resp_{}", Uuid::new_v4().simple()),
+        created_at: chrono::Utc::now().timestamp(),
+        req,
+        input,
+        key_id,
+    };
+
+    if turn.req.stream {
+        stream_response(engine, state.storage.clone(), turn, gen_req, timer)
+            .await
+            .into_response()
+    } else {
+        match generate_response(engine.as_ref(), &turn, &gen_req, timer).await {
+            Ok(response) => {
+                if let Err(e) = turn.store(&state.storage, &response) {
+                    return ApiError::server_error(format!("Failed to store response: {}", e))
+                        .into_response();
+                }
+                Json(response).into_response()
+            }
+            Err(e) => ApiError::from(e).into_response(),
+        }
+    }
+}
+
+/// Handler for retrieving a stored response of the caller's key
+pub async fn get_response<E: InferenceEngine + 'static>(
+    State(state): State<AppState<E>>,
+    identity: Option<Extension<ApiKeyIdentity>>,
+    Path(id): Path<String>,
+) -> Response {
+    match state.storage.get_response(&id, key_id(identity).as_deref()) {
+        Ok(Some(stored)) => Json(stored.response).into_response(),
+        Ok(None) => response_not_found(&id).into_response(),
+        Err(e) => ApiError::server_error(format!("Failed to load response: {}", e)).into_response(),
+    }
+}
+
+/// Handler for deleting a stored response of the caller's key
+pub async fn delete_response<E: InferenceEngine + 'static>(
+    State(state): State<AppState<E>>,
+    identity: Option<Extension<ApiKeyIdentity>>,
+    Path(id): Path<String>,
+) -> Response {
+    match state
+        .storage
+        .delete_response(&id, key_id(identity).as_deref())
+    {
+        Ok(true) => Json(ResponseDeleted {
+            id,
+            object: "response".to_string(),
+            deleted: true,
+        })
+        .into_response(),
+        Ok(false) => response_not_found(&id).into_response(),
+        Err(e) => {
+            ApiError::server_error(format!("Failed to delete response: {}", e)).into_response()
+        }
+    }
+}
+
+/// Stored responses belong to the key that created them, as in OpenAI's API
+/// where responses are scoped to the project
+fn key_id(identity: Option<Extension<ApiKeyIdentity>>) -> Option<String> {
+    identity.map(|Extension(identity)| identity.id)
+}
+
+/// A single request/response turn being generated
+struct Turn {
+    id: String,
+    created_at: i64,
+    req: ResponsesRequest,
+    /// Input items of this turn only
+    input: Vec<InputItem>,
+    /// API key the response is stored for
+    key_id: Option<String>,
+}
+
+impl Turn {
+    fn response(
+        &self,
+        status: &str,
+        output: Vec<OutputItem>,
+        usage: Option<ResponseUsage>,
+    ) -> ResponseObject {
+        ResponseObject {
+            id: self.id.clone(),
+            object: "response".to_string(),
+            created_at: self.created_at,
+            status: status.to_string(),
+            model: self.req.model.clone(),
+            instructions: self.req.instructions.clone(),
+            previous_response_id: self.req.previous_response_id.clone(),
+            output,
+            tools: self.req.tools.clone(),
+            max_output_tokens: self.req.max_output_tokens,
+            temperature: self.req.temperature,
+            store: self.req.store,
+            usage,
+        }
+    }
+
+    /// Store the finished response for retrieval and chaining, if requested
+    fn store(&self, storage: &SqliteStorage, response: &ResponseObject) -> std::io::Result<()> {
+        if !self.req.store {
+            return Ok(());
+        }
+        storage.store_response(StoredResponse {
+            id: self.id.clone(),
+            previous_response_id: self.req.previous_response_id.clone(),
+            model: self.req.model.clone(),
+            input: serde_json::to_value(&self.input)?,
+            response: serde_json::to_value(response)?,
+            created_at: chrono::Utc::now().to_rfc3339(),
+            key_id: self.key_id.clone(),
+        })
+    }
+}
+
+/// Load the items of every response chained before `previous_response_id`,
+/// oldest first. Only responses of the caller's key can be chained.
+fn load_history(
+    storage: &SqliteStorage,
+    previous_response_id: Option<&str>,
+    key_id: Option<&str>,
+) -> Result<Vec<InputItem>, ApiError> {
+    let mut turns = Vec::new();
+    let mut next = previous_response_id.map(str::to_string);
+
+    while let Some(id) = next {
+        if turns.len() >= MAX_CHAIN_LENGTH {
+            return Err(ApiError::invalid_request(format!(
+                "Conversation is longer than {} responses",
+                MAX_CHAIN_LENGTH
+            )));
+        }
+
+        let stored = match storage.get_response(&id, key_id) {
+            Ok(Some(stored)) => stored,
+            Ok(None) => return Err(response_not_found(&id).with_param("previous_response_id")),
+            Err(e) => {
+                return Err(ApiError::server_error(format!(
+                    "Failed to load response: {}",
+                    e
+                )))
+            }
+        };
+
+        // Output items are valid input items for the next turn
+        let parsed = serde_json::from_value::<Vec<InputItem>>(stored.input).and_then(|input| {
+            let output = stored.response.get("output").cloned().unwrap_or_default();
+            Ok((input, serde_json::from_value::<Vec<InputItem>>(output)?))
+        });
+        match parsed {
+            Ok(turn) => turns.push(turn),
+            Err(e) => {
+                return Err(ApiError::server_error(format!(
+                    "Stored response '{}' is invalid: {}",
+                    id, e
+                )))
+            }
+        }
+        next = stored.previous_response_id;
+    }
+
+    Ok(turns
+        .into_iter()
+        .rev()
+        .flat_map(|(input, output)| input.into_iter().chain(output))
+        .collect())
+}
+
+/// Convert an input item to a chat message, reasoning items are dropped
+fn item_to_message(item: &InputItem) -> Option<ChatMessage> {
+    let (role, content) = match item {
+        InputItem::Message(message) => (message.role.as_str(), message.content.clone().into()),
+        InputItem::Typed(TypedInputItem::Message { role, content }) => {
+            (role.as_str(), content.clone().into())
+        }
+        InputItem::Typed(TypedInputItem::FunctionCall {
+            name, arguments, ..
+        }) => ("assistant", format_tool_call(name, arguments).into()),
+        InputItem::Typed(TypedInputItem::FunctionCallOutput { output, .. }) => {
+            ("tool", output.clone().into())
+        }
+        InputItem::Typed(TypedInputItem::Reasoning {}) => return None,
+    };
+
+    Some(ChatMessage {
+        // Developer messages are system messages for local models
+        role: if role == "developer" { "system" } else { role }.to_string(),
+        content,
+        reasoning_content: None,
+    })
+}
+
+fn tool_definition(tool: &ResponseTool) -> ToolDefinition {
+    match tool {
+        ResponseTool::Function {
+            name,
+            description,
+            parameters,
+            ..
+        } => ToolDefinition {
+            name: name.clone(),
+            description: description.clone(),
+            parameters: parameters.clone(),
+        },
+    }
+}
+
+fn new_item_id(prefix: &str) -> String {
+    format!("{}_{}", prefix, Uuid::new_v4().simple())
+}
+
+fn message_item(id: String, status: &str, text: String) -> OutputItem {
+    OutputItem::Message {
+        id,
+        status: status.to_string(),
+        role: "assistant".to_string(),
+        content: vec![OutputContent::OutputText {
+            text,
+            annotations: vec![],
+        }],
+    }
+}
+
+fn reasoning_item(id: String, text: String) -> OutputItem {
+    OutputItem::Reasoning {
+        id,
+        summary: vec![],
+        content: vec![ReasoningContent::ReasoningText { text }],
+    }
+}
+
+fn function_call_item(
+    id: String,
+    call_id: String,
+    call: ParsedToolCall,
+    status: &str,
+) -> OutputItem {
+    OutputItem::FunctionCall {
+        id,
+        call_id,
+        name: call.name,
+        arguments: call.arguments,
+        status: status.to_string(),
+    }
+}
+
+async fn usage<E: InferenceEngine>(
+    engine: &E,
+    model: &str,
+    input_tokens: usize,
+    cached_tokens: usize,
+    output_tokens: usize,
+    reasoning: Option<&str>,
+) -> Result<ResponseUsage, std::io::Error> {
+    let reasoning_tokens = match reasoning {
+        Some(reasoning) => engine.tokenize(model, reasoning).await?.len(),
+        None => 0,
+    };
+    Ok(ResponseUsage {
+        input_tokens,
+        input_tokens_details: InputTokensDetails { cached_tokens },
+        output_tokens,
+        output_tokens_details: OutputTokensDetails { reasoning_tokens },
+        total_tokens: input_tokens + output_tokens,
+    })
+}
+
+/// Non-streaming response
+async fn generate_response<E: InferenceEngine>(
+    engine: &E,
+    turn: &Turn,
+    gen_req: &GenerateRequest,
+    mut timer: RequestTimer,
+) -> Result<ResponseObject, std::io::Error> {
+    timer.generation_started();
+    let response = engine.generate(gen_req).await?;
+    let timings = timer.generate_timings(
+        response.prompt_tokens,
+        response.completion_tokens,
+        response.prompt_duration,
+        response.decode_duration,
+    );
+    timer.finish(
+        &timings,
+        "/v1/responses",
+        &turn.req.model,
+        response.prompt_tokens,
+        response.completion_tokens,
+        "completed",
+    );
+
+    let parsed = parse_output(&response.text, !gen_req.tools.is_empty());
+    let usage = usage(
+        engine,
+        &gen_req.model,
+        response.prompt_tokens,
+        response.cached_tokens,
+        response.completion_tokens,
+        parsed.reasoning.as_deref(),
+    )
+    .await?;
+
+    let mut output = Vec::new();
+    if let Some(reasoning) = parsed.reasoning {
+        output.push(reasoning_item(new_item_id("rs"), reasoning));
+    }
+    if !parsed.text.is_empty() || parsed.tool_calls.is_empty() {
+        output.push(message_item(new_item_id("msg"), "completed", parsed.text));
+    }
+    for call in parsed.tool_calls {
+        output.push(function_call_item(
+            new_item_id("fc"),
+            new_item_id("call"),
+            call,
+            "completed",
+        ));
+    }
+
+    Ok(turn.response("completed", output, Some(usage)))
+}
+
+/// The client closed the stream
+struct Disconnected;
+
+/// Emits the semantic events of a streamed response and collects its output items
+struct EventStream {
+    tx: mpsc::Sender<Result<Event, Infallible>>,
+    sequence_number: usize,
+    output: Vec<OutputItem>,
+    /// Item currently receiving deltas, with its id and text so far
+    open: Option<OpenItem>,
+}
+
+enum OpenItem {
+    Reasoning { id: String, text: String },
+    Message { id: String, text: String },
+}
+
+impl EventStream {
+    async fn send(
+        &mut self,
+        event_type: &str,
+        mut data: serde_json::Value,
+    ) -> Result<(), Disconnected> {
+        data["type"] = json!(event_type);
+        data["sequence_number"] = json!(self.sequence_number);
+        self.sequence_number += 1;
+
+        self.tx
+            .send(Ok(Event::default()
+                .event(event_type)
+                .data(data.to_string())))
+            .await
+            .map_err(|_| Disconnected)
+    }
+
+    async fn reasoning_delta(&mut self, delta: &str) -> Result<(), Disconnected> {
+        if !matches!(self.open, Some(OpenItem::Reasoning { .. })) {
+            self.close_item().await?;
+            let id = new_item_id("rs");
+            self.send(
+                "response.output_item.added",
+                json!({
+                    "output_index": self.output.len(),
+                    "item": reasoning_item(id.clone(), String::new()),
+                }),
+            )
+            .await?;
+            self.open = Some(OpenItem::Reasoning {
+                id,
+                text: String::new(),
+            });
+        }
+
+        let Some(OpenItem::Reasoning { id, text }) = &mut self.open else {
+            unreachable!("reasoning item was just opened");
+        };
+        text.push_str(delta);
+        let data = json!({
+            "item_id": id,
+            "output_index": self.output.len(),
+            "content_index": 0,
+            "delta": delta,
+        });
+        self.send("response.reasoning_text.delta", data).await
+    }
+
+    async fn text_delta(&mut self, delta: &str) -> Result<(), Disconnected> {
+        if !matches!(self.open, Some(OpenItem::Message { .. })) {
+            self.close_item().await?;
+            let id = new_item_id("msg");
+            let output_index = self.output.len();
+            self.send(
+                "response.output_item.added",
+                json!({
+                    "output_index": output_index,
+                    "item": {
+                        "type": "message",
+                        "id": id,
+                        "status": "in_progress",
+                        "role": "assistant",
+                        "content": [],
+                    },
+                }),
+            )
+            .await?;
+            self.send(
+                "response.content_part.added",
+                json!({
+                    "item_id": id,
+                    "output_index": output_index,
+                    "content_index": 0,
+                    "part": {"type": "output_text", "text": "", "annotations": []},
+                }),
+            )
+            .await?;
+            self.open = Some(OpenItem::Message {
+                id,
+                text: String::new(),
+            });
+        }
+
+        let Some(OpenItem::Message { id, text }) = &mut self.open else {
+            unreachable!("message item was just opened");
+        };
+        text.push_str(delta);
+        let data = json!({
+            "item_id": id,
+            "output_index": self.output.len(),
+            "content_index": 0,
+            "delta": delta,
+            "logprobs": [],
+        });
+        self.send("response.output_text.delta", data).await
+    }
+
+    /// Emit a complete function call item
+    async fn function_call(&mut self, call: ParsedToolCall) -> Result<(), Disconnected> {
+        self.close_item().await?;
+
+        let id = new_item_id("fc");
+        let call_id = new_item_id("call");
+        let output_index = self.output.len();
+        let arguments = call.arguments.clone();
+        let in_progress = function_call_item(
+            id.clone(),
+            call_id.clone(),
+            ParsedToolCall {
+                name: call.name.clone(),
+                arguments: String::new(),
+            },
+            "in_progress",
+        );
+        self.send(
+            "response.output_item.added",
+            json!({"output_index": output_index, "item": in_progress}),
+        )
+        .await?;
+        self.send(
+            "response.function_call_arguments.delta",
+            json!({"item_id": id, "output_index": output_index, "delta": arguments}),
+        )
+        .await?;
+        self.send(
+            "response.function_call_arguments.done",
+            json!({"item_id": id, "output_index": output_index, "arguments": arguments}),
+        )
+        .await?;
+
+        let item = function_call_item(id, call_id, call, "completed");
+        self.send(
+            "response.output_item.done",
+            json!({"output_index": output_index, "item": item}),
+        )
+        .await?;
+        self.output.push(item);
+        Ok(())
+    }
+
+    /// Finish the item currently receiving deltas, if any
+    async fn close_item(&mut self) -> Result<(), Disconnected> {
+        let output_index = self.output.len();
+        let item = match self.open.take() {
+            None => return Ok(()),
+            Some(OpenItem::Reasoning { id, text }) => {
+                self.send(
+                    "response.reasoning_text.done",
+                    json!({
+                        "item_id": id,
+                        "output_index": output_index,
+                        "content_index": 0,
+                        "text": text,
+                    }),
+                )
+                .await?;
+                reasoning_item(id, text)
+            }
+            Some(OpenItem::Message { id, text }) => {
+                self.send(
+                    "response.output_text.done",
+                    json!({
+                        "item_id": id,
+                        "output_index": output_index,
+                        "content_index": 0,
+                        "text": text,
+                        "logprobs": [],
+                    }),
+                )
+                .await?;
+                self.send(
+                    "response.content_part.done",
+                    json!({
+                        "item_id": id,
+                        "output_index": output_index,
+                        "content_index": 0,
+                        "part": {"type": "output_text", "text": text, "annotations": []},
+                    }),
+                )
+                .await?;
+                message_item(id, "completed", text)
+            }
+        };
+
+        self.send(
+            "response.output_item.done",
+            json!({"output_index": output_index, "item": item}),
+        )
+        .await?;
+        self.output.push(item);
+        Ok(())
+    }
+}
+
+/// Streaming response with semantic events
+async fn stream_response<E: InferenceEngine + 'static>(
+    engine: Arc<E>,
+    storage: Arc<SqliteStorage>,
+    turn: Turn,
+    gen_req: GenerateRequest,
+    mut timer: RequestTimer,
+) -> Sse<impl futures::Stream<Item = Result<Event, Infallible>>> {
+    let (tx, rx) = mpsc::channel(100);
+
+    tokio::spawn(async move {
+        let mut events = EventStream {
+            tx,
+            sequence_number: 0,
+            output: Vec::new(),
+            open: None,
+        };
+        let in_progress = turn.response("in_progress", vec![], None);
+        if events
+            .send("response.created", json!({"response": in_progress}))
+            .await
+            .is_err()
+            || events
+                .send("response.in_progress", json!({"response": in_progress}))
+                .await
+                .is_err()
+        {
+            return;
+        }
+
+        timer.generation_started();
+        let result = if gen_req.tools.is_empty() {
+            stream_output(engine.as_ref(), &gen_req, &mut events, &mut timer).await
+        } else {
+            // Tool calls can only be parsed from the complete output
+            generate_output(engine.as_ref(), &gen_req, &mut events).await
+        };
+
+        let (prompt_tokens, cached_tokens, completion_tokens, reasoning) = match result {
+            Ok(Ok(counts)) => counts,
+            Ok(Err(Disconnected)) => return,
+            Err(e) => {
+                tracing::error!("Error generating response: {}", e);
+                let failed = turn.response("failed", events.output.clone(), None);
+                let _ = events
+                    .send("response.failed", json!({"response": failed}))
+                    .await;
+                return;
+            }
+        };
+
+        timer.finish(
+            &timer.stream_timings(prompt_tokens, completion_tokens),
+            "/v1/responses",
+            &turn.req.model,
+            prompt_tokens,
+            completion_tokens,
+            "completed",
+        );
+        let usage = usage(
+            engine.as_ref(),
+            &gen_req.model,
+            prompt_tokens,
+            cached_tokens,
+            completion_tokens,
+            reasoning.as_deref(),
+        )
+        .await
+        .ok();
+
+        let completed = turn.response("completed", events.output.clone(), usage);
+        if let Err(e) = turn.store(&storage, &completed) {
+            tracing::error!("Failed to store response {}: {}", turn.id, e);
+        }
+        let _ = events
+            .send("response.completed", json!({"response": completed}))
+            .await;
+    });
+
+    Sse::new(ReceiverStream::new(rx)).keep_alive(KeepAlive::default())
+}
+
+/// Token counts and reasoning of a streamed output
+type StreamedOutput = (usize, usize, usize, Option<String>);
+
+/// Stream engine tokens as reasoning and text deltas
+async fn stream_output<E: InferenceEngine>(
+    engine: &E,
+    gen_req: &GenerateRequest,
+    events: &mut EventStream,
+    timer: &mut RequestTimer,
+) -> Result<Result<StreamedOutput, Disconnected>, std::io::Error> {
+    let mut stream = engine.generate_stream(gen_req).await?;
+    let mut parser = ReasoningParser::new();
+    let mut completion_tokens = 0;
+    let mut reasoning = String::new();
+
+    let mut finished = false;
+    while !finished {
+        // Split reasoning out of each token, flushing the parser at the end
+        let delta = match stream.tokens.next().await {
+            Some(token) => {
+                timer.first_token();
+                completion_tokens += 1;
+                parser.push(&token)
+            }
+            None => {
+                finished = true;
+                parser.finish()
+            }
+        };
+        if !delta.reasoning.is_empty() {
+            reasoning.push_str(&delta.reasoning);
+            if let Err(e) = events.reasoning_delta(&delta.reasoning).await {
+                return Ok(Err(e));
+            }
+        }
+        if !delta.content.is_empty() {
+            if let Err(e) = events.text_delta(&delta.content).await {
+                return Ok(Err(e));
+            }
+        }
+    }
+
+    // Always answer with a message, even if it is empty
+    if events
+        .output
+        .iter()
+        .all(|item| !matches!(item, OutputItem::Message { .. }))
+        && !matches!(events.open, Some(OpenItem::Message { .. }))
+    {
+        if let Err(e) = events.text_delta("").await {
+            return Ok(Err(e));
+        }
+    }
+    if let Err(e) = events.close_item().await {
+        return Ok(Err(e));
+    }
+
+    let reasoning = (!reasoning.is_empty()).then_some(reasoning);
+    Ok(Ok((
+        stream.prompt_tokens,
+        stream.cached_tokens,
+        completion_tokens,
+        reasoning,
+    )))
+}
+
+/// Generate the complete output and emit its items
+async fn generate_output<E: InferenceEngine>(
+    engine: &E,
+    gen_req: &GenerateRequest,
+    events: &mut EventStream,
+) -> Result<Result<StreamedOutput, Disconnected>, std::io::Error> {
+    let response = engine.generate(gen_req).await?;
+    let parsed = parse_output(&response.text, true);
+
+    let emitted = async {
+        if let Some(reasoning) = &parsed.reasoning {
+            events.reasoning_delta(reasoning).await?;
+        }
+        if !parsed.text.is_empty() || parsed.tool_calls.is_empty() {
+            events.text_delta(&parsed.text).await?;
+        }
+        for call in parsed.tool_calls {
+            events.function_call(call).await?;
+        }
+        events.close_item().await
+    }
+    .await;
+
+    Ok(emitted.map(|()| {
+        (
+            response.prompt_tokens,
+            response.cached_tokens,
+            response.completion_tokens,
+            parsed.reasoning,
+        )
+    }))
+}
+
+fn response_not_found(id: &str) -> ApiError {
+    ApiError::not_found(format!("Response with id '{}' not found", id))
+        .with_code("response_not_found")
+}
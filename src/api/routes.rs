@@ -1,8 +1,11 @@
 use axum::{
+    extract::DefaultBodyLimit,
+    handler::Handler,
+    middleware,
     routing::{get, post},
-    Json, Router,
+    Router,
 };
-use serde::Serialize;
+use std::path::PathBuf;
 use std::sync::Arc;
 use tower_http::{
     cors::CorsLayer,
@@ -10,37 +13,222 @@ use tower_http::{
     LatencyUnit,
 };
 
+use crate::api::audit::{self, AuditLog};
+use crate::api::auth::{self, ApiKeys};
+use crate::api::health::Readiness;
+use crate::api::images::MAX_IMAGE_REQUEST_BYTES;
+use crate::api::metrics::{self, Metrics};
+use crate::api::queue::{self, RequestQueue};
+use crate::api::ratelimit::{self, RateLimiter};
+use crate::backend::fim::FimCache;
 use crate::backend::InferenceEngine;
 use crate::registry::model_registry::ModelRegistry;
+use crate::storage::SqliteStorage;
 
-use super::{chat, completions, models};
+use super::{
+    admin, chat, completions, error, health, messages, models, ollama, rerank, responses, tokenize,
+};
 
 /// Shared application state
 #[derive(Clone)]
 pub struct AppState<E: InferenceEngine> {
     pub engine: Arc<E>,
     pub registry: Arc<ModelRegistry>,
+    pub readiness: Arc<Readiness>,
+    /// Server-side state (stored responses), in the registry's database
+    pub storage: Arc<SqliteStorage>,
+    /// Accepted API keys, every request is allowed when unset
+    pub auth: Option<Arc<ApiKeys>>,
+    /// Per-key limits, enforced on authenticated requests
+    pub rate_limiter: Option<Arc<RateLimiter>>,
+    /// Bounds concurrent generations, unbounded unless configured
+    pub queue: Arc<RequestQueue>,
+    /// Request and generation metrics, served on `/metrics`
+    pub metrics: Arc<Metrics>,
+    /// Log of handled requests, nothing is recorded when unset
+    pub audit: Option<Arc<AuditLog>>,
+    /// Cross-origin policy for browser clients, none allowed unless configured
+    pub cors: CorsLayer,
+    /// Serve the `/admin` model management endpoints
+    pub admin: bool,
+    /// Directory image inputs may be read from by path, none unless configured
+    pub image_dir: Option<PathBuf>,
+    /// Fill-in-the-middle tokens detected per model
+    pub fim_tokens: Arc<FimCache>,
 }
 
-/// Create the API router with all endpoints
-pub fn create_router<E: InferenceEngine + Clone + 'static>(
-    engine: Arc<E>,
-    registry: Arc<ModelRegistry>,
-) -> Router {
-    let state = AppState { engine, registry };
+impl<E: InferenceEngine> AppState<E> {
+    /// Create state for a server with nothing left to load
+    pub fn new(engine: Arc<E>, registry: Arc<ModelRegistry>) -> Self {
+        let storage = SqliteStorage::new(registry.db_path().to_path_buf())
+            .expect("Failed to initialize storage");
+        Self {
+            engine,
+            registry,
+            readiness: Arc::new(Readiness::ready()),
+            storage: Arc::new(storage),
+            auth: None,
+            rate_limiter: None,
+            queue: Arc::new(RequestQueue::unbounded()),
+            metrics: Arc::new(Metrics::new()),
+            audit: None,
+            cors: CorsLayer::new(),
+            admin: false,
+            image_dir: None,
+            fim_tokens: Arc::new(FimCache::new()),
+        }
+    }
+
+    /// Gate inference on the given readiness state
+    pub fn with_readiness(mut self, readiness: Arc<Readiness>) -> Self {
+        self.readiness = readiness;
+        self
+    }
+
+    /// Require one of the given API keys on every request
+    pub fn with_auth(mut self, keys: Arc<ApiKeys>) -> Self {
+        self.auth = Some(keys);
+        self
+    }
+
+    /// Limit the requests and tokens of each API key
+    pub fn with_rate_limiter(mut self, limiter: Arc<RateLimiter>) -> Self {
+        self.rate_limiter = Some(limiter);
+        self
+    }
+
+    /// Record every request in the audit log
+    pub fn with_audit(mut self, audit: Arc<AuditLog>) -> Self {
+        self.audit = Some(audit);
+        self
+    }
+
+    /// Queue generation requests over the queue's concurrency
+    pub fn with_queue(mut self, queue: Arc<RequestQueue>) -> Self {
+        self.queue = queue;
+        self
+    }
 
-    Router::new()
+    /// Allow the cross-origin requests the layer allows
+    pub fn with_cors(mut self, cors: CorsLayer) -> Self {
+        self.cors = cors;
+        self
+    }
+
+    /// Let requests reference image files in this directory by path
+    pub fn with_image_dir(mut self, dir: PathBuf) -> Self {
+        self.image_dir = Some(dir);
+        self
+    }
+
+    /// Let clients pull and delete models through the `/admin` endpoints
+    pub fn with_admin(mut self) -> Self {
+        self.admin = true;
+        self
+    }
+}
+
+/// Create the API router with all endpoints
+pub fn create_router<E: InferenceEngine + Clone + 'static>(state: AppState<E>) -> Router {
+    let auth = state.auth.clone();
+    let rate_limiter = state.rate_limiter.clone();
+    let metrics = state.metrics.clone();
+    let audit = state.audit.clone();
+    let cors = state.cors.clone();
+    let admin = state.admin;
+    // Generation endpoints wait for a slot in the request queue
+    let queue = state.queue.clone();
+    let queued = || middleware::from_fn_with_state(queue.clone(), queue::queued);
+    // Requests with image inputs exceed axum's default 2MB body limit
+    let images = || DefaultBodyLimit::max(MAX_IMAGE_REQUEST_BYTES);
+    let router = Router::new()
         // Chat completions (most important)
-        .route("/v1/chat/completions", post(chat::chat_completions::<E>))
+        .route(
+            "/v1/chat/completions",
+            post(chat::chat_completions::<E>.layer(images())).layer(queued()),
+        )
         // Legacy completions
-        .route("/v1/completions", post(completions::completions::<E>))
+        .route(
+            "/v1/completions",
+            post(completions::completions::<E>).layer(queued()),
+        )
+        // Responses API
+        .route(
+            "/v1/responses",
+            post(responses::create_response::<E>.layer(images())).layer(queued()),
+        )
+        .route(
+            "/v1/responses/:id",
+            get(responses::get_response::<E>).delete(responses::delete_response::<E>),
+        )
+        // Anthropic Messages API
+        .route(
+            "/v1/messages",
+            post(messages::messages::<E>.layer(images())).layer(queued()),
+        )
+        // Reranking with cross-encoder models
+        .route("/v1/rerank", post(rerank::rerank::<E>).layer(queued()))
         // Models
         .route("/v1/models", get(models::list_models::<E>))
         .route("/v1/models/:model", get(models::get_model::<E>))
-        // Health check
-        .route("/health", get(health_check))
+        // Tokenizer utilities
+        .route("/tokenize", post(tokenize::tokenize::<E>))
+        .route("/detokenize", post(tokenize::detokenize::<E>))
+        // Health checks
+        .route("/health", get(health::health_check))
+        .route("/health/live", get(health::live))
+        .route("/health/ready", get(health::ready::<E>))
+        // Prometheus metrics
+        .route("/metrics", get(metrics::metrics::<E>))
+        // Ollama API
+        .merge(ollama::router::<E>(queue.clone(), admin));
+
+    // Model management writes to disk, it is only served when enabled
+    let router = if admin {
+        router
+            .route("/admin/models", post(admin::pull_model::<E>))
+            .route(
+                "/admin/models/*model",
+                get(admin::get_model::<E>).delete(admin::delete_model::<E>),
+            )
+    } else {
+        router
+    };
+
+    let router = router
+        // Unknown routes answer with an error body too
+        .fallback(error::unknown_route)
         // Pass state
-        .with_state(state)
+        .with_state(state);
+
+    // Limits apply to the key found by authentication, which runs first
+    let router = match rate_limiter {
+        Some(limiter) => router.layer(middleware::from_fn_with_state(
+            limiter,
+            ratelimit::rate_limit,
+        )),
+        None => router,
+    };
+
+    // Authenticate inside the trace layer, so rejected requests are logged
+    let router = match auth {
+        Some(keys) => router.layer(middleware::from_fn_with_state(keys, auth::require_api_key)),
+        None => router,
+    };
+
+    // Record rejected requests too, with the key found by authentication
+    let router = match audit {
+        Some(log) => router.layer(middleware::from_fn_with_state(log, audit::record_requests)),
+        None => router,
+    };
+
+    // Count every request, including those rejected by auth and rate limits
+    let router = router.layer(middleware::from_fn_with_state(
+        metrics,
+        metrics::track_requests,
+    ));
+
+    router
         // Enable request/response logging at INFO level
         .layer(
             TraceLayer::new_for_http()
@@ -52,19 +240,6 @@ pub fn create_router<E: InferenceEngine + Clone + 'static>(
                         .latency_unit(LatencyUnit::Millis),
                 ),
         )
-        // Enable CORS for browser clients
-        .layer(CorsLayer::permissive())
-}
-
-/// Health check response
-#[derive(Serialize)]
-struct HealthResponse {
-    status: String,
-}
-
-/// Health check endpoint
-async fn health_check() -> Json<HealthResponse> {
-    Json(HealthResponse {
-        status: "ok".to_string(),
-    })
+        // Answer CORS preflights before authentication, browsers send them without keys
+        .layer(cors)
 }
@@ -0,0 +1,434 @@
+use axum::Router;
+use futures::future;
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use hyper_util::server::conn::auto;
+use hyper_util::server::graceful::{GracefulShutdown, Watcher};
+use hyper_util::service::TowerToHyperService;
+use std::future::Future;
+use std::io;
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::{TcpListener, UnixListener, UnixStream};
+use tracing::{debug, error, warn};
+
+use crate::api::tls::TlsConfig;
+
+/// Time a client gets to complete the TLS handshake
+const TLS_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Where the server accepts connections
+pub enum Listener {
+    Tcp(TcpListener),
+    /// TCP with TLS termination
+    Tls(TcpListener, Arc<TlsConfig>),
+    /// Unix domain socket, removed once the server stops
+    Unix(UnixListener, PathBuf),
+}
+
+/// Bind a Unix domain socket at `path`, readable and writable as `mode` allows.
+/// A socket left behind by a server that did not stop cleanly is replaced, one
+/// a server still listens on is an error.
+pub async fn bind_unix(path: &Path, mode: u32) -> io::Result<Listener> {
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if !metadata.file_type().is_socket() => {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("'{}' exists and is not a socket", path.display()),
+            ));
+        }
+        Ok(_) => {
+            if UnixStream::connect(path).await.is_ok() {
+                return Err(io::Error::new(
+                    io::ErrorKind::AddrInUse,
+                    format!("A server is already listening on '{}'", path.display()),
+                ));
+            }
+            std::fs::remove_file(path)?;
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let listener = UnixListener::bind(path)?;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))?;
+    Ok(Listener::Unix(listener, path.to_path_buf()))
+}
+
+/// Serve `app` on all `listeners` until `shutdown` resolves, then stop
+/// accepting connections and let in-flight requests finish, streams included,
+/// for up to `grace_period`. Returns whether every request finished in time.
+/// Connections still open afterwards are dropped when the runtime shuts down.
+pub async fn serve(
+    listeners: Vec<Listener>,
+    app: Router,
+    shutdown: impl Future<Output = ()>,
+    grace_period: Duration,
+) -> io::Result<bool> {
+    let graceful = GracefulShutdown::new();
+    let accepting = future::join_all(
+        listeners
+            .iter()
+            .map(|listener| accept(listener, &app, &graceful)),
+    );
+    tokio::select! {
+        _ = accepting => {}
+        _ = shutdown => {}
+    }
+
+    for listener in listeners {
+        if let Listener::Unix(listener, path) = listener {
+            drop(listener);
+            if let Err(e) = std::fs::remove_file(&path) {
+                warn!("Failed to remove socket '{}': {}", path.display(), e);
+            }
+        }
+    }
+    Ok(tokio::time::timeout(grace_period, graceful.shutdown())
+        .await
+        .is_ok())
+}
+
+/// Accept connections on `listener`, serving each in its own task
+async fn accept(listener: &Listener, app: &Router, graceful: &GracefulShutdown) {
+    loop {
+        let accepted = match listener {
+            Listener::Tcp(tcp) => tcp.accept().await.map(|(stream, _)| {
+                tokio::spawn(serve_connection(stream, app.clone(), graceful.watcher()));
+            }),
+            Listener::Tls(tcp, tls) => tcp.accept().await.map(|(stream, _)| {
+                let acceptor = tls.acceptor();
+                let (app, watcher) = (app.clone(), graceful.watcher());
+                tokio::spawn(async move {
+                    match tokio::time::timeout(TLS_HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await
+                    {
+                        Ok(Ok(stream)) => serve_connection(stream, app, watcher).await,
+                        Ok(Err(e)) => debug!("TLS handshake failed: {}", e),
+                        Err(_) => debug!("TLS handshake timed out"),
+                    }
+                });
+            }),
+            Listener::Unix(unix, _) => unix.accept().await.map(|(stream, _)| {
+                tokio::spawn(serve_connection(stream, app.clone(), graceful.watcher()));
+            }),
+        };
+        if let Err(e) = accepted {
+            // Out of file descriptors, give connections time to close
+            error!("Failed to accept connection: {}", e);
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+    }
+}
+
+async fn serve_connection<I>(io: I, app: Router, watcher: Watcher)
+where
+    I: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let builder = auto::Builder::new(TokioExecutor::new());
+    let connection = builder
+        .serve_connection_with_upgrades(TokioIo::new(io), TowerToHyperService::new(app))
+        .into_owned();
+    if let Err(e) = watcher.watch(connection).await {
+        debug!("Connection closed with an error: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::tls::TlsOptions;
+    use axum::{body::Body, routing::get};
+    use futures::stream::{self, StreamExt};
+    use rcgen::{BasicConstraints, CertificateParams, DnType, IsCa, KeyPair};
+    use rustls::pki_types::pem::PemObject;
+    use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName};
+    use rustls::{ClientConfig, RootCertStore};
+    use tempfile::TempDir;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::sync::oneshot;
+    use tokio::task::JoinHandle;
+    use tokio_rustls::TlsConnector;
+
+    /// App streaming three chunks 100ms apart
+    fn streaming_app() -> Router {
+        Router::new().route(
+            "/stream",
+            get(|| async {
+                let chunks = stream::iter(["a", "b", "c"]).then(|chunk| async move {
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                    Ok::<_, io::Error>(chunk)
+                });
+                Body::from_stream(chunks)
+            }),
+        )
+    }
+
+    /// Serve the streaming app until the returned sender fires
+    fn start_server(
+        listeners: Vec<Listener>,
+        grace_period: Duration,
+    ) -> (oneshot::Sender<()>, JoinHandle<io::Result<bool>>) {
+        let (trigger, triggered) = oneshot::channel::<()>();
+        let shutdown = async move {
+            let _ = triggered.await;
+        };
+        let server = tokio::spawn(serve(listeners, streaming_app(), shutdown, grace_period));
+        (trigger, server)
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_drains_streams() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/stream", listener.local_addr().unwrap());
+        let (trigger, server) =
+            start_server(vec![Listener::Tcp(listener)], Duration::from_secs(10));
+
+        let response = reqwest::get(&url).await.unwrap();
+        trigger.send(()).unwrap();
+
+        // The stream in flight finishes, new connections are refused
+        assert_eq!(response.text().await.unwrap(), "abc");
+        assert!(server.await.unwrap().unwrap());
+        assert!(reqwest::get(&url).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_grace_period_elapsed() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/stream", listener.local_addr().unwrap());
+        let (trigger, server) =
+            start_server(vec![Listener::Tcp(listener)], Duration::from_millis(50));
+
+        let _response = reqwest::get(&url).await.unwrap();
+        let triggered = std::time::Instant::now();
+        trigger.send(()).unwrap();
+
+        // Returns before the stream could finish
+        assert!(!server.await.unwrap().unwrap());
+        assert!(triggered.elapsed() < Duration::from_millis(250));
+    }
+
+    /// A certificate and its key, written to PEM files
+    struct TestCert {
+        cert: PathBuf,
+        key: PathBuf,
+        /// Signs the certificates this one issues
+        issuer: (rcgen::Certificate, KeyPair),
+    }
+
+    /// Generate a certificate for localhost, issued by `ca` or a self-signed
+    /// CA when not given
+    fn certificate(dir: &Path, name: &str, ca: Option<&TestCert>) -> TestCert {
+        let mut params = CertificateParams::new(vec!["localhost".to_string()]).unwrap();
+        params.distinguished_name.push(DnType::CommonName, name);
+        let key = KeyPair::generate().unwrap();
+        let cert = match ca {
+            Some(ca) => params.signed_by(&key, &ca.issuer.0, &ca.issuer.1),
+            None => {
+                params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+                params.self_signed(&key)
+            }
+        }
+        .unwrap();
+
+        let cert_path = dir.join(format!("{}.crt", name));
+        let key_path = dir.join(format!("{}.key", name));
+        std::fs::write(&cert_path, cert.pem()).unwrap();
+        std::fs::write(&key_path, key.serialize_pem()).unwrap();
+        TestCert {
+            cert: cert_path,
+            key: key_path,
+            issuer: (cert, key),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unix_socket() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("run").join("puma.sock");
+        let unix = bind_unix(&path, 0o600).await.unwrap();
+        let tcp = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/stream", tcp.local_addr().unwrap());
+        let (trigger, server) =
+            start_server(vec![unix, Listener::Tcp(tcp)], Duration::from_secs(1));
+
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        // Served on the socket and over TCP alike
+        let response = raw_get(UnixStream::connect(&path).await.unwrap())
+            .await
+            .unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert_eq!(
+            reqwest::get(&url).await.unwrap().text().await.unwrap(),
+            "abc"
+        );
+
+        // A socket in use is not taken over
+        let error = bind_unix(&path, 0o600).await.err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::AddrInUse);
+
+        // The socket is removed on shutdown
+        trigger.send(()).unwrap();
+        assert!(server.await.unwrap().unwrap());
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_bind_unix_stale_socket() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("puma.sock");
+
+        // Left behind by a server that crashed
+        drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+        assert!(path.exists());
+        assert!(bind_unix(&path, 0o660).await.is_ok());
+
+        // Other files are never replaced
+        let file = dir.path().join("puma.db");
+        std::fs::write(&file, "data").unwrap();
+        let error = bind_unix(&file, 0o600).await.err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "data");
+    }
+
+    /// GET /stream over TLS, trusting `ca` and presenting the client
+    /// certificate when given
+    async fn tls_get(
+        addr: std::net::SocketAddr,
+        ca: &Path,
+        client: Option<(&Path, &Path)>,
+    ) -> io::Result<String> {
+        let mut roots = RootCertStore::empty();
+        for cert in CertificateDer::pem_file_iter(ca).unwrap() {
+            roots.add(cert.unwrap()).unwrap();
+        }
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let builder = ClientConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_root_certificates(roots);
+        let config = match client {
+            Some((cert, key)) => builder
+                .with_client_auth_cert(
+                    CertificateDer::pem_file_iter(cert)
+                        .unwrap()
+                        .map(Result::unwrap)
+                        .collect(),
+                    PrivateKeyDer::from_pem_file(key).unwrap(),
+                )
+                .unwrap(),
+            None => builder.with_no_client_auth(),
+        };
+
+        let stream = tokio::net::TcpStream::connect(addr).await?;
+        let server_name = ServerName::try_from("localhost").unwrap();
+        let stream = TlsConnector::from(Arc::new(config))
+            .connect(server_name, stream)
+            .await?;
+        raw_get(stream).await
+    }
+
+    /// GET /stream over an established connection, returns the raw response
+    async fn raw_get<S: AsyncRead + AsyncWrite + Unpin>(mut stream: S) -> io::Result<String> {
+        stream
+            .write_all(b"GET /stream HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .await?;
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await?;
+        Ok(response)
+    }
+
+    async fn start_tls_server(
+        options: TlsOptions,
+    ) -> (std::net::SocketAddr, Arc<TlsConfig>, oneshot::Sender<()>) {
+        let tls = Arc::new(TlsConfig::load(options).unwrap());
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (trigger, _) = start_server(
+            vec![Listener::Tls(listener, tls.clone())],
+            Duration::from_secs(1),
+        );
+        (addr, tls, trigger)
+    }
+
+    #[tokio::test]
+    async fn test_tls() {
+        let dir = TempDir::new().unwrap();
+        let ca = certificate(dir.path(), "ca", None);
+        let server = certificate(dir.path(), "server", Some(&ca));
+        let (addr, tls, _trigger) = start_tls_server(TlsOptions {
+            cert: server.cert.clone(),
+            key: server.key.clone(),
+            client_ca: None,
+        })
+        .await;
+        assert!(!tls.requires_client_cert());
+
+        let response = tls_get(addr, &ca.cert, None).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.ends_with("\r\nc\r\n0\r\n\r\n"));
+
+        // Reloading picks up a renewed certificate from another CA
+        let renewed_ca = certificate(dir.path(), "renewed-ca", None);
+        let renewed = certificate(dir.path(), "renewed", Some(&renewed_ca));
+        std::fs::copy(&renewed.cert, &server.cert).unwrap();
+        std::fs::copy(&renewed.key, &server.key).unwrap();
+        tls.reload().unwrap();
+        assert!(tls_get(addr, &renewed_ca.cert, None).await.is_ok());
+        assert!(tls_get(addr, &ca.cert, None).await.is_err());
+
+        // A broken certificate file keeps the previous configuration
+        std::fs::write(&server.cert, "not a certificate").unwrap();
+        assert!(tls.reload().is_err());
+        assert!(tls_get(addr, &renewed_ca.cert, None).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_mutual_tls() {
+        let dir = TempDir::new().unwrap();
+        let ca = certificate(dir.path(), "ca", None);
+        let server = certificate(dir.path(), "server", Some(&ca));
+        let client_ca = certificate(dir.path(), "client-ca", None);
+        let client = certificate(dir.path(), "client", Some(&client_ca));
+        let other = certificate(dir.path(), "other", Some(&ca));
+        let (addr, tls, _trigger) = start_tls_server(TlsOptions {
+            cert: server.cert,
+            key: server.key,
+            client_ca: Some(client_ca.cert.clone()),
+        })
+        .await;
+        assert!(tls.requires_client_cert());
+
+        let response = tls_get(addr, &ca.cert, Some((&client.cert, &client.key)))
+            .await
+            .unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+
+        // TLS 1.3 reports client certificate errors after the handshake
+        let rejected = |result: io::Result<String>| result.map_or(true, |r| r.is_empty());
+        assert!(rejected(tls_get(addr, &ca.cert, None).await));
+        assert!(rejected(
+            tls_get(addr, &ca.cert, Some((&other.cert, &other.key))).await
+        ));
+    }
+
+    #[test]
+    fn test_tls_missing_files() {
+        let dir = TempDir::new().unwrap();
+        let error = TlsConfig::load(TlsOptions {
+            cert: dir.path().join("missing.crt"),
+            key: dir.path().join("missing.key"),
+            client_ca: None,
+        })
+        .err()
+        .unwrap();
+        assert!(error.to_string().contains("missing.crt"));
+    }
+}
@@ -8,17 +8,36 @@ use axum::{
     http::{Request, StatusCode},
 };
 use serde_json::{json, Value};
+use std::io;
 use std::sync::Arc;
 use tempfile::TempDir;
 use tower::util::ServiceExt; // for `oneshot` and `ready`
 
-use super::routes::create_router;
+use super::audit::AuditLog;
+use super::auth::ApiKeys;
+use super::cors::CorsOptions;
+use super::health::{Readiness, ReadinessPhase};
+use super::queue::RequestQueue;
+use super::ratelimit::{RateLimiter, RateLimits};
+use super::routes::{create_router, AppState};
 use crate::backend::mock::MockEngine;
-use crate::registry::model_registry::{CacheInfo, ModelInfo, ModelMetadata, ModelRegistry};
+use crate::backend::prompt_cache::{PromptCache, PROMPT_CACHE_BLOCK_TOKENS};
+use crate::backend::{GenerateRequest, GenerateResponse, GenerateStream, InferenceEngine, Token};
+use crate::registry::model_registry::{
+    AdapterInfo, CacheInfo, ModelInfo, ModelMetadata, ModelRegistry,
+};
+use crate::storage::{ApiKeyStorage, AuditStorage, StoredApiKey};
 
 /// Helper to create test app with a pre-registered test model
 /// Returns the router and the temp directory (which must be kept alive)
 fn create_test_app() -> (axum::Router, TempDir) {
+    let (state, temp_dir) = create_test_state();
+    (create_router(state), temp_dir)
+}
+
+/// Helper to create the app state behind the test app, for tests that
+/// need to customize it before building the router
+fn create_test_state() -> (AppState<MockEngine>, TempDir) {
     let engine = Arc::new(MockEngine::new());
     let temp_dir = TempDir::new().unwrap();
     let registry = Arc::new(ModelRegistry::new(Some(temp_dir.path().to_path_buf())));
@@ -49,7 +68,7 @@ fn create_test_app() -> (axum::Router, TempDir) {
         .register_model(test_model)
         .expect("failed to register test model");
 
-    (create_router(engine, registry), temp_dir)
+    (AppState::new(engine, registry), temp_dir)
 }
 
 /// Helper to make a JSON request
@@ -282,7 +301,12 @@ async fn test_chat_completion_default_values() {
 
 #[tokio::test]
 async fn test_cors_headers() {
-    let (app, _temp_dir) = create_test_app();
+    let (state, _temp_dir) = create_test_state();
+    let options = CorsOptions {
+        origins: vec!["*".to_string()],
+        ..Default::default()
+    };
+    let app = create_router(state.with_cors(options.layer().unwrap()));
     let request = Request::builder()
         .uri("/health")
         .method("GET")
@@ -292,23 +316,102 @@ async fn test_cors_headers() {
 
     let response = app.oneshot(request).await.unwrap();
 
-    // CORS should be permissive
-    assert!(response
-        .headers()
-        .contains_key("access-control-allow-origin"));
+    // Any origin is allowed once configured
+    assert_eq!(response.headers()["access-control-allow-origin"], "*");
 }
 
 #[tokio::test]
 async fn test_invalid_route() {
     let (app, _temp_dir) = create_test_app();
+    let (status, json) = make_json_request(app, "GET", "/invalid/route", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(json["error"]["type"], "invalid_request_error");
+    assert_eq!(json["error"]["message"], "Invalid URL (GET /invalid/route)");
+}
+
+/// Helper to post a raw body, for requests that are not valid JSON
+async fn make_raw_request(app: axum::Router, uri: &str, body: &str) -> (StatusCode, Value) {
     let request = Request::builder()
-        .uri("/invalid/route")
-        .method("GET")
-        .body(Body::empty())
+        .uri(uri)
+        .method("POST")
+        .header("content-type", "application/json")
+        .body(Body::from(body.to_string()))
         .unwrap();
 
     let response = app.oneshot(request).await.unwrap();
-    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+#[tokio::test]
+async fn test_malformed_json_body() {
+    let (app, _temp_dir) = create_test_app();
+    let (status, json) = make_raw_request(app, "/v1/chat/completions", "{\"model\": ").await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(json["error"]["type"], "invalid_request_error");
+    assert!(json["error"]["param"].is_null());
+    assert!(json["error"]["message"]
+        .as_str()
+        .unwrap()
+        .starts_with("We could not parse the JSON body"));
+}
+
+#[tokio::test]
+async fn test_non_json_content_type() {
+    let (app, _temp_dir) = create_test_app();
+    let body = json!({
+        "model": "test-model",
+        "messages": [{"role": "user", "content": "Hello"}]
+    });
+
+    // A "simple" cross-origin request browsers send without a preflight
+    for content_type in [Some("text/plain"), None] {
+        let mut request = Request::builder()
+            .uri("/v1/chat/completions")
+            .method("POST")
+            .header("origin", "http://evil.example");
+        if let Some(content_type) = content_type {
+            request = request.header("content-type", content_type);
+        }
+        let response = app
+            .clone()
+            .oneshot(request.body(Body::from(body.to_string())).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+}
+
+#[tokio::test]
+async fn test_invalid_field_names_param() {
+    let (app, _temp_dir) = create_test_app();
+    let request_body = json!({"model": "test-model"});
+    let (status, json) = make_json_request(
+        app.clone(),
+        "POST",
+        "/v1/chat/completions",
+        Some(request_body),
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(json["error"]["type"], "invalid_request_error");
+    assert_eq!(json["error"]["param"], "messages");
+
+    let request_body = json!({
+        "model": "test-model",
+        "messages": [{"role": "user", "content": "Hello"}],
+        "max_tokens": "many"
+    });
+    let (status, json) =
+        make_json_request(app, "POST", "/v1/chat/completions", Some(request_body)).await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(json["error"]["param"], "max_tokens");
 }
 
 #[tokio::test]
@@ -340,7 +443,9 @@ async fn test_chat_completion_nonexistent_model() {
         make_json_request(app, "POST", "/v1/chat/completions", Some(request_body)).await;
 
     assert_eq!(status, StatusCode::NOT_FOUND);
-    assert_eq!(json["error"]["type"], "model_not_found");
+    assert_eq!(json["error"]["type"], "invalid_request_error");
+    assert_eq!(json["error"]["code"], "model_not_found");
+    assert_eq!(json["error"]["param"], "model");
     assert!(json["error"]["message"]
         .as_str()
         .unwrap()
@@ -359,7 +464,9 @@ async fn test_text_completion_nonexistent_model() {
         make_json_request(app, "POST", "/v1/completions", Some(request_body)).await;
 
     assert_eq!(status, StatusCode::NOT_FOUND);
-    assert_eq!(json["error"]["type"], "model_not_found");
+    assert_eq!(json["error"]["type"], "invalid_request_error");
+    assert_eq!(json["error"]["code"], "model_not_found");
+    assert_eq!(json["error"]["param"], "model");
     assert!(json["error"]["message"]
         .as_str()
         .unwrap()
@@ -435,3 +542,2841 @@ async fn test_chat_completion_streaming() {
     // Check stream ends with [DONE]
     assert!(events.contains(&"[DONE]"), "Stream should end with [DONE]");
 }
+
+/// Register a LoRA adapter on top of the test model
+fn register_test_adapter(temp_dir: &TempDir, name: &str) {
+    let registry = ModelRegistry::new(Some(temp_dir.path().to_path_buf()));
+    registry
+        .register_adapter(AdapterInfo {
+            name: name.to_string(),
+            base_model: "test-model".to_string(),
+            path: "/tmp/test-adapter".to_string(),
+            created_at: chrono::Utc::now().to_rfc3339(),
+        })
+        .expect("failed to register test adapter");
+}
+
+#[tokio::test]
+async fn test_chat_completion_with_adapter() {
+    let (app, temp_dir) = create_test_app();
+    register_test_adapter(&temp_dir, "sql-lora");
+    register_test_adapter(&temp_dir, "chat-lora");
+
+    for adapter in ["sql-lora", "chat-lora"] {
+        let request_body = json!({
+            "model": adapter,
+            "messages": [
+                {"role": "user", "content": "Hello"}
+            ]
+        });
+
+        let (status, json) = make_json_request(
+            app.clone(),
+            "POST",
+            "/v1/chat/completions",
+            Some(request_body),
+        )
+        .await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json["model"], adapter);
+        // Both adapters run on the same base model
+        assert!(json["choices"][0]["message"]["content"]
+            .as_str()
+            .unwrap()
+            .contains(&format!("test-model+{}", adapter)));
+    }
+}
+
+#[tokio::test]
+async fn test_list_models_includes_adapters() {
+    let (app, temp_dir) = create_test_app();
+    register_test_adapter(&temp_dir, "sql-lora");
+
+    let (status, json) = make_json_request(app, "GET", "/v1/models", None).await;
+
+    assert_eq!(status, StatusCode::OK);
+    let adapter = json["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|m| m["id"] == "sql-lora")
+        .expect("adapter should be listed");
+    assert_eq!(adapter["parent"], "test-model");
+}
+
+#[tokio::test]
+async fn test_get_adapter_model() {
+    let (app, temp_dir) = create_test_app();
+    register_test_adapter(&temp_dir, "sql-lora");
+
+    let (status, json) = make_json_request(app.clone(), "GET", "/v1/models/sql-lora", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["id"], "sql-lora");
+    assert_eq!(json["object"], "model");
+    assert_eq!(json["parent"], "test-model");
+
+    // Base models have no parent
+    let (status, json) = make_json_request(app, "GET", "/v1/models/test-model", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(json.get("parent").is_none());
+}
+
+#[tokio::test]
+async fn test_tokenize_prompt() {
+    let (app, _temp_dir) = create_test_app();
+    let request_body = json!({
+        "model": "test-model",
+        "prompt": "Hello"
+    });
+
+    let (status, json) = make_json_request(app, "POST", "/tokenize", Some(request_body)).await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["count"], 5);
+    assert_eq!(json["max_model_len"], 2048);
+    assert_eq!(json["tokens"].as_array().unwrap().len(), 5);
+    assert_eq!(json["token_strs"][0], "H");
+}
+
+#[tokio::test]
+async fn test_tokenize_messages_applies_chat_template() {
+    let (app, _temp_dir) = create_test_app();
+    let request_body = json!({
+        "model": "test-model",
+        "messages": [
+            {"role": "user", "content": "Hello"}
+        ]
+    });
+
+    let (status, json) = make_json_request(app, "POST", "/tokenize", Some(request_body)).await;
+
+    assert_eq!(status, StatusCode::OK);
+    let text: String = json["token_strs"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|t| t.as_str().unwrap())
+        .collect();
+    assert_eq!(text, "User: Hello");
+}
+
+#[tokio::test]
+async fn test_tokenize_requires_prompt_or_messages() {
+    let (app, _temp_dir) = create_test_app();
+    let request_body = json!({
+        "model": "test-model"
+    });
+
+    let (status, json) = make_json_request(app, "POST", "/tokenize", Some(request_body)).await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(json["error"]["type"], "invalid_request_error");
+}
+
+#[tokio::test]
+async fn test_detokenize_roundtrip() {
+    let (app, _temp_dir) = create_test_app();
+    let (_, tokenized) = make_json_request(
+        app.clone(),
+        "POST",
+        "/tokenize",
+        Some(json!({"model": "test-model", "prompt": "Hello world"})),
+    )
+    .await;
+
+    let request_body = json!({
+        "model": "test-model",
+        "tokens": tokenized["tokens"]
+    });
+    let (status, json) = make_json_request(app, "POST", "/detokenize", Some(request_body)).await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["prompt"], "Hello world");
+}
+
+#[tokio::test]
+async fn test_chat_completion_context_length_exceeded() {
+    let (app, _temp_dir) = create_test_app();
+    // The mock tokenizer is character level and the test model has a 2048 token window
+    let request_body = json!({
+        "model": "test-model",
+        "messages": [
+            {"role": "user", "content": "a".repeat(2000)}
+        ],
+        "max_tokens": 100
+    });
+
+    let (status, json) =
+        make_json_request(app, "POST", "/v1/chat/completions", Some(request_body)).await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(json["error"]["type"], "invalid_request_error");
+    assert_eq!(json["error"]["code"], "context_length_exceeded");
+    assert!(json["error"]["message"]
+        .as_str()
+        .unwrap()
+        .contains("maximum context length is 2048 tokens"));
+}
+
+#[tokio::test]
+async fn test_max_tokens_overflow() {
+    let (app, _temp_dir) = create_test_app();
+
+    // The prompt plus max_tokens overflows, it must not wrap around the check
+    let (status, json) = make_json_request(
+        app.clone(),
+        "POST",
+        "/v1/chat/completions",
+        Some(json!({
+            "model": "test-model",
+            "messages": [{"role": "user", "content": "Hello"}],
+            "max_tokens": usize::MAX
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(json["error"]["code"], "context_length_exceeded");
+
+    let (status, json) = make_json_request(
+        app,
+        "POST",
+        "/v1/completions",
+        Some(json!({"model": "test-model", "prompt": "Hello", "max_tokens": usize::MAX})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(json["error"]["code"], "context_length_exceeded");
+}
+
+#[tokio::test]
+async fn test_chat_completion_clamp_max_tokens() {
+    let (app, _temp_dir) = create_test_app();
+    let request_body = json!({
+        "model": "test-model",
+        "messages": [
+            {"role": "user", "content": "a".repeat(2000)}
+        ],
+        "max_tokens": 100,
+        "truncation_strategy": "clamp_max_tokens"
+    });
+
+    let (status, json) =
+        make_json_request(app, "POST", "/v1/chat/completions", Some(request_body)).await;
+
+    // "User: " + 2000 chars leaves 42 tokens for the completion
+    assert_eq!(status, StatusCode::OK);
+    assert!(json["choices"][0]["message"]["content"]
+        .as_str()
+        .unwrap()
+        .contains("(max_tokens: 42)"));
+}
+
+#[tokio::test]
+async fn test_chat_completion_drop_oldest_messages() {
+    let (app, _temp_dir) = create_test_app();
+    let request_body = json!({
+        "model": "test-model",
+        "messages": [
+            {"role": "system", "content": "You are a helpful assistant."},
+            {"role": "user", "content": "a".repeat(2000)},
+            {"role": "assistant", "content": "OK"},
+            {"role": "user", "content": "Hello"}
+        ],
+        "max_tokens": 100,
+        "truncation_strategy": "drop_oldest"
+    });
+
+    let (status, json) =
+        make_json_request(app, "POST", "/v1/chat/completions", Some(request_body)).await;
+
+    assert_eq!(status, StatusCode::OK);
+    // The system message is kept at the start of the prompt
+    assert!(json["choices"][0]["message"]["content"]
+        .as_str()
+        .unwrap()
+        .contains("prompt: 'System: You are a helpful assistant."));
+}
+
+#[tokio::test]
+async fn test_text_completion_context_length_exceeded() {
+    let (app, _temp_dir) = create_test_app();
+    let request_body = json!({
+        "model": "test-model",
+        "prompt": "a".repeat(2048),
+        "max_tokens": 10,
+        "truncation_strategy": "clamp_max_tokens"
+    });
+
+    // Clamping cannot help when the prompt alone fills the window
+    let (status, json) =
+        make_json_request(app, "POST", "/v1/completions", Some(request_body)).await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(json["error"]["code"], "context_length_exceeded");
+}
+
+/// A base64 data URL holding a PNG header, enough for the mock vision encoder
+fn test_image_data_url() -> String {
+    use base64::{engine::general_purpose::STANDARD, Engine as _};
+    format!(
+        "data:image/png;base64,{}",
+        STANDARD.encode(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR")
+    )
+}
+
+fn register_vision_model(temp_dir: &TempDir) {
+    let registry = ModelRegistry::new(Some(temp_dir.path().to_path_buf()));
+    registry
+        .register_model(ModelInfo {
+            uuid: "vision-uuid".to_string(),
+            name: "vision-model".to_string(),
+            provider: "test".to_string(),
+            author: None,
+            task: Some("image-text-to-text".to_string()),
+            model_series: Some("qwen2_vl".to_string()),
+            license: None,
+            created_at: chrono::Utc::now().to_rfc3339(),
+            updated_at: chrono::Utc::now().to_rfc3339(),
+            metadata: ModelMetadata {
+                cache: CacheInfo {
+                    revision: "test-rev".to_string(),
+                    size: 1000,
+                    path: "/tmp/vision-model".to_string(),
+                },
+                context_window: Some(4096),
+                safetensors: None,
+            },
+        })
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_chat_completion_with_image() {
+    let (app, temp_dir) = create_test_app();
+    register_vision_model(&temp_dir);
+
+    let request_body = json!({
+        "model": "vision-model",
+        "messages": [{
+            "role": "user",
+            "content": [
+                {"type": "text", "text": "What is in this image?"},
+                {"type": "image_url", "image_url": {"url": test_image_data_url()}}
+            ]
+        }]
+    });
+
+    let (status, json) =
+        make_json_request(app, "POST", "/v1/chat/completions", Some(request_body)).await;
+
+    assert_eq!(status, StatusCode::OK);
+    let content = json["choices"][0]["message"]["content"].as_str().unwrap();
+    assert!(content.contains("What is in this image?<image>"));
+    assert!(content.contains("with 1 image(s)"));
+}
+
+#[tokio::test]
+async fn test_chat_completion_with_large_image() {
+    use base64::{engine::general_purpose::STANDARD, Engine as _};
+    let (app, temp_dir) = create_test_app();
+    register_vision_model(&temp_dir);
+
+    // Larger than axum's default 2MB body limit once encoded
+    let mut image = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR".to_vec();
+    image.resize(8 * 1024 * 1024, 0);
+    let url = format!("data:image/png;base64,{}", STANDARD.encode(&image));
+    let messages = json!([{
+        "role": "user",
+        "content": [
+            {"type": "text", "text": "What is in this image?"},
+            {"type": "image_url", "image_url": {"url": url}}
+        ]
+    }]);
+
+    let (status, _) = make_json_request(
+        app.clone(),
+        "POST",
+        "/v1/chat/completions",
+        Some(json!({"model": "vision-model", "messages": messages})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, _) = make_json_request(
+        app,
+        "POST",
+        "/api/chat",
+        Some(json!({
+            "model": "vision-model",
+            "messages": [{
+                "role": "user",
+                "content": "What is in this image?",
+                "images": [STANDARD.encode(&image)]
+            }],
+            "stream": false
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_chat_completion_image_on_text_model() {
+    let (app, _temp_dir) = create_test_app();
+    let request_body = json!({
+        "model": "test-model",
+        "messages": [{
+            "role": "user",
+            "content": [
+                {"type": "image_url", "image_url": {"url": test_image_data_url()}}
+            ]
+        }]
+    });
+
+    let (status, json) =
+        make_json_request(app, "POST", "/v1/chat/completions", Some(request_body)).await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(json["error"]["message"]
+        .as_str()
+        .unwrap()
+        .contains("does not support image inputs"));
+}
+
+#[tokio::test]
+async fn test_chat_completion_reasoning_content() {
+    let (app, _temp_dir) = create_test_app();
+    let request_body = json!({
+        "model": "test-model",
+        "messages": [
+            {"role": "user", "content": "Hello"}
+        ],
+        "reasoning_effort": "low"
+    });
+
+    let (status, json) =
+        make_json_request(app, "POST", "/v1/chat/completions", Some(request_body)).await;
+
+    assert_eq!(status, StatusCode::OK);
+    let message = &json["choices"][0]["message"];
+    assert!(message["reasoning_content"]
+        .as_str()
+        .unwrap()
+        .starts_with("The user sent a prompt."));
+    let content = message["content"].as_str().unwrap();
+    assert!(content.starts_with("This is a mock response"));
+    assert!(!content.contains("<think>"));
+    assert!(
+        json["usage"]["completion_tokens_details"]["reasoning_tokens"]
+            .as_u64()
+            .unwrap()
+            > 0
+    );
+}
+
+#[tokio::test]
+async fn test_chat_completion_thinking_budget() {
+    let (app, _temp_dir) = create_test_app();
+    let request_body = json!({
+        "model": "test-model",
+        "messages": [
+            {"role": "user", "content": "Hello"}
+        ],
+        "reasoning_effort": "high",
+        "thinking_budget": 8
+    });
+
+    let (status, json) =
+        make_json_request(app, "POST", "/v1/chat/completions", Some(request_body)).await;
+
+    // The explicit budget wins over reasoning_effort
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        json["usage"]["completion_tokens_details"]["reasoning_tokens"],
+        8
+    );
+}
+
+#[tokio::test]
+async fn test_chat_completion_without_reasoning() {
+    let (app, _temp_dir) = create_test_app();
+    let request_body = json!({
+        "model": "test-model",
+        "messages": [
+            {"role": "user", "content": "Hello"}
+        ]
+    });
+
+    let (status, json) =
+        make_json_request(app, "POST", "/v1/chat/completions", Some(request_body)).await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert!(json["choices"][0]["message"]
+        .get("reasoning_content")
+        .is_none());
+    assert!(json["usage"].get("completion_tokens_details").is_none());
+}
+
+#[tokio::test]
+async fn test_chat_completion_streaming_reasoning_content() {
+    let (app, _temp_dir) = create_test_app();
+    let request_body = json!({
+        "model": "test-model",
+        "messages": [
+            {"role": "user", "content": "Hello"}
+        ],
+        "stream": true,
+        "reasoning_effort": "medium"
+    });
+
+    let request = Request::builder()
+        .uri("/v1/chat/completions")
+        .method("POST")
+        .header("content-type", "application/json")
+        .body(Body::from(serde_json::to_vec(&request_body).unwrap()))
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body_bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body_text = String::from_utf8_lossy(&body_bytes);
+    let deltas: Vec<Value> = body_text
+        .split("\n\n")
+        .filter_map(|line| line.strip_prefix("data: "))
+        .filter(|data| *data != "[DONE]")
+        .map(|data| serde_json::from_str::<Value>(data).unwrap())
+        .map(|chunk| chunk["choices"][0]["delta"].clone())
+        .collect();
+
+    let reasoning: String = deltas
+        .iter()
+        .filter_map(|d| d["reasoning_content"].as_str())
+        .collect();
+    let content: String = deltas
+        .iter()
+        .filter_map(|d| d["content"].as_str())
+        .collect();
+
+    assert!(reasoning.contains("Let me think"));
+    assert!(content.starts_with("This is a mock streaming response"));
+    assert!(!content.contains("think>"));
+}
+
+#[tokio::test]
+async fn test_completion_usage_timings() {
+    let (app, _temp_dir) = create_test_app();
+
+    let (status, json) = make_json_request(
+        app.clone(),
+        "POST",
+        "/v1/chat/completions",
+        Some(json!({
+            "model": "test-model",
+            "messages": [{"role": "user", "content": "Hello"}]
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let timings = &json["usage"]["timings"];
+    for field in [
+        "queue_time_ms",
+        "time_to_first_token_ms",
+        "prompt_tokens_per_second",
+        "decode_tokens_per_second",
+        "total_time_ms",
+    ] {
+        assert!(timings[field].is_number(), "missing timings.{}", field);
+    }
+    assert!(timings["decode_tokens_per_second"].as_f64().unwrap() > 0.0);
+
+    let (status, json) = make_json_request(
+        app,
+        "POST",
+        "/v1/completions",
+        Some(json!({"model": "test-model", "prompt": "Once upon a time"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(json["usage"]["timings"]["time_to_first_token_ms"].is_number());
+}
+
+#[tokio::test]
+async fn test_health_live_and_ready() {
+    let (app, _temp_dir) = create_test_app();
+
+    let (status, json) = make_json_request(app.clone(), "GET", "/health/live", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["status"], "ok");
+
+    let (status, json) = make_json_request(app, "GET", "/health/ready", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["status"], "ready");
+    assert_eq!(json["progress"], 1.0);
+}
+
+#[tokio::test]
+async fn test_not_ready_until_warmed_up() {
+    let (state, _temp_dir) = create_test_state();
+    let readiness = Arc::new(Readiness::new());
+    readiness.set_phase(ReadinessPhase::Loading, 0.5);
+    let app = create_router(state.with_readiness(readiness.clone()));
+
+    // Liveness is independent of model loading
+    let (status, _) = make_json_request(app.clone(), "GET", "/health/live", None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, json) = make_json_request(app.clone(), "GET", "/health/ready", None).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(json["status"], "loading");
+    assert_eq!(json["progress"], 0.5);
+
+    // Inference is refused while loading
+    let request_body = json!({
+        "model": "test-model",
+        "messages": [{"role": "user", "content": "Hello"}]
+    });
+    let (status, _) = make_json_request(
+        app.clone(),
+        "POST",
+        "/v1/chat/completions",
+        Some(request_body.clone()),
+    )
+    .await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+
+    readiness.set_phase(ReadinessPhase::Ready, 1.0);
+    let (status, _) =
+        make_json_request(app, "POST", "/v1/chat/completions", Some(request_body)).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_draining_refuses_new_requests() {
+    let (state, _temp_dir) = create_test_state();
+    let readiness = Arc::new(Readiness::ready());
+    let app = create_router(state.with_readiness(readiness.clone()));
+    readiness.drain();
+
+    let (status, json) = make_json_request(app.clone(), "GET", "/health/ready", None).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(json["status"], "draining");
+
+    let (status, json) = make_json_request(
+        app,
+        "POST",
+        "/v1/chat/completions",
+        Some(json!({
+            "model": "test-model",
+            "messages": [{"role": "user", "content": "Hello"}]
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(json["error"]["code"], "server_shutting_down");
+}
+
+#[tokio::test]
+async fn test_ready_reports_load_failure() {
+    let (state, _temp_dir) = create_test_state();
+    let readiness = Arc::new(Readiness::new());
+    readiness.fail("Failed to load model: out of memory".to_string());
+    let app = create_router(state.with_readiness(readiness));
+
+    let (status, json) = make_json_request(app, "GET", "/health/ready", None).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(json["status"], "failed");
+    assert!(json["error"].as_str().unwrap().contains("out of memory"));
+}
+
+/// Register a cross-encoder model next to the test model
+fn register_test_reranker(temp_dir: &TempDir) {
+    let registry = ModelRegistry::new(Some(temp_dir.path().to_path_buf()));
+    registry
+        .register_model(ModelInfo {
+            uuid: "reranker-uuid".to_string(),
+            name: "reranker-model".to_string(),
+            provider: "test".to_string(),
+            author: None,
+            task: Some("text-ranking".to_string()),
+            model_series: Some("xlm-roberta".to_string()),
+            license: None,
+            created_at: chrono::Utc::now().to_rfc3339(),
+            updated_at: chrono::Utc::now().to_rfc3339(),
+            metadata: ModelMetadata {
+                cache: CacheInfo {
+                    revision: "test-rev".to_string(),
+                    size: 1000,
+                    path: "/tmp/reranker-model".to_string(),
+                },
+                context_window: Some(512),
+                safetensors: None,
+            },
+        })
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_rerank() {
+    let (app, temp_dir) = create_test_app();
+    register_test_reranker(&temp_dir);
+
+    let request_body = json!({
+        "model": "reranker-model",
+        "query": "capital of France",
+        "documents": [
+            "Berlin is in Germany",
+            {"text": "Paris is the capital of France"},
+            "France is in Europe"
+        ],
+        "return_documents": true
+    });
+
+    let (status, json) = make_json_request(app, "POST", "/v1/rerank", Some(request_body)).await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert!(json["id"].as_str().unwrap().starts_with("rerank-"));
+    assert_eq!(json["model"], "reranker-model");
+
+    let results = json["results"].as_array().unwrap();
+    let indices: Vec<u64> = results
+        .iter()
+        .map(|r| r["index"].as_u64().unwrap())
+        .collect();
+    assert_eq!(indices, vec![1, 2, 0]);
+    assert_eq!(results[0]["relevance_score"], 1.0);
+    assert_eq!(results[2]["relevance_score"], 0.0);
+    assert_eq!(
+        results[0]["document"]["text"],
+        "Paris is the capital of France"
+    );
+
+    // Each document is scored together with the query
+    let query_len = "capital of France".len() as u64;
+    let documents_len = ("Berlin is in Germany".len()
+        + "Paris is the capital of France".len()
+        + "France is in Europe".len()) as u64;
+    assert_eq!(json["usage"]["total_tokens"], 3 * query_len + documents_len);
+}
+
+#[tokio::test]
+async fn test_rerank_top_n() {
+    let (app, temp_dir) = create_test_app();
+    register_test_reranker(&temp_dir);
+
+    let request_body = json!({
+        "model": "reranker-model",
+        "query": "capital of France",
+        "documents": ["Berlin is in Germany", "Paris is the capital of France"],
+        "top_n": 1
+    });
+
+    let (status, json) = make_json_request(app, "POST", "/v1/rerank", Some(request_body)).await;
+
+    assert_eq!(status, StatusCode::OK);
+    let results = json["results"].as_array().unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0]["index"], 1);
+    assert!(results[0].get("document").is_none());
+}
+
+#[tokio::test]
+async fn test_rerank_requires_ranking_model() {
+    let (app, _temp_dir) = create_test_app();
+
+    let request_body = json!({
+        "model": "test-model",
+        "query": "capital of France",
+        "documents": ["Paris is the capital of France"]
+    });
+
+    let (status, json) = make_json_request(app, "POST", "/v1/rerank", Some(request_body)).await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(json["error"]["message"]
+        .as_str()
+        .unwrap()
+        .contains("does not support reranking"));
+}
+
+#[tokio::test]
+async fn test_rerank_empty_documents() {
+    let (app, temp_dir) = create_test_app();
+    register_test_reranker(&temp_dir);
+
+    let request_body = json!({
+        "model": "reranker-model",
+        "query": "capital of France",
+        "documents": []
+    });
+
+    let (status, _) = make_json_request(app, "POST", "/v1/rerank", Some(request_body)).await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_completion_echo() {
+    let (app, _temp_dir) = create_test_app();
+
+    let request_body = json!({
+        "model": "test-model",
+        "prompt": "Once upon a time",
+        "echo": true
+    });
+
+    let (status, json) =
+        make_json_request(app, "POST", "/v1/completions", Some(request_body)).await;
+
+    assert_eq!(status, StatusCode::OK);
+    let text = json["choices"][0]["text"].as_str().unwrap();
+    assert!(text.starts_with("Once upon a timeThis is a mock response"));
+}
+
+#[tokio::test]
+async fn test_completion_suffix() {
+    let (app, temp_dir) = create_test_app();
+
+    // Code model whose tokenizer has FIM tokens
+    let model_dir = temp_dir.path().join("coder-model");
+    std::fs::create_dir_all(&model_dir).unwrap();
+    std::fs::write(
+        model_dir.join("tokenizer_config.json"),
+        r#"{"additional_special_tokens": ["<fim_prefix>", "<fim_middle>", "<fim_suffix>"]}"#,
+    )
+    .unwrap();
+    let registry = ModelRegistry::new(Some(temp_dir.path().to_path_buf()));
+    registry
+        .register_model(ModelInfo {
+            uuid: "coder-uuid".to_string(),
+            name: "coder-model".to_string(),
+            provider: "test".to_string(),
+            author: None,
+            task: Some("text-generation".to_string()),
+            model_series: Some("starcoder2".to_string()),
+            license: None,
+            created_at: chrono::Utc::now().to_rfc3339(),
+            updated_at: chrono::Utc::now().to_rfc3339(),
+            metadata: ModelMetadata {
+                cache: CacheInfo {
+                    revision: "test-rev".to_string(),
+                    size: 1000,
+                    path: model_dir.to_string_lossy().to_string(),
+                },
+                context_window: Some(4096),
+                safetensors: None,
+            },
+        })
+        .unwrap();
+
+    let request_body = json!({
+        "model": "coder-model",
+        "prompt": "def add(a, b):",
+        "suffix": "return c"
+    });
+
+    let (status, json) =
+        make_json_request(app.clone(), "POST", "/v1/completions", Some(request_body)).await;
+
+    assert_eq!(status, StatusCode::OK);
+    let text = json["choices"][0]["text"].as_str().unwrap();
+    // The mock echoes the first 50 chars of the prompt it was given
+    assert!(text.contains("'<fim_prefix>def add(a, b):<fim_suffix>return c"));
+
+    // Models without FIM tokens reject a suffix
+    let request_body = json!({
+        "model": "test-model",
+        "prompt": "def add(a, b):",
+        "suffix": "return c"
+    });
+
+    let (status, json) =
+        make_json_request(app, "POST", "/v1/completions", Some(request_body)).await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(json["error"]["message"]
+        .as_str()
+        .unwrap()
+        .contains("suffix is not supported"));
+}
+
+#[tokio::test]
+async fn test_sampling_params_out_of_range() {
+    let (app, _temp_dir) = create_test_app();
+
+    let cases = [
+        (json!({"temperature": 2.5}), "temperature"),
+        (json!({"temperature": -0.1}), "temperature"),
+        (json!({"top_p": 1.5}), "top_p"),
+        (json!({"max_tokens": 0}), "max_tokens"),
+        (json!({"n": 0}), "n"),
+    ];
+    for (params, param) in cases {
+        for (uri, mut body) in [
+            (
+                "/v1/chat/completions",
+                json!({"model": "test-model", "messages": [{"role": "user", "content": "Hello"}]}),
+            ),
+            (
+                "/v1/completions",
+                json!({"model": "test-model", "prompt": "Hello"}),
+            ),
+        ] {
+            body.as_object_mut()
+                .unwrap()
+                .extend(params.as_object().unwrap().clone());
+            let (status, json) = make_json_request(app.clone(), "POST", uri, Some(body)).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST, "{} {}", uri, params);
+            assert_eq!(json["error"]["type"], "invalid_request_error");
+            assert_eq!(json["error"]["code"], "invalid_value");
+            assert_eq!(json["error"]["param"], param);
+        }
+    }
+
+    // The bounds themselves are valid
+    let (status, _) = make_json_request(
+        app,
+        "POST",
+        "/v1/chat/completions",
+        Some(json!({
+            "model": "test-model",
+            "messages": [{"role": "user", "content": "Hello"}],
+            "temperature": 2.0,
+            "top_p": 0.0,
+            "max_tokens": 1,
+            "n": 1
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_completion_best_of() {
+    let (app, _temp_dir) = create_test_app();
+
+    let request_body = json!({
+        "model": "test-model",
+        "prompt": "Hello",
+        "n": 2,
+        "best_of": 3
+    });
+
+    let (status, json) =
+        make_json_request(app.clone(), "POST", "/v1/completions", Some(request_body)).await;
+
+    assert_eq!(status, StatusCode::OK);
+    let choices = json["choices"].as_array().unwrap();
+    assert_eq!(choices.len(), 2);
+    assert_eq!(choices[0]["index"], 0);
+    assert_eq!(choices[1]["index"], 1);
+
+    // Every candidate counts towards completion tokens, the prompt only once
+    let usage = &json["usage"];
+    assert_eq!(usage["prompt_tokens"], 5);
+    let completion_tokens = usage["completion_tokens"].as_u64().unwrap();
+    assert_eq!(completion_tokens % 3, 0);
+
+    // best_of must not be smaller than n
+    let request_body = json!({
+        "model": "test-model",
+        "prompt": "Hello",
+        "n": 3,
+        "best_of": 2
+    });
+
+    let (status, json) =
+        make_json_request(app, "POST", "/v1/completions", Some(request_body)).await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(json["error"]["code"], "invalid_value");
+}
+
+#[tokio::test]
+async fn test_completion_prompt_cache() {
+    let (state, temp_dir) = create_test_state();
+    let cache = PromptCache::new(temp_dir.path().join("prompts"), u64::MAX).unwrap();
+    let engine = Arc::new(MockEngine::new().with_prompt_cache(Arc::new(cache)));
+    let app = create_router(AppState::new(engine, state.registry));
+
+    // Long shared prefix followed by a short varying question
+    let document = "All work and no play makes Jack a dull boy. ".repeat(8);
+    let request = |question: &str| {
+        json!({
+            "model": "test-model",
+            "prompt": format!("{}{}", document, question)
+        })
+    };
+
+    let (status, json) = make_json_request(
+        app.clone(),
+        "POST",
+        "/v1/completions",
+        Some(request("Who is Jack?")),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(json["usage"].get("prompt_tokens_details").is_none());
+
+    let (status, json) = make_json_request(
+        app,
+        "POST",
+        "/v1/completions",
+        Some(request("Why is he dull?")),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        json["usage"]["prompt_tokens_details"]["cached_tokens"],
+        PROMPT_CACHE_BLOCK_TOKENS
+    );
+}
+
+/// Helper to make a streaming request and collect the JSON chunks of its SSE
+/// body, checking that the stream ends with [DONE]
+async fn make_stream_request(app: axum::Router, uri: &str, body: Value) -> Vec<Value> {
+    let request = Request::builder()
+        .uri(uri)
+        .method("POST")
+        .header("content-type", "application/json")
+        .body(Body::from(serde_json::to_vec(&body).unwrap()))
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body_bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body_text = String::from_utf8_lossy(&body_bytes);
+    let events: Vec<&str> = body_text
+        .split("\n\n")
+        .filter_map(|line| line.strip_prefix("data: "))
+        .collect();
+
+    assert_eq!(events.last(), Some(&"[DONE]"));
+    events[..events.len() - 1]
+        .iter()
+        .map(|data| serde_json::from_str(data).unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn test_chat_completion_streaming_include_usage() {
+    let (app, _temp_dir) = create_test_app();
+    let request_body = json!({
+        "model": "test-model",
+        "messages": [{"role": "user", "content": "Hello"}],
+        "stream": true,
+        "stream_options": {"include_usage": true},
+        "reasoning_effort": "low"
+    });
+
+    let chunks = make_stream_request(app, "/v1/chat/completions", request_body).await;
+
+    // Only the final chunk has usage, with empty choices
+    let (usage_chunk, chunks) = chunks.split_last().unwrap();
+    assert!(chunks.iter().all(|c| c.get("usage").is_none()));
+    assert_eq!(
+        chunks.last().unwrap()["choices"][0]["finish_reason"],
+        "stop"
+    );
+    assert_eq!(usage_chunk["object"], "chat.completion.chunk");
+    assert_eq!(usage_chunk["choices"].as_array().unwrap().len(), 0);
+
+    let usage = &usage_chunk["usage"];
+    assert_eq!(usage["prompt_tokens"], "User: Hello".len());
+    assert!(usage["completion_tokens"].as_u64().unwrap() > 0);
+    assert_eq!(
+        usage["total_tokens"],
+        usage["prompt_tokens"].as_u64().unwrap() + usage["completion_tokens"].as_u64().unwrap()
+    );
+    assert!(usage["completion_tokens_details"]["reasoning_tokens"]
+        .as_u64()
+        .is_some_and(|n| n > 0));
+    assert!(usage["timings"]["total_time_ms"].is_number());
+}
+
+#[tokio::test]
+async fn test_chat_completion_streaming_usage_with_image() {
+    let (app, temp_dir) = create_test_app();
+    register_vision_model(&temp_dir);
+    let mut request_body = json!({
+        "model": "vision-model",
+        "messages": [{
+            "role": "user",
+            "content": [
+                {"type": "text", "text": "What is in this image?"},
+                {"type": "image_url", "image_url": {"url": test_image_data_url()}}
+            ]
+        }]
+    });
+
+    let (status, json) = make_json_request(
+        app.clone(),
+        "POST",
+        "/v1/chat/completions",
+        Some(request_body.clone()),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Streams report the tokens the image takes, as complete responses do
+    request_body["stream"] = json!(true);
+    request_body["stream_options"] = json!({"include_usage": true});
+    let chunks = make_stream_request(app, "/v1/chat/completions", request_body).await;
+    let usage = &chunks.last().unwrap()["usage"];
+    assert_eq!(usage["prompt_tokens"], json["usage"]["prompt_tokens"]);
+    assert!(
+        usage["prompt_tokens"].as_u64().unwrap()
+            > "User: What is in this image?<image>".len() as u64
+    );
+}
+
+/// Engine whose generations fail, to check how errors reach clients
+#[derive(Clone)]
+struct FailingEngine(MockEngine);
+
+impl InferenceEngine for FailingEngine {
+    async fn generate(&self, _request: &GenerateRequest) -> io::Result<GenerateResponse> {
+        Err(io::Error::other("engine crashed"))
+    }
+
+    async fn generate_stream(&self, _request: &GenerateRequest) -> io::Result<GenerateStream> {
+        Err(io::Error::other("engine crashed"))
+    }
+
+    async fn load_model(
+        &self,
+        model: &str,
+        path: &str,
+        progress: &(dyn Fn(f32) + Send + Sync),
+    ) -> io::Result<()> {
+        self.0.load_model(model, path, progress).await
+    }
+
+    async fn unload_model(&self, model: &str) -> io::Result<()> {
+        self.0.unload_model(model).await
+    }
+
+    async fn load_adapter(&self, base_model: &str, adapter: &str, path: &str) -> io::Result<()> {
+        self.0.load_adapter(base_model, adapter, path).await
+    }
+
+    async fn tokenize(&self, model: &str, text: &str) -> io::Result<Vec<Token>> {
+        self.0.tokenize(model, text).await
+    }
+
+    async fn detokenize(&self, model: &str, ids: &[u32]) -> io::Result<String> {
+        self.0.detokenize(model, ids).await
+    }
+
+    async fn rerank(&self, model: &str, query: &str, documents: &[String]) -> io::Result<Vec<f32>> {
+        self.0.rerank(model, query, documents).await
+    }
+}
+
+#[tokio::test]
+async fn test_chat_completion_streaming_error() {
+    let (state, _temp_dir) = create_test_state();
+    let engine = Arc::new(FailingEngine(MockEngine::new()));
+    let app = create_router(AppState::new(engine, state.registry));
+
+    // The role chunk is already sent, the error ends the stream
+    let chunks = make_stream_request(
+        app,
+        "/v1/chat/completions",
+        json!({
+            "model": "test-model",
+            "messages": [{"role": "user", "content": "Hello"}],
+            "stream": true,
+            "stream_options": {"include_usage": true}
+        }),
+    )
+    .await;
+    assert_eq!(chunks.len(), 2);
+    assert_eq!(chunks[0]["choices"][0]["delta"]["role"], "assistant");
+    assert_eq!(chunks[1]["error"]["type"], "server_error");
+    assert_eq!(chunks[1]["error"]["message"], "engine crashed");
+}
+
+#[tokio::test]
+async fn test_chat_completion_streaming_without_usage() {
+    let (app, _temp_dir) = create_test_app();
+    let request_body = json!({
+        "model": "test-model",
+        "messages": [{"role": "user", "content": "Hello"}],
+        "stream": true,
+        "stream_options": {"include_usage": false}
+    });
+
+    let chunks = make_stream_request(app, "/v1/chat/completions", request_body).await;
+
+    assert!(chunks.iter().all(|c| c.get("usage").is_none()));
+    assert_eq!(
+        chunks.last().unwrap()["choices"][0]["finish_reason"],
+        "stop"
+    );
+}
+
+/// Helper to make a streaming request with named SSE events and collect them
+async fn make_event_stream_request(app: axum::Router, uri: &str, body: Value) -> Vec<Value> {
+    let request = Request::builder()
+        .uri(uri)
+        .method("POST")
+        .header("content-type", "application/json")
+        .body(Body::from(serde_json::to_vec(&body).unwrap()))
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body_bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body_text = String::from_utf8_lossy(&body_bytes);
+
+    // Each event is "event: <type>\ndata: {...}" and the type is repeated in the data
+    body_text
+        .split("\n\n")
+        .filter(|event| !event.is_empty())
+        .map(|event| {
+            let mut lines = event.lines();
+            let event_type = lines.next().unwrap().strip_prefix("event: ").unwrap();
+            let data: Value =
+                serde_json::from_str(lines.next().unwrap().strip_prefix("data: ").unwrap())
+                    .unwrap();
+            assert_eq!(data["type"], event_type);
+            data
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_responses_text_input() {
+    let (app, _temp_dir) = create_test_app();
+    let request_body = json!({
+        "model": "test-model",
+        "instructions": "Be brief.",
+        "input": "Hello"
+    });
+
+    let (status, json) = make_json_request(app, "POST", "/v1/responses", Some(request_body)).await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert!(json["id"].as_str().unwrap().starts_with("resp_"));
+    assert_eq!(json["object"], "response");
+    assert_eq!(json["status"], "completed");
+    assert_eq!(json["instructions"], "Be brief.");
+
+    let output = json["output"].as_array().unwrap();
+    assert_eq!(output.len(), 1);
+    assert_eq!(output[0]["type"], "message");
+    assert_eq!(output[0]["role"], "assistant");
+    let text = output[0]["content"][0]["text"].as_str().unwrap();
+    assert!(text.contains("System: Be brief.\nUser: Hello"));
+
+    let usage = &json["usage"];
+    assert_eq!(
+        usage["input_tokens"],
+        "System: Be brief.\nUser: Hello".len()
+    );
+    assert_eq!(
+        usage["total_tokens"],
+        usage["input_tokens"].as_u64().unwrap() + usage["output_tokens"].as_u64().unwrap()
+    );
+}
+
+#[tokio::test]
+async fn test_responses_previous_response_id() {
+    let (app, _temp_dir) = create_test_app();
+
+    let (status, first) = make_json_request(
+        app.clone(),
+        "POST",
+        "/v1/responses",
+        Some(json!({
+            "model": "test-model",
+            "instructions": "Only for the first turn.",
+            "input": [{"role": "user", "content": "My name is Ada."}]
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let first_id = first["id"].as_str().unwrap();
+    let first_text = first["output"][0]["content"][0]["text"].as_str().unwrap();
+
+    // The stored response can be retrieved
+    let (status, stored) = make_json_request(
+        app.clone(),
+        "GET",
+        &format!("/v1/responses/{}", first_id),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(stored["id"], first_id);
+
+    let (status, second) = make_json_request(
+        app.clone(),
+        "POST",
+        "/v1/responses",
+        Some(json!({
+            "model": "test-model",
+            "previous_response_id": first_id,
+            "input": [{
+                "type": "message",
+                "role": "user",
+                "content": [{"type": "input_text", "text": "What is my name?"}]
+            }]
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(second["previous_response_id"], first_id);
+
+    // The prompt replays the first turn, without its instructions
+    let prompt = format!(
+        "User: My name is Ada.\nAssistant: {}\nUser: What is my name?",
+        first_text
+    );
+    assert_eq!(
+        second["usage"]["input_tokens"],
+        prompt.chars().count() as u64
+    );
+
+    // Deleted responses can no longer be chained
+    let (status, json) = make_json_request(
+        app.clone(),
+        "DELETE",
+        &format!("/v1/responses/{}", first_id),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["deleted"], true);
+
+    let (status, json) = make_json_request(
+        app,
+        "POST",
+        "/v1/responses",
+        Some(json!({
+            "model": "test-model",
+            "previous_response_id": first_id,
+            "input": "Hello again"
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(json["error"]["code"], "response_not_found");
+}
+
+#[tokio::test]
+async fn test_responses_store_false() {
+    let (app, _temp_dir) = create_test_app();
+
+    let (status, json) = make_json_request(
+        app.clone(),
+        "POST",
+        "/v1/responses",
+        Some(json!({"model": "test-model", "input": "Hello", "store": false})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let uri = format!("/v1/responses/{}", json["id"].as_str().unwrap());
+    let (status, _) = make_json_request(app, "GET", &uri, None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_responses_tool_calls() {
+    let (app, _temp_dir) = create_test_app();
+    let tools = json!([{
+        "type": "function",
+        "name": "get_weather",
+        "description": "Get the weather in a city",
+        "parameters": {"type": "object", "properties": {"city": {"type": "string"}}}
+    }]);
+
+    let (status, first) = make_json_request(
+        app.clone(),
+        "POST",
+        "/v1/responses",
+        Some(json!({
+            "model": "test-model",
+            "input": "What is the weather in Paris?",
+            "tools": tools
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let output = first["output"].as_array().unwrap();
+    assert_eq!(output.len(), 1);
+    assert_eq!(output[0]["type"], "function_call");
+    assert_eq!(output[0]["name"], "get_weather");
+    assert_eq!(output[0]["arguments"], "{}");
+    let call_id = output[0]["call_id"].as_str().unwrap();
+    assert!(call_id.starts_with("call_"));
+
+    // Send the tool result back, the model answers with a message
+    let (status, second) = make_json_request(
+        app,
+        "POST",
+        "/v1/responses",
+        Some(json!({
+            "model": "test-model",
+            "previous_response_id": first["id"],
+            "input": [{"type": "function_call_output", "call_id": call_id, "output": "Sunny"}],
+            "tools": tools
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(second["output"][0]["type"], "message");
+}
+
+#[tokio::test]
+async fn test_responses_streaming() {
+    let (app, _temp_dir) = create_test_app();
+    let request_body = json!({
+        "model": "test-model",
+        "input": "Hello",
+        "stream": true,
+        "reasoning": {"effort": "low"}
+    });
+
+    let events = make_event_stream_request(app.clone(), "/v1/responses", request_body).await;
+
+    let types: Vec<&str> = events.iter().map(|e| e["type"].as_str().unwrap()).collect();
+    assert_eq!(types[0], "response.created");
+    assert_eq!(types[1], "response.in_progress");
+    assert_eq!(*types.last().unwrap(), "response.completed");
+    assert!(types.contains(&"response.reasoning_text.delta"));
+    assert!(types.contains(&"response.output_text.done"));
+
+    // Sequence numbers increase by one
+    for (i, event) in events.iter().enumerate() {
+        assert_eq!(event["sequence_number"], i);
+    }
+
+    let text: String = events
+        .iter()
+        .filter(|e| e["type"] == "response.output_text.delta")
+        .map(|e| e["delta"].as_str().unwrap())
+        .collect();
+    assert!(text.starts_with("This is a mock streaming response"));
+
+    // The completed response has reasoning and message items, and is stored
+    let completed = &events.last().unwrap()["response"];
+    assert_eq!(completed["status"], "completed");
+    assert_eq!(completed["output"][0]["type"], "reasoning");
+    assert_eq!(completed["output"][1]["content"][0]["text"], text);
+    assert!(
+        completed["usage"]["output_tokens_details"]["reasoning_tokens"]
+            .as_u64()
+            .is_some_and(|n| n > 0)
+    );
+
+    let uri = format!("/v1/responses/{}", completed["id"].as_str().unwrap());
+    let (status, _) = make_json_request(app, "GET", &uri, None).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_responses_streaming_tool_call() {
+    let (app, _temp_dir) = create_test_app();
+    let request_body = json!({
+        "model": "test-model",
+        "input": "What is the weather in Paris?",
+        "tools": [{"type": "function", "name": "get_weather"}],
+        "stream": true
+    });
+
+    let events = make_event_stream_request(app, "/v1/responses", request_body).await;
+
+    let done = events
+        .iter()
+        .find(|e| e["type"] == "response.function_call_arguments.done")
+        .unwrap();
+    assert_eq!(done["arguments"], "{}");
+
+    let completed = &events.last().unwrap()["response"];
+    assert_eq!(completed["output"][0]["type"], "function_call");
+    assert_eq!(completed["output"][0]["name"], "get_weather");
+}
+
+#[tokio::test]
+async fn test_messages() {
+    let (app, _temp_dir) = create_test_app();
+    let request_body = json!({
+        "model": "test-model",
+        "system": [{"type": "text", "text": "Be brief."}],
+        "messages": [
+            {"role": "user", "content": [{"type": "text", "text": "Hello"}]}
+        ],
+        "max_tokens": 100
+    });
+
+    let (status, json) = make_json_request(app, "POST", "/v1/messages", Some(request_body)).await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert!(json["id"].as_str().unwrap().starts_with("msg_"));
+    assert_eq!(json["type"], "message");
+    assert_eq!(json["role"], "assistant");
+    assert_eq!(json["stop_reason"], "end_turn");
+    assert_eq!(json["content"][0]["type"], "text");
+    assert!(json["content"][0]["text"]
+        .as_str()
+        .unwrap()
+        .contains("System: Be brief.\nUser: Hello"));
+    assert_eq!(
+        json["usage"]["input_tokens"],
+        "System: Be brief.\nUser: Hello".len()
+    );
+    assert_eq!(json["usage"]["output_tokens"], 20);
+}
+
+#[tokio::test]
+async fn test_messages_max_tokens_stop_reason() {
+    let (app, _temp_dir) = create_test_app();
+    let request_body = json!({
+        "model": "test-model",
+        "messages": [{"role": "user", "content": "Hello"}],
+        "max_tokens": 10
+    });
+
+    let (status, json) = make_json_request(app, "POST", "/v1/messages", Some(request_body)).await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["stop_reason"], "max_tokens");
+}
+
+#[tokio::test]
+async fn test_messages_validation() {
+    let (app, _temp_dir) = create_test_app();
+
+    let (status, json) = make_json_request(
+        app.clone(),
+        "POST",
+        "/v1/messages",
+        Some(json!({
+            "model": "test-model",
+            "messages": [{"role": "system", "content": "Be brief."}],
+            "max_tokens": 100
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(json["error"]["type"], "invalid_request_error");
+
+    let (status, _) = make_json_request(
+        app,
+        "POST",
+        "/v1/messages",
+        Some(json!({
+            "model": "nonexistent-model",
+            "messages": [{"role": "user", "content": "Hello"}],
+            "max_tokens": 100
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_messages_tool_use() {
+    let (app, _temp_dir) = create_test_app();
+    let tools = json!([{
+        "name": "get_weather",
+        "description": "Get the weather in a city",
+        "input_schema": {"type": "object", "properties": {"city": {"type": "string"}}}
+    }]);
+
+    let (status, first) = make_json_request(
+        app.clone(),
+        "POST",
+        "/v1/messages",
+        Some(json!({
+            "model": "test-model",
+            "messages": [{"role": "user", "content": "What is the weather in Paris?"}],
+            "tools": tools,
+            "max_tokens": 100
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(first["stop_reason"], "tool_use");
+    let tool_use = &first["content"][0];
+    assert_eq!(tool_use["type"], "tool_use");
+    assert_eq!(tool_use["name"], "get_weather");
+    assert_eq!(tool_use["input"], json!({}));
+
+    // Send the tool result back, the model answers with text
+    let (status, second) = make_json_request(
+        app,
+        "POST",
+        "/v1/messages",
+        Some(json!({
+            "model": "test-model",
+            "messages": [
+                {"role": "user", "content": "What is the weather in Paris?"},
+                {"role": "assistant", "content": first["content"]},
+                {"role": "user", "content": [
+                    {"type": "tool_result", "tool_use_id": tool_use["id"], "content": "Sunny"}
+                ]}
+            ],
+            "tools": tools,
+            "max_tokens": 100
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(second["stop_reason"], "end_turn");
+    assert_eq!(second["content"][0]["type"], "text");
+}
+
+#[tokio::test]
+async fn test_messages_streaming() {
+    let (app, _temp_dir) = create_test_app();
+    let request_body = json!({
+        "model": "test-model",
+        "messages": [{"role": "user", "content": "Hello"}],
+        "max_tokens": 100,
+        "thinking": {"type": "enabled", "budget_tokens": 1024},
+        "stream": true
+    });
+
+    let events = make_event_stream_request(app, "/v1/messages", request_body).await;
+
+    let types: Vec<&str> = events.iter().map(|e| e["type"].as_str().unwrap()).collect();
+    assert_eq!(types[0], "message_start");
+    assert_eq!(types[1], "ping");
+    assert_eq!(types[types.len() - 2], "message_delta");
+    assert_eq!(types[types.len() - 1], "message_stop");
+    assert_eq!(
+        events[0]["message"]["usage"]["input_tokens"],
+        "User: Hello".len()
+    );
+
+    // A thinking block followed by a text block
+    let starts: Vec<&Value> = events
+        .iter()
+        .filter(|e| e["type"] == "content_block_start")
+        .collect();
+    assert_eq!(starts.len(), 2);
+    assert_eq!(starts[0]["content_block"]["type"], "thinking");
+    assert_eq!(starts[1]["index"], 1);
+    assert_eq!(starts[1]["content_block"]["type"], "text");
+    assert_eq!(
+        types.iter().filter(|t| **t == "content_block_stop").count(),
+        2
+    );
+
+    let text: String = events
+        .iter()
+        .filter(|e| e["delta"]["type"] == "text_delta")
+        .map(|e| e["delta"]["text"].as_str().unwrap())
+        .collect();
+    assert!(text.starts_with("This is a mock streaming response"));
+
+    let message_delta = &events[events.len() - 2];
+    assert_eq!(message_delta["delta"]["stop_reason"], "end_turn");
+    assert!(message_delta["usage"]["output_tokens"].as_u64().unwrap() > 0);
+}
+
+#[tokio::test]
+async fn test_messages_streaming_tool_use() {
+    let (app, _temp_dir) = create_test_app();
+    let request_body = json!({
+        "model": "test-model",
+        "messages": [{"role": "user", "content": "What is the weather in Paris?"}],
+        "tools": [{"name": "get_weather", "input_schema": {"type": "object"}}],
+        "max_tokens": 100,
+        "stream": true
+    });
+
+    let events = make_event_stream_request(app, "/v1/messages", request_body).await;
+
+    let start = events
+        .iter()
+        .find(|e| e["type"] == "content_block_start")
+        .unwrap();
+    assert_eq!(start["content_block"]["type"], "tool_use");
+    assert_eq!(start["content_block"]["name"], "get_weather");
+
+    let delta = events
+        .iter()
+        .find(|e| e["type"] == "content_block_delta")
+        .unwrap();
+    assert_eq!(delta["delta"]["type"], "input_json_delta");
+    assert_eq!(delta["delta"]["partial_json"], "{}");
+
+    assert_eq!(events[events.len() - 2]["delta"]["stop_reason"], "tool_use");
+}
+
+/// Helper to make a streaming Ollama request and collect its NDJSON lines
+async fn make_ndjson_request(app: axum::Router, uri: &str, body: Value) -> Vec<Value> {
+    let request = Request::builder()
+        .uri(uri)
+        .method("POST")
+        .header("content-type", "application/json")
+        .body(Body::from(serde_json::to_vec(&body).unwrap()))
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "application/x-ndjson");
+
+    let body_bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    String::from_utf8_lossy(&body_bytes)
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn test_ollama_generate() {
+    let (app, _temp_dir) = create_test_app();
+    let request_body = json!({
+        "model": "test-model:latest",
+        "system": "Be brief.",
+        "prompt": "Hello",
+        "stream": false
+    });
+
+    let (status, json) = make_json_request(app, "POST", "/api/generate", Some(request_body)).await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["model"], "test-model:latest");
+    assert_eq!(json["done"], true);
+    assert_eq!(json["done_reason"], "stop");
+    assert!(json["response"]
+        .as_str()
+        .unwrap()
+        .contains("System: Be brief.\nUser: Hello"));
+    assert_eq!(
+        json["prompt_eval_count"],
+        "System: Be brief.\nUser: Hello".len()
+    );
+    assert_eq!(json["eval_count"], 20);
+    assert!(json["total_duration"].as_u64().is_some());
+}
+
+#[tokio::test]
+async fn test_ollama_generate_raw() {
+    let (app, _temp_dir) = create_test_app();
+    let request_body = json!({
+        "model": "test-model",
+        "prompt": "Once upon a time",
+        "raw": true,
+        "stream": false,
+        "options": {"num_predict": 10}
+    });
+
+    let (status, json) = make_json_request(app, "POST", "/api/generate", Some(request_body)).await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert!(json["response"]
+        .as_str()
+        .unwrap()
+        .contains("for prompt: 'Once upon a time'"));
+    assert_eq!(json["done_reason"], "length");
+}
+
+#[tokio::test]
+async fn test_ollama_generate_load() {
+    let (app, _temp_dir) = create_test_app();
+    let (status, json) = make_json_request(
+        app,
+        "POST",
+        "/api/generate",
+        Some(json!({"model": "test-model"})),
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["done"], true);
+    assert_eq!(json["done_reason"], "load");
+}
+
+#[tokio::test]
+async fn test_ollama_chat_streaming() {
+    let (app, _temp_dir) = create_test_app();
+    let request_body = json!({
+        "model": "test-model",
+        "messages": [{"role": "user", "content": "Hello"}],
+        "think": true
+    });
+
+    let lines = make_ndjson_request(app, "/api/chat", request_body).await;
+
+    let (last, deltas) = lines.split_last().unwrap();
+    assert!(deltas.iter().all(|line| line["done"] == false));
+    assert_eq!(last["done"], true);
+    assert_eq!(last["done_reason"], "stop");
+    assert_eq!(last["prompt_eval_count"], "User: Hello".len());
+    assert!(last["eval_count"].as_u64().unwrap() > 0);
+
+    let thinking: String = deltas
+        .iter()
+        .filter_map(|line| line["message"]["thinking"].as_str())
+        .collect();
+    assert!(!thinking.is_empty());
+
+    let content: String = deltas
+        .iter()
+        .map(|line| line["message"]["content"].as_str().unwrap())
+        .collect();
+    assert!(content.starts_with("This is a mock streaming response"));
+}
+
+#[tokio::test]
+async fn test_ollama_chat_streaming_with_image() {
+    use base64::{engine::general_purpose::STANDARD, Engine as _};
+    let (app, temp_dir) = create_test_app();
+    register_vision_model(&temp_dir);
+    let mut request_body = json!({
+        "model": "vision-model",
+        "messages": [{
+            "role": "user",
+            "content": "What is in this image?",
+            "images": [STANDARD.encode(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR")]
+        }],
+        "stream": false
+    });
+
+    let (status, json) =
+        make_json_request(app.clone(), "POST", "/api/chat", Some(request_body.clone())).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Streams count the tokens the image takes, as complete responses do
+    request_body["stream"] = json!(true);
+    let lines = make_ndjson_request(app, "/api/chat", request_body).await;
+    let last = lines.last().unwrap();
+    assert_eq!(last["done"], true);
+    assert_eq!(last["prompt_eval_count"], json["prompt_eval_count"]);
+    assert!(
+        last["prompt_eval_count"].as_u64().unwrap()
+            > "User: What is in this image?<image>".len() as u64
+    );
+}
+
+#[tokio::test]
+async fn test_ollama_chat_tool_calls() {
+    let (app, _temp_dir) = create_test_app();
+    let tools = json!([{
+        "type": "function",
+        "function": {
+            "name": "get_weather",
+            "parameters": {"type": "object", "properties": {"city": {"type": "string"}}}
+        }
+    }]);
+
+    let (status, first) = make_json_request(
+        app.clone(),
+        "POST",
+        "/api/chat",
+        Some(json!({
+            "model": "test-model",
+            "messages": [{"role": "user", "content": "What is the weather in Paris?"}],
+            "tools": tools,
+            "stream": false
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let message = &first["message"];
+    assert_eq!(message["content"], "");
+    assert_eq!(message["tool_calls"][0]["function"]["name"], "get_weather");
+    assert_eq!(message["tool_calls"][0]["function"]["arguments"], json!({}));
+
+    // Send the tool result back, the model answers with text
+    let (status, second) = make_json_request(
+        app,
+        "POST",
+        "/api/chat",
+        Some(json!({
+            "model": "test-model",
+            "messages": [
+                {"role": "user", "content": "What is the weather in Paris?"},
+                message,
+                {"role": "tool", "content": "Sunny"}
+            ],
+            "tools": tools,
+            "stream": false
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(second["message"].get("tool_calls").is_none());
+    assert!(!second["message"]["content"].as_str().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_ollama_errors() {
+    let (app, _temp_dir) = create_test_app();
+
+    // Errors of the shared OpenAI helpers are rewritten to Ollama's format
+    let (status, json) = make_json_request(
+        app.clone(),
+        "POST",
+        "/api/chat",
+        Some(json!({"model": "nonexistent-model", "messages": []})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(json["error"], "Model 'nonexistent-model' not found");
+
+    let (status, json) = make_json_request(
+        app,
+        "POST",
+        "/api/show",
+        Some(json!({"model": "nonexistent-model"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert!(json["error"].as_str().unwrap().contains("not found"));
+}
+
+#[tokio::test]
+async fn test_ollama_tags_and_show() {
+    let (state, _temp_dir) = create_test_state();
+    state
+        .registry
+        .register_adapter(AdapterInfo {
+            name: "test-adapter".to_string(),
+            base_model: "test-model".to_string(),
+            path: "/tmp/test-adapter".to_string(),
+            created_at: chrono::Utc::now().to_rfc3339(),
+        })
+        .unwrap();
+    let app = create_router(state);
+
+    let (status, json) = make_json_request(app.clone(), "GET", "/api/tags", None).await;
+    assert_eq!(status, StatusCode::OK);
+    let models = json["models"].as_array().unwrap();
+    assert_eq!(models.len(), 2);
+    assert_eq!(models[0]["name"], "test-model");
+    assert_eq!(models[0]["digest"], "test-rev");
+    assert_eq!(models[0]["size"], 1000);
+    assert_eq!(models[0]["details"]["family"], "test-series");
+    assert_eq!(models[1]["name"], "test-adapter");
+    assert_eq!(models[1]["details"]["parent_model"], "test-model");
+
+    let (status, json) = make_json_request(
+        app,
+        "POST",
+        "/api/show",
+        Some(json!({"name": "test-model"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["license"], "MIT");
+    assert_eq!(json["capabilities"], json!(["completion"]));
+    assert_eq!(json["model_info"]["test-series.context_length"], 2048);
+}
+
+#[tokio::test]
+async fn test_ollama_ps() {
+    let (state, _temp_dir) = create_test_state();
+    let readiness = Arc::new(Readiness::new());
+    readiness.set_model("test-model");
+    let app = create_router(state.with_readiness(readiness.clone()));
+
+    // Nothing is loaded until the model is ready
+    let (status, json) = make_json_request(app.clone(), "GET", "/api/ps", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["models"], json!([]));
+
+    readiness.set_phase(ReadinessPhase::Ready, 1.0);
+    let (status, json) = make_json_request(app, "GET", "/api/ps", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["models"][0]["name"], "test-model");
+    assert_eq!(json["models"][0]["size_vram"], 1000);
+}
+
+#[tokio::test]
+async fn test_ollama_delete() {
+    let (app, _temp_dir) = create_admin_app();
+
+    let (status, _) = make_json_request(
+        app.clone(),
+        "DELETE",
+        "/api/delete",
+        Some(json!({"model": "test-model"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (_, json) = make_json_request(app.clone(), "GET", "/api/tags", None).await;
+    assert_eq!(json["models"], json!([]));
+
+    let (status, json) = make_json_request(
+        app,
+        "DELETE",
+        "/api/delete",
+        Some(json!({"model": "test-model"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(json["error"], "model 'test-model' not found");
+}
+
+#[tokio::test]
+async fn test_ollama_delete_served_model() {
+    let (state, _temp_dir) = create_test_state();
+    let readiness = Arc::new(Readiness::ready());
+    readiness.set_model("test-model");
+    let app = create_router(state.with_readiness(readiness).with_admin());
+
+    for name in ["test-model", "Test-Model:latest"] {
+        let (status, json) = make_json_request(
+            app.clone(),
+            "DELETE",
+            "/api/delete",
+            Some(json!({"model": name})),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(
+            json["error"],
+            "model 'test-model' is being served and cannot be deleted"
+        );
+    }
+
+    let (_, json) = make_json_request(app, "GET", "/api/tags", None).await;
+    assert_eq!(json["models"][0]["name"], "test-model");
+}
+
+/// Helper to create the test app with the model management endpoints
+fn create_admin_app() -> (axum::Router, TempDir) {
+    let (state, temp_dir) = create_test_state();
+    (create_router(state.with_admin()), temp_dir)
+}
+
+#[tokio::test]
+async fn test_admin_disabled_by_default() {
+    let (app, _temp_dir) = create_test_app();
+
+    let (status, _) = make_json_request(app.clone(), "GET", "/admin/models/test-model", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) =
+        make_json_request(app.clone(), "DELETE", "/admin/models/test-model", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = make_json_request(
+        app.clone(),
+        "POST",
+        "/admin/models",
+        Some(json!({"model": "a/b"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // Nor through the Ollama API
+    let (status, _) = make_json_request(
+        app.clone(),
+        "DELETE",
+        "/api/delete",
+        Some(json!({"model": "test-model"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = make_json_request(
+        app.clone(),
+        "POST",
+        "/api/pull",
+        Some(json!({"model": "a/b"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // The model is left in place
+    let (status, _) = make_json_request(app, "GET", "/v1/models/test-model", None).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_admin_get_model() {
+    let (app, _temp_dir) = create_admin_app();
+
+    let (status, json) =
+        make_json_request(app.clone(), "GET", "/admin/models/test-model", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["name"], "test-model");
+    assert_eq!(json["license"], "MIT");
+    assert_eq!(json["metadata"]["cache"]["revision"], "test-rev");
+    assert_eq!(json["metadata"]["context_window"], 2048);
+
+    // Hugging Face names contain a slash
+    let (status, json) = make_json_request(app, "GET", "/admin/models/org/missing", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(json["error"]["code"], "model_not_found");
+    assert_eq!(json["error"]["message"], "Model 'org/missing' not found");
+}
+
+#[tokio::test]
+async fn test_admin_delete_model() {
+    let (app, _temp_dir) = create_admin_app();
+
+    let (status, json) =
+        make_json_request(app.clone(), "DELETE", "/admin/models/test-model", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        json,
+        json!({"id": "test-model", "object": "model", "deleted": true})
+    );
+
+    let (status, _) = make_json_request(app.clone(), "GET", "/admin/models/test-model", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, json) = make_json_request(app, "DELETE", "/admin/models/test-model", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(json["error"]["code"], "model_not_found");
+}
+
+#[tokio::test]
+async fn test_admin_delete_served_model() {
+    let (state, _temp_dir) = create_test_state();
+    let readiness = Arc::new(Readiness::ready());
+    readiness.set_model("test-model");
+    let app = create_router(state.with_readiness(readiness).with_admin());
+
+    let (status, json) =
+        make_json_request(app.clone(), "DELETE", "/admin/models/test-model", None).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(json["error"]["code"], "model_in_use");
+
+    // The registry matches names ignoring case, so does the check
+    let (status, _) =
+        make_json_request(app.clone(), "DELETE", "/admin/models/Test-Model", None).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    // The model is left in place
+    let (status, _) = make_json_request(app, "GET", "/admin/models/test-model", None).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_admin_pull_requires_model() {
+    let (app, _temp_dir) = create_admin_app();
+
+    let (status, json) =
+        make_json_request(app, "POST", "/admin/models", Some(json!({"model": " "}))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(json["error"]["param"], "model");
+}
+
+/// Pull a model that cannot be downloaded, returning the streamed body
+async fn pull_invalid_model(accept: &str) -> (axum::http::HeaderMap, String) {
+    let (app, _temp_dir) = create_admin_app();
+    let request = Request::builder()
+        .uri("/admin/models")
+        .method("POST")
+        .header("content-type", "application/json")
+        .header("accept", accept)
+        .body(Body::from(
+            json!({"model": "invalid/nonexistent-model-12345"}).to_string(),
+        ))
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let headers = response.headers().clone();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (headers, String::from_utf8(body.to_vec()).unwrap())
+}
+
+#[tokio::test]
+async fn test_admin_pull_streams_ndjson() {
+    let (headers, body) = pull_invalid_model("*/*").await;
+    assert_eq!(headers["content-type"], "application/x-ndjson");
+
+    let events: Vec<Value> = body
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(events[0]["status"], "pulling_manifest");
+    assert_eq!(events[0]["model"], "invalid/nonexistent-model-12345");
+    // The stream ends with the error failing the download
+    let last = events.last().unwrap();
+    assert_eq!(last["status"], "error");
+    assert!(last["error"]
+        .as_str()
+        .unwrap()
+        .contains("nonexistent-model-12345"));
+}
+
+#[tokio::test]
+async fn test_admin_pull_streams_sse() {
+    let (headers, body) = pull_invalid_model("text/event-stream").await;
+    assert_eq!(headers["content-type"], "text/event-stream");
+
+    let events: Vec<Value> = body
+        .lines()
+        .filter_map(|line| line.strip_prefix("data: "))
+        .map(|data| serde_json::from_str(data).unwrap())
+        .collect();
+    assert_eq!(events[0]["status"], "pulling_manifest");
+    assert_eq!(events.last().unwrap()["status"], "error");
+}
+
+/// Helper to make a GET request with optional headers, returning the status and body
+async fn make_authorized_request(
+    app: axum::Router,
+    uri: &str,
+    headers: &[(&str, &str)],
+) -> (StatusCode, axum::http::HeaderMap, Value) {
+    let mut request = Request::builder().uri(uri).method("GET");
+    for (name, value) in headers {
+        request = request.header(*name, *value);
+    }
+
+    let response = app
+        .oneshot(request.body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let status = response.status();
+    let headers = response.headers().clone();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, headers, serde_json::from_slice(&body).unwrap())
+}
+
+#[tokio::test]
+async fn test_api_key_required() {
+    let (state, _temp_dir) = create_test_state();
+    let keys = ApiKeys::new(vec!["secret-key-1".to_string()]);
+    let app = create_router(state.with_auth(Arc::new(keys)));
+
+    let (status, headers, json) = make_authorized_request(app.clone(), "/v1/models", &[]).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(headers["www-authenticate"], "Bearer");
+    assert_eq!(json["error"]["type"], "invalid_request_error");
+    assert!(json["error"]["code"].is_null());
+
+    let (status, _, json) = make_authorized_request(
+        app.clone(),
+        "/v1/models",
+        &[("authorization", "Bearer wrong-key-123")],
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(json["error"]["code"], "invalid_api_key");
+    assert!(!json["error"]["message"]
+        .as_str()
+        .unwrap()
+        .contains("wrong-key-123"));
+
+    let (status, _, _) = make_authorized_request(
+        app.clone(),
+        "/v1/models",
+        &[("authorization", "Bearer secret-key-1")],
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Anthropic clients send the key in x-api-key
+    let (status, _, _) =
+        make_authorized_request(app.clone(), "/v1/models", &[("x-api-key", "secret-key-1")]).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Health checks are authenticated unless made public
+    let (status, _, _) = make_authorized_request(app, "/health", &[]).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_api_key_public_health() {
+    let (state, _temp_dir) = create_test_state();
+    let keys = ApiKeys::new(vec!["secret-key-1".to_string()]).with_public_health(true);
+    let app = create_router(state.with_auth(Arc::new(keys)));
+
+    let (status, _, _) = make_authorized_request(app.clone(), "/health", &[]).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _, _) = make_authorized_request(app.clone(), "/health/ready", &[]).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, _, _) = make_authorized_request(app, "/v1/models", &[]).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_responses_scoped_to_api_key() {
+    let (state, _temp_dir) = create_test_state();
+    let keys = ApiKeys::new(vec!["secret-key-1".to_string(), "secret-key-2".to_string()]);
+    let app = create_router(state.with_auth(Arc::new(keys)));
+    let request = |method: &str, uri: &str, key: &str, body: Option<Value>| {
+        let request = Request::builder()
+            .uri(uri)
+            .method(method)
+            .header("authorization", format!("Bearer {}", key))
+            .header("content-type", "application/json");
+        let body = body.map_or_else(Body::empty, |body| Body::from(body.to_string()));
+        let response = app.clone().oneshot(request.body(body).unwrap());
+        async move {
+            let response = response.await.unwrap();
+            let status = response.status();
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            (status, serde_json::from_slice::<Value>(&body).unwrap())
+        }
+    };
+
+    let (status, first) = request(
+        "POST",
+        "/v1/responses",
+        "secret-key-1",
+        Some(json!({"model": "test-model", "input": "My name is Ada."})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let uri = format!("/v1/responses/{}", first["id"].as_str().unwrap());
+
+    // Another key can't read, chain or delete the response
+    let (status, json) = request("GET", &uri, "secret-key-2", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(json["error"]["code"], "response_not_found");
+
+    let (status, json) = request(
+        "POST",
+        "/v1/responses",
+        "secret-key-2",
+        Some(json!({
+            "model": "test-model",
+            "previous_response_id": first["id"],
+            "input": "What is my name?"
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(json["error"]["param"], "previous_response_id");
+
+    let (status, _) = request("DELETE", &uri, "secret-key-2", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // The key that created it still can
+    let (status, json) = request("GET", &uri, "secret-key-1", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["id"], first["id"]);
+
+    let (status, _) = request(
+        "POST",
+        "/v1/responses",
+        "secret-key-1",
+        Some(json!({
+            "model": "test-model",
+            "previous_response_id": first["id"],
+            "input": "What is my name?"
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, json) = request("DELETE", &uri, "secret-key-1", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["deleted"], true);
+}
+
+#[tokio::test]
+async fn test_api_key_from_storage() {
+    let (state, _temp_dir) = create_test_state();
+    state
+        .storage
+        .create_api_key(StoredApiKey {
+            id: "key_1".to_string(),
+            name: "ci".to_string(),
+            key_hash: super::auth::hash_api_key("stored-key-1"),
+            prefix: "stored-key-1".to_string(),
+            created_at: chrono::Utc::now().to_rfc3339(),
+            revoked_at: None,
+            requests_per_minute: None,
+            tokens_per_minute: None,
+            daily_token_quota: None,
+        })
+        .unwrap();
+    let keys = ApiKeys::new(Vec::new()).with_storage(state.storage.clone());
+    let storage = state.storage.clone();
+    let app = create_router(state.with_auth(Arc::new(keys)));
+
+    let headers = [("authorization", "Bearer stored-key-1")];
+    let (status, _, _) = make_authorized_request(app.clone(), "/v1/models", &headers).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Revoked keys are rejected without a restart
+    storage
+        .revoke_api_key("ci", &chrono::Utc::now().to_rfc3339())
+        .unwrap();
+    let (status, _, _) = make_authorized_request(app, "/v1/models", &headers).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+/// Helper to create an app requiring `secret-key-1`, with the given default limits
+fn create_rate_limited_app(limits: RateLimits) -> (axum::Router, TempDir) {
+    let (state, temp_dir) = create_test_state();
+    let keys = ApiKeys::new(vec!["secret-key-1".to_string()]);
+    let limiter = RateLimiter::new(limits, state.storage.clone());
+    let app = create_router(
+        state
+            .with_auth(Arc::new(keys))
+            .with_rate_limiter(Arc::new(limiter)),
+    );
+    (app, temp_dir)
+}
+
+#[tokio::test]
+async fn test_rate_limit_requests() {
+    let (app, _temp_dir) = create_rate_limited_app(RateLimits {
+        requests_per_minute: Some(2),
+        ..Default::default()
+    });
+    let headers = [("authorization", "Bearer secret-key-1")];
+
+    let (status, response_headers, _) =
+        make_authorized_request(app.clone(), "/v1/models", &headers).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(response_headers["x-ratelimit-limit-requests"], "2");
+    assert_eq!(response_headers["x-ratelimit-remaining-requests"], "1");
+    assert_eq!(response_headers["x-ratelimit-reset-requests"], "1m0s");
+
+    let (status, _, _) = make_authorized_request(app.clone(), "/v1/models", &headers).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, response_headers, json) =
+        make_authorized_request(app, "/v1/models", &headers).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(json["error"]["type"], "requests");
+    assert_eq!(json["error"]["code"], "rate_limit_exceeded");
+    assert_eq!(response_headers["x-ratelimit-remaining-requests"], "0");
+    let retry_after: u64 = response_headers["retry-after"]
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!((1..=60).contains(&retry_after));
+}
+
+#[tokio::test]
+async fn test_rate_limit_tokens() {
+    let (app, _temp_dir) = create_rate_limited_app(RateLimits {
+        tokens_per_minute: Some(30),
+        ..Default::default()
+    });
+    let request = |app: axum::Router| {
+        let body = json!({
+            "model": "test-model",
+            "messages": [{"role": "user", "content": "Hello"}]
+        });
+        app.oneshot(
+            Request::builder()
+                .uri("/v1/chat/completions")
+                .method("POST")
+                .header("authorization", "Bearer secret-key-1")
+                .header("content-type", "application/json")
+                .body(Body::from(serde_json::to_vec(&body).unwrap()))
+                .unwrap(),
+        )
+    };
+
+    // The mock completes with 20 tokens, the prompt takes the key over its limit
+    let response = request(app.clone()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["x-ratelimit-remaining-tokens"], "30");
+
+    let response = request(app).await.unwrap();
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(response.headers()["x-ratelimit-remaining-tokens"], "0");
+    assert!(response.headers().contains_key("retry-after"));
+}
+
+#[tokio::test]
+async fn test_rate_limit_rerank_tokens() {
+    let (app, temp_dir) = create_rate_limited_app(RateLimits {
+        tokens_per_minute: Some(30),
+        ..Default::default()
+    });
+    register_test_reranker(&temp_dir);
+    let request = |app: axum::Router| {
+        let body = json!({
+            "model": "reranker-model",
+            "query": "capital of France",
+            "documents": ["Paris is the capital of France."]
+        });
+        app.oneshot(
+            Request::builder()
+                .uri("/v1/rerank")
+                .method("POST")
+                .header("authorization", "Bearer secret-key-1")
+                .header("content-type", "application/json")
+                .body(Body::from(serde_json::to_vec(&body).unwrap()))
+                .unwrap(),
+        )
+    };
+
+    // Reranking counts its tokens like generations do, not bypassing the limit
+    let response = request(app.clone()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = request(app).await.unwrap();
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(response.headers()["x-ratelimit-remaining-tokens"], "0");
+}
+
+/// Helper to create a test app whose generations go through the given queue
+fn create_queued_app(queue: Arc<RequestQueue>) -> (axum::Router, TempDir) {
+    let (state, temp_dir) = create_test_state();
+    (create_router(state.with_queue(queue)), temp_dir)
+}
+
+#[tokio::test]
+async fn test_queue_full_rejects_generations() {
+    let queue = Arc::new(RequestQueue::new(1, 0));
+    let (app, _temp_dir) = create_queued_app(queue.clone());
+    let _busy = queue.acquire().await.unwrap();
+
+    let request = Request::builder()
+        .uri("/v1/chat/completions")
+        .method("POST")
+        .header("content-type", "application/json")
+        .body(Body::from(
+            json!({
+                "model": "test-model",
+                "messages": [{"role": "user", "content": "Hello"}]
+            })
+            .to_string(),
+        ))
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(response.headers()["retry-after"], "1");
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json["error"]["code"], "server_overloaded");
+
+    // Ollama clients get the error in Ollama's format
+    let (status, json) = make_json_request(
+        app.clone(),
+        "POST",
+        "/api/generate",
+        Some(json!({"model": "test-model", "prompt": "Hello", "stream": false})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert!(json["error"].as_str().unwrap().contains("overloaded"));
+
+    // Other endpoints are not queued
+    let (status, _) = make_json_request(app, "GET", "/v1/models", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(queue.stats().rejected, 2);
+}
+
+#[tokio::test]
+async fn test_queue_slot_held_until_stream_ends() {
+    let queue = Arc::new(RequestQueue::new(1, 0));
+    let (app, _temp_dir) = create_queued_app(queue.clone());
+
+    let request = Request::builder()
+        .uri("/v1/chat/completions")
+        .method("POST")
+        .header("content-type", "application/json")
+        .body(Body::from(
+            json!({
+                "model": "test-model",
+                "messages": [{"role": "user", "content": "Hello"}],
+                "stream": true
+            })
+            .to_string(),
+        ))
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(queue.stats().in_flight, 1);
+
+    axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    assert_eq!(queue.stats().in_flight, 0);
+}
+
+#[tokio::test]
+async fn test_metrics() {
+    let (app, _temp_dir) = create_test_app();
+    let (status, _) = make_json_request(
+        app.clone(),
+        "POST",
+        "/v1/chat/completions",
+        Some(json!({
+            "model": "test-model",
+            "messages": [{"role": "user", "content": "Hello"}]
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    make_json_request(app.clone(), "GET", "/no/such/route", None).await;
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/metrics")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers()["content-type"]
+        .to_str()
+        .unwrap()
+        .starts_with("text/plain; version=0.0.4"));
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let text = String::from_utf8(body.to_vec()).unwrap();
+
+    // Routes are labeled by their pattern, unknown paths share one label
+    assert!(text.contains(
+        "puma_http_requests_total{route=\"/v1/chat/completions\",method=\"POST\",status=\"200\"} 1\n"
+    ));
+    assert!(text.contains(
+        "puma_http_requests_total{route=\"unmatched\",method=\"GET\",status=\"404\"} 1\n"
+    ));
+    assert!(text.contains("puma_generation_tokens_total{model=\"test-model\"} 20\n"));
+    assert!(text.contains("puma_time_to_first_token_seconds_count{model=\"test-model\"} 1\n"));
+    assert!(text.contains("puma_queue_depth 0\n"));
+    assert!(text.contains("process_resident_memory_bytes "));
+}
+
+#[tokio::test]
+async fn test_audit_log() {
+    let (state, _temp_dir) = create_test_state();
+    let storage = state.storage.clone();
+    let audit = Arc::new(AuditLog::new(storage.clone()));
+    let app = create_router(
+        state
+            .with_auth(Arc::new(ApiKeys::new(["secret-key-1".to_string()])))
+            .with_audit(audit.clone()),
+    );
+    let request = |key: &str, stream: bool| {
+        let body = json!({
+            "model": "test-model",
+            "messages": [{"role": "user", "content": "Hello"}],
+            "stream": stream
+        });
+        Request::builder()
+            .uri("/v1/chat/completions")
+            .method("POST")
+            .header("authorization", format!("Bearer {}", key))
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    };
+
+    let response = app
+        .clone()
+        .oneshot(request("secret-key-1", false))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = app
+        .clone()
+        .oneshot(request("wrong-key", false))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    // Streams are recorded once they end
+    let response = app
+        .clone()
+        .oneshot(request("secret-key-1", true))
+        .await
+        .unwrap();
+    axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    // Probes are not recorded
+    make_json_request(app, "GET", "/health", None).await;
+
+    audit.flush().await;
+    let entries = storage.recent_requests(10).unwrap();
+    assert_eq!(entries.len(), 3);
+    let (streamed, rejected, completed) = (&entries[0], &entries[1], &entries[2]);
+
+    assert_eq!(completed.path, "/v1/chat/completions");
+    assert_eq!(completed.model.as_deref(), Some("test-model"));
+    assert!(completed
+        .key_name
+        .as_deref()
+        .unwrap()
+        .starts_with("configured-"));
+    assert_eq!(completed.completion_tokens, 20);
+    assert_eq!(completed.status, 200);
+    assert_eq!(completed.finish_reason.as_deref(), Some("stop"));
+    assert_eq!(completed.request_body, None);
+
+    assert_eq!(rejected.status, 401);
+    assert_eq!(rejected.key_name, None);
+    assert_eq!(rejected.model, None);
+
+    assert!(streamed.completion_tokens > 0);
+    assert_eq!(streamed.finish_reason.as_deref(), Some("stop"));
+}
+
+#[tokio::test]
+async fn test_audit_log_bodies() {
+    let (state, _temp_dir) = create_test_state();
+    let storage = state.storage.clone();
+    let audit = Arc::new(AuditLog::new(storage.clone()).with_bodies(true));
+    let app = create_router(state.with_audit(audit.clone()));
+
+    let (status, json) = make_json_request(
+        app.clone(),
+        "POST",
+        "/v1/completions",
+        Some(json!({"model": "test-model", "prompt": "Hello"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let chunks = make_stream_request(
+        app,
+        "/v1/chat/completions",
+        json!({
+            "model": "test-model",
+            "messages": [{"role": "user", "content": "Hello"}],
+            "stream": true
+        }),
+    )
+    .await;
+
+    audit.flush().await;
+    let entries = storage.recent_requests(2).unwrap();
+    let request: Value = serde_json::from_str(entries[1].request_body.as_ref().unwrap()).unwrap();
+    assert_eq!(request["prompt"], "Hello");
+    let response: Value = serde_json::from_str(entries[1].response_body.as_ref().unwrap()).unwrap();
+    assert_eq!(response, json);
+
+    let streamed = entries[0].response_body.as_ref().unwrap();
+    assert!(streamed.ends_with("data: [DONE]\n\n"));
+    assert_eq!(streamed.matches("data: ").count(), chunks.len() + 1);
+}
+
+#[tokio::test]
+async fn test_audit_log_bodies_over_limit() {
+    let (state, _temp_dir) = create_test_state();
+    let storage = state.storage.clone();
+    let audit = Arc::new(AuditLog::new(storage.clone()).with_bodies(true));
+    let app = create_router(state.with_audit(audit.clone()));
+
+    // Captured bodies are still bound by the route's body limit
+    let prompt = "a".repeat(3 * 1024 * 1024);
+    let (status, _) = make_json_request(
+        app,
+        "POST",
+        "/tokenize",
+        Some(json!({"model": "test-model", "prompt": prompt})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+
+    audit.flush().await;
+    let entries = storage.recent_requests(1).unwrap();
+    assert_eq!(entries[0].status, 413);
+}
+
+/// Send a cross-origin request from `origin`, a preflight for POST when `preflight`
+async fn cors_request(
+    app: axum::Router,
+    origin: &str,
+    preflight: bool,
+) -> axum::response::Response {
+    let request = if preflight {
+        Request::builder()
+            .method("OPTIONS")
+            .uri("/v1/chat/completions")
+            .header("origin", origin)
+            .header("access-control-request-method", "POST")
+            .header(
+                "access-control-request-headers",
+                "authorization,content-type",
+            )
+    } else {
+        Request::builder()
+            .uri("/v1/models")
+            .header("origin", origin)
+    };
+    app.oneshot(request.body(Body::empty()).unwrap())
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_cors_refused_by_default() {
+    let (app, _temp_dir) = create_test_app();
+
+    // Browsers block pages from reading the response and skip the request after a preflight
+    let response = cors_request(app.clone(), "https://evil.example.com", false).await;
+    assert!(response
+        .headers()
+        .get("access-control-allow-origin")
+        .is_none());
+    let response = cors_request(app, "https://evil.example.com", true).await;
+    assert!(response
+        .headers()
+        .get("access-control-allow-origin")
+        .is_none());
+}
+
+#[tokio::test]
+async fn test_cors_configured_origins() {
+    let (state, _temp_dir) = create_test_state();
+    let options = CorsOptions {
+        origins: vec!["http://localhost:3000".to_string()],
+        credentials: true,
+        max_age: Some(std::time::Duration::from_secs(600)),
+        ..Default::default()
+    };
+    let keys = ApiKeys::new(vec!["secret".to_string()]);
+    let app = create_router(
+        state
+            .with_auth(Arc::new(keys))
+            .with_cors(options.layer().unwrap()),
+    );
+
+    // Preflights are answered without an API key
+    let response = cors_request(app.clone(), "http://localhost:3000", true).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let headers = response.headers();
+    assert_eq!(
+        headers["access-control-allow-origin"],
+        "http://localhost:3000"
+    );
+    assert_eq!(headers["access-control-allow-credentials"], "true");
+    assert_eq!(headers["access-control-max-age"], "600");
+    let methods = headers["access-control-allow-methods"].to_str().unwrap();
+    assert!(methods.contains("POST"));
+    let allowed = headers["access-control-allow-headers"].to_str().unwrap();
+    assert!(allowed.contains("authorization"));
+
+    let response = cors_request(app.clone(), "http://localhost:3000", false).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(
+        response.headers()["access-control-allow-origin"],
+        "http://localhost:3000"
+    );
+
+    // Other origins are not allowed
+    let response = cors_request(app, "http://localhost:8080", false).await;
+    assert!(response
+        .headers()
+        .get("access-control-allow-origin")
+        .is_none());
+}
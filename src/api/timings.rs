@@ -0,0 +1,188 @@
+use axum::{async_trait, extract::FromRequestParts, http::request::Parts};
+use std::convert::Infallible;
+use std::time::{Duration, Instant};
+
+use crate::api::types::Timings;
+use crate::api::usage::{GenerationUsage, UsageRecorder};
+
+/// Arrival time of a request, set by middleware that holds requests back
+/// (the request queue) so the wait counts towards the queue time
+#[derive(Debug, Clone, Copy)]
+pub struct RequestReceived(pub Instant);
+
+/// Tracks the phases of a single generation request
+#[derive(Debug, Clone)]
+pub struct RequestTimer {
+    received: Instant,
+    generation_started: Option<Instant>,
+    first_token: Option<Instant>,
+    usage: UsageRecorder,
+}
+
+impl RequestTimer {
+    /// Start timing, call as soon as the request is received
+    pub fn start() -> Self {
+        Self {
+            received: Instant::now(),
+            generation_started: None,
+            first_token: None,
+            usage: UsageRecorder::default(),
+        }
+    }
+
+    /// Mark the end of queueing and the start of generation
+    pub fn generation_started(&mut self) {
+        self.generation_started = Some(Instant::now());
+    }
+
+    /// Mark the arrival of the first streamed token (only the first call counts)
+    pub fn first_token(&mut self) {
+        if self.first_token.is_none() {
+            self.first_token = Some(Instant::now());
+        }
+    }
+
+    fn queue_time(&self) -> Duration {
+        self.generation_started
+            .map(|started| started.duration_since(self.received))
+            .unwrap_or_default()
+    }
+
+    /// Timings for a streamed request, measured from token arrival
+    pub fn stream_timings(&self, prompt_tokens: usize, completion_tokens: usize) -> Timings {
+        let now = Instant::now();
+        let started = self.generation_started.unwrap_or(self.received);
+        let first_token = self.first_token.unwrap_or(now);
+
+        // The first token closes prefill, the rest are decoded
+        Timings {
+            queue_time_ms: millis(self.queue_time()),
+            time_to_first_token_ms: millis(first_token.duration_since(started)),
+            prompt_tokens_per_second: rate(prompt_tokens, first_token.duration_since(started)),
+            decode_tokens_per_second: rate(
+                completion_tokens.saturating_sub(1),
+                now.duration_since(first_token),
+            ),
+            total_time_ms: millis(now.duration_since(self.received)),
+        }
+    }
+
+    /// Timings for a non-streamed request, using durations reported by the engine
+    pub fn generate_timings(
+        &self,
+        prompt_tokens: usize,
+        completion_tokens: usize,
+        prompt_duration: Duration,
+        decode_duration: Duration,
+    ) -> Timings {
+        Timings {
+            queue_time_ms: millis(self.queue_time()),
+            time_to_first_token_ms: millis(prompt_duration),
+            prompt_tokens_per_second: rate(prompt_tokens, prompt_duration),
+            decode_tokens_per_second: rate(completion_tokens, decode_duration),
+            total_time_ms: millis(self.received.elapsed()),
+        }
+    }
+}
+
+/// Handlers take the timer as an argument, started when the request arrived
+/// and reporting usage to the request's recorder
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for RequestTimer {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let mut timer = Self::start();
+        if let Some(RequestReceived(received)) = parts.extensions.get::<RequestReceived>() {
+            timer.received = *received;
+        }
+        timer.usage = UsageRecorder::from_request_parts(parts, state).await?;
+        Ok(timer)
+    }
+}
+
+impl RequestTimer {
+    /// Log the timings of a finished generation and record its usage
+    pub fn finish(
+        &self,
+        timings: &Timings,
+        route: &str,
+        model: &str,
+        prompt_tokens: usize,
+        completion_tokens: usize,
+        finish_reason: &str,
+    ) {
+        timings.log(route, model, prompt_tokens, completion_tokens);
+        self.usage.record(GenerationUsage {
+            model: model.to_string(),
+            prompt_tokens,
+            completion_tokens,
+            finish_reason: finish_reason.to_string(),
+            timings: timings.clone(),
+        });
+    }
+}
+
+impl Timings {
+    /// Emit the timings as a structured tracing event
+    pub fn log(&self, route: &str, model: &str, prompt_tokens: usize, completion_tokens: usize) {
+        tracing::info!(
+            target: "puma::timings",
+            route,
+            model,
+            prompt_tokens,
+            completion_tokens,
+            queue_time_ms = self.queue_time_ms,
+            time_to_first_token_ms = self.time_to_first_token_ms,
+            prompt_tokens_per_second = self.prompt_tokens_per_second,
+            decode_tokens_per_second = self.decode_tokens_per_second,
+            total_time_ms = self.total_time_ms,
+            "request timings"
+        );
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+fn rate(tokens: usize, duration: Duration) -> f64 {
+    let secs = duration.as_secs_f64();
+    if secs > 0.0 {
+        tokens as f64 / secs
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_timings() {
+        let mut timer = RequestTimer::start();
+        timer.generation_started();
+
+        let timings = timer.generate_timings(
+            100,
+            20,
+            Duration::from_millis(50),
+            Duration::from_millis(200),
+        );
+        assert_eq!(timings.time_to_first_token_ms, 50.0);
+        assert_eq!(timings.prompt_tokens_per_second, 2000.0);
+        assert_eq!(timings.decode_tokens_per_second, 100.0);
+        assert!(timings.total_time_ms >= timings.queue_time_ms);
+    }
+
+    #[test]
+    fn test_stream_timings_without_tokens() {
+        let mut timer = RequestTimer::start();
+        timer.generation_started();
+
+        let timings = timer.stream_timings(0, 0);
+        assert_eq!(timings.prompt_tokens_per_second, 0.0);
+        assert_eq!(timings.decode_tokens_per_second, 0.0);
+    }
+}
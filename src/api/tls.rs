@@ -0,0 +1,144 @@
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::server::WebPkiClientVerifier;
+use rustls::{RootCertStore, ServerConfig};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use tokio::signal::unix::{signal, SignalKind};
+use tokio_rustls::TlsAcceptor;
+use tracing::{info, warn};
+
+/// Certificate files of the server
+#[derive(Debug, Clone)]
+pub struct TlsOptions {
+    /// PEM certificate chain, leaf first
+    pub cert: PathBuf,
+    /// PEM private key (PKCS#8, PKCS#1 or SEC1)
+    pub key: PathBuf,
+    /// PEM CA certificates that client certificates must chain to. Clients
+    /// without a valid certificate are refused when set (mTLS).
+    pub client_ca: Option<PathBuf>,
+}
+
+/// TLS configuration of the server, reloadable while it runs
+pub struct TlsConfig {
+    options: TlsOptions,
+    current: RwLock<Arc<ServerConfig>>,
+}
+
+impl TlsConfig {
+    /// Load the certificate files, failing on missing or invalid files
+    pub fn load(options: TlsOptions) -> io::Result<Self> {
+        let config = server_config(&options)?;
+        Ok(Self {
+            options,
+            current: RwLock::new(Arc::new(config)),
+        })
+    }
+
+    /// Load the certificate files again. On failure the previous
+    /// configuration stays in use.
+    pub fn reload(&self) -> io::Result<()> {
+        let config = server_config(&self.options)?;
+        *self.current.write().unwrap() = Arc::new(config);
+        Ok(())
+    }
+
+    /// Acceptor for a new connection, with the current certificates
+    pub fn acceptor(&self) -> TlsAcceptor {
+        TlsAcceptor::from(self.current.read().unwrap().clone())
+    }
+
+    pub fn requires_client_cert(&self) -> bool {
+        self.options.client_ca.is_some()
+    }
+}
+
+/// Reload the certificates on every SIGHUP, so renewed certificates are
+/// picked up without a restart. Connections already open keep theirs.
+pub async fn reload_on_sighup(config: Arc<TlsConfig>) {
+    let mut hangup = match signal(SignalKind::hangup()) {
+        Ok(hangup) => hangup,
+        Err(e) => {
+            warn!(
+                "Failed to install the SIGHUP handler, TLS reload disabled: {}",
+                e
+            );
+            return;
+        }
+    };
+    while hangup.recv().await.is_some() {
+        match config.reload() {
+            Ok(()) => info!("Reloaded TLS certificates"),
+            Err(e) => warn!(
+                "Failed to reload TLS certificates, keeping the previous ones: {}",
+                e
+            ),
+        }
+    }
+}
+
+fn server_config(options: &TlsOptions) -> io::Result<ServerConfig> {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let certs = load_certs(&options.cert)?;
+    let key = PrivateKeyDer::from_pem_file(&options.key).map_err(|e| {
+        invalid_data(format!(
+            "Failed to read TLS key '{}': {}",
+            options.key.display(),
+            e
+        ))
+    })?;
+
+    let builder = ServerConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .map_err(io::Error::other)?;
+    let builder = match &options.client_ca {
+        Some(path) => {
+            let mut roots = RootCertStore::empty();
+            for cert in load_certs(path)? {
+                roots.add(cert).map_err(|e| {
+                    invalid_data(format!(
+                        "Invalid client CA certificate '{}': {}",
+                        path.display(),
+                        e
+                    ))
+                })?;
+            }
+            let verifier = WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider)
+                .build()
+                .map_err(io::Error::other)?;
+            builder.with_client_cert_verifier(verifier)
+        }
+        None => builder.with_no_client_auth(),
+    };
+
+    let mut config = builder
+        .with_single_cert(certs, key)
+        .map_err(|e| invalid_data(format!("Invalid TLS certificate or key: {}", e)))?;
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    Ok(config)
+}
+
+fn load_certs(path: &Path) -> io::Result<Vec<CertificateDer<'static>>> {
+    let certs = CertificateDer::pem_file_iter(path)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| {
+            invalid_data(format!(
+                "Failed to read certificates '{}': {}",
+                path.display(),
+                e
+            ))
+        })?;
+    if certs.is_empty() {
+        return Err(invalid_data(format!(
+            "No certificate found in '{}'",
+            path.display()
+        )));
+    }
+    Ok(certs)
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
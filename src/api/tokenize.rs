@@ -0,0 +1,73 @@
+use axum::{extract::State, Json};
+use std::time::{Duration, Instant};
+
+use crate::api::chat::format_chat_messages;
+use crate::api::error::ApiError;
+use crate::api::extract::ApiJson;
+use crate::api::models::resolve_model;
+use crate::api::routes::AppState;
+use crate::api::timings::RequestTimer;
+use crate::api::types::{DetokenizeRequest, DetokenizeResponse, TokenizeRequest, TokenizeResponse};
+use crate::backend::InferenceEngine;
+
+/// Handler for tokenizing a prompt or chat messages, the tokens count
+/// towards the usage of the request
+pub async fn tokenize<E: InferenceEngine + 'static>(
+    State(state): State<AppState<E>>,
+    mut timer: RequestTimer,
+    ApiJson(req): ApiJson<TokenizeRequest>,
+) -> Result<Json<TokenizeResponse>, ApiError> {
+    // Exactly one of prompt and messages must be set
+    let text = match (&req.prompt, &req.messages) {
+        (Some(prompt), None) => prompt.clone(),
+        (None, Some(messages)) => format_chat_messages(messages),
+        _ => {
+            return Err(ApiError::invalid_request(
+                "exactly one of prompt or messages must be provided",
+            ))
+        }
+    };
+
+    // Adapters share the tokenizer of their base model
+    let resolved = resolve_model(&state, &req.model).await?;
+
+    timer.generation_started();
+    let started = Instant::now();
+    let tokens = state
+        .engine
+        .tokenize(&resolved.base.name, &text)
+        .await
+        .map_err(|e| ApiError::server_error(format!("Failed to tokenize: {}", e)))?;
+    let timings = timer.generate_timings(tokens.len(), 0, started.elapsed(), Duration::ZERO);
+    timer.finish(&timings, "/tokenize", &req.model, tokens.len(), 0, "stop");
+
+    Ok(Json(TokenizeResponse {
+        count: tokens.len(),
+        max_model_len: resolved.base.metadata.context_window,
+        token_strs: tokens.iter().map(|t| t.text.clone()).collect(),
+        tokens: tokens.into_iter().map(|t| t.id).collect(),
+    }))
+}
+
+/// Handler for converting token ids back into text
+pub async fn detokenize<E: InferenceEngine + 'static>(
+    State(state): State<AppState<E>>,
+    ApiJson(req): ApiJson<DetokenizeRequest>,
+) -> Result<Json<DetokenizeResponse>, ApiError> {
+    let resolved = resolve_model(&state, &req.model).await?;
+
+    match state
+        .engine
+        .detokenize(&resolved.base.name, &req.tokens)
+        .await
+    {
+        Ok(prompt) => Ok(Json(DetokenizeResponse { prompt })),
+        Err(e) if e.kind() == std::io::ErrorKind::InvalidInput => {
+            Err(ApiError::invalid_request(e.to_string()).with_param("tokens"))
+        }
+        Err(e) => Err(ApiError::server_error(format!(
+            "Failed to detokenize: {}",
+            e
+        ))),
+    }
+}
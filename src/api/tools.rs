@@ -0,0 +1,141 @@
+use serde::Deserialize;
+
+use crate::api::reasoning::split_reasoning;
+
+/// Tool calls are emitted by the chat template in Hermes format:
+/// `<tool_call>{"name": ..., "arguments": {...}}</tool_call>`
+const TOOL_CALL_START: &str = "<tool_call>";
+const TOOL_CALL_END: &str = "</tool_call>";
+
+/// A function call parsed from model output
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedToolCall {
+    pub name: String,
+    /// Arguments as a JSON encoded string, as in the OpenAI APIs
+    pub arguments: String,
+}
+
+#[derive(Deserialize)]
+struct RawToolCall {
+    name: String,
+    #[serde(default)]
+    arguments: serde_json::Value,
+}
+
+/// Render a tool call the way the model emits it, to replay it in a prompt
+pub fn format_tool_call(name: &str, arguments: &str) -> String {
+    let arguments: serde_json::Value =
+        serde_json::from_str(arguments).unwrap_or_else(|_| arguments.into());
+    format!(
+        "{}\n{}\n{}",
+        TOOL_CALL_START,
+        serde_json::json!({"name": name, "arguments": arguments}),
+        TOOL_CALL_END
+    )
+}
+
+/// Split model output into text content and tool calls. Blocks that are not
+/// valid tool calls are left in the content.
+pub fn parse_tool_calls(text: &str) -> (String, Vec<ParsedToolCall>) {
+    let mut content = String::new();
+    let mut calls = Vec::new();
+    let mut rest = text;
+
+    while let Some(start) = rest.find(TOOL_CALL_START) {
+        let body_start = start + TOOL_CALL_START.len();
+        let Some(len) = rest[body_start..].find(TOOL_CALL_END) else {
+            break;
+        };
+        let body = &rest[body_start..body_start + len];
+        let end = body_start + len + TOOL_CALL_END.len();
+
+        match serde_json::from_str::<RawToolCall>(body.trim()) {
+            Ok(call) => {
+                content.push_str(&rest[..start]);
+                calls.push(ParsedToolCall {
+                    name: call.name,
+                    arguments: match call.arguments {
+                        serde_json::Value::Null => "{}".to_string(),
+                        serde_json::Value::String(arguments) => arguments,
+                        arguments => arguments.to_string(),
+                    },
+                });
+            }
+            Err(_) => content.push_str(&rest[..end]),
+        }
+        rest = &rest[end..];
+    }
+    content.push_str(rest);
+
+    (content.trim().to_string(), calls)
+}
+
+/// Generated text split into reasoning, message text and tool calls
+pub struct ParsedOutput {
+    pub reasoning: Option<String>,
+    pub text: String,
+    pub tool_calls: Vec<ParsedToolCall>,
+}
+
+pub fn parse_output(text: &str, tools_enabled: bool) -> ParsedOutput {
+    let (reasoning, content) = split_reasoning(text);
+    let (text, tool_calls) = if tools_enabled {
+        parse_tool_calls(&content)
+    } else {
+        (content, Vec::new())
+    };
+    ParsedOutput {
+        reasoning,
+        text,
+        tool_calls,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tool_calls() {
+        let text = "Let me check.\n<tool_call>\n{\"name\": \"get_weather\", \"arguments\": {\"city\": \"Paris\"}}\n</tool_call>";
+        let (content, calls) = parse_tool_calls(text);
+
+        assert_eq!(content, "Let me check.");
+        assert_eq!(
+            calls,
+            vec![ParsedToolCall {
+                name: "get_weather".to_string(),
+                arguments: "{\"city\":\"Paris\"}".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_parse_multiple_tool_calls() {
+        let text = "<tool_call>{\"name\": \"a\"}</tool_call><tool_call>{\"name\": \"b\", \"arguments\": \"{}\"}</tool_call>";
+        let (content, calls) = parse_tool_calls(text);
+
+        assert_eq!(content, "");
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0].arguments, "{}");
+        assert_eq!(calls[1].name, "b");
+    }
+
+    #[test]
+    fn test_parse_invalid_tool_call() {
+        let text = "<tool_call>not json</tool_call> and <tool_call>unterminated";
+        let (content, calls) = parse_tool_calls(text);
+
+        assert_eq!(content, text);
+        assert!(calls.is_empty());
+    }
+
+    #[test]
+    fn test_format_tool_call_round_trip() {
+        let formatted = format_tool_call("get_weather", "{\"city\":\"Paris\"}");
+        let (_, calls) = parse_tool_calls(&formatted);
+
+        assert_eq!(calls[0].name, "get_weather");
+        assert_eq!(calls[0].arguments, "{\"city\":\"Paris\"}");
+    }
+}
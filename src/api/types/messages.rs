@@ -0,0 +1,158 @@
+use serde::{Deserialize, Serialize};
+
+/// Messages API request (Anthropic compatible)
+#[derive(Debug, Clone, Deserialize)]
+pub struct MessagesRequest {
+    pub model: String,
+    pub messages: Vec<AnthropicMessage>,
+    /// System prompt, either text or an array of text blocks
+    #[serde(default)]
+    pub system: Option<AnthropicContent>,
+    pub max_tokens: usize,
+    #[serde(default)]
+    pub temperature: Option<f32>,
+    #[serde(default)]
+    pub stream: bool,
+    #[serde(default)]
+    pub tools: Vec<AnthropicTool>,
+    /// Extended thinking, mapped to a thinking budget
+    #[serde(default)]
+    pub thinking: Option<ThinkingConfig>,
+}
+
+/// Message of the conversation
+#[derive(Debug, Clone, Deserialize)]
+pub struct AnthropicMessage {
+    pub role: String, // "user", "assistant"
+    pub content: AnthropicContent,
+}
+
+/// Content, either plain text or an array of content blocks
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum AnthropicContent {
+    Text(String),
+    Blocks(Vec<ContentBlock>),
+}
+
+impl AnthropicContent {
+    pub fn into_blocks(self) -> Vec<ContentBlock> {
+        match self {
+            AnthropicContent::Text(text) => vec![ContentBlock::Text { text }],
+            AnthropicContent::Blocks(blocks) => blocks,
+        }
+    }
+
+    /// Concatenated text of the text blocks
+    pub fn text(&self) -> String {
+        match self {
+            AnthropicContent::Text(text) => text.clone(),
+            AnthropicContent::Blocks(blocks) => blocks
+                .iter()
+                .filter_map(|block| match block {
+                    ContentBlock::Text { text } => Some(text.as_str()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+        }
+    }
+}
+
+/// Content block of a request or response message
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentBlock {
+    Text {
+        text: String,
+    },
+    Image {
+        source: ImageSource,
+    },
+    ToolUse {
+        id: String,
+        name: String,
+        input: serde_json::Value,
+    },
+    ToolResult {
+        tool_use_id: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        content: Option<AnthropicContent>,
+        #[serde(default)]
+        is_error: bool,
+    },
+    /// Reasoning of thinking models, not replayed in the prompt
+    Thinking {
+        thinking: String,
+        #[serde(default)]
+        signature: String,
+    },
+}
+
+/// Image data, base64 encoded or a local file path
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ImageSource {
+    Base64 { media_type: String, data: String },
+    Url { url: String },
+}
+
+impl ImageSource {
+    /// Image reference in the form accepted by `images::load_image`
+    pub fn to_url(&self) -> String {
+        match self {
+            ImageSource::Base64 { media_type, data } => {
+                format!("data:{};base64,{}", media_type, data)
+            }
+            ImageSource::Url { url } => url.clone(),
+        }
+    }
+}
+
+/// Tool the model may call
+#[derive(Debug, Clone, Deserialize)]
+pub struct AnthropicTool {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub input_schema: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ThinkingConfig {
+    Enabled { budget_tokens: usize },
+    Disabled,
+}
+
+impl ThinkingConfig {
+    pub fn budget(self) -> Option<usize> {
+        match self {
+            ThinkingConfig::Enabled { budget_tokens } => Some(budget_tokens),
+            ThinkingConfig::Disabled => None,
+        }
+    }
+}
+
+/// Messages API response
+#[derive(Debug, Clone, Serialize)]
+pub struct MessagesResponse {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub object: String, // "message"
+    pub role: String,
+    pub content: Vec<ContentBlock>,
+    pub model: String,
+    pub stop_reason: Option<String>, // "end_turn", "max_tokens", "tool_use"
+    pub stop_sequence: Option<String>,
+    pub usage: MessagesUsage,
+}
+
+/// Token usage of a message
+#[derive(Debug, Clone, Serialize)]
+pub struct MessagesUsage {
+    pub input_tokens: usize,
+    pub output_tokens: usize,
+    pub cache_read_input_tokens: usize,
+}
@@ -1,5 +1,11 @@
+pub mod messages;
+pub mod ollama;
 pub mod request;
 pub mod response;
+pub mod responses;
 
+pub use messages::*;
+pub use ollama::*;
 pub use request::*;
 pub use response::*;
+pub use responses::*;
@@ -0,0 +1,218 @@
+use serde::{Deserialize, Serialize};
+
+/// Generate request (Ollama compatible)
+#[derive(Debug, Clone, Deserialize)]
+pub struct OllamaGenerateRequest {
+    pub model: String,
+    /// An empty prompt only loads the model
+    #[serde(default)]
+    pub prompt: String,
+    /// Text after the completion, builds a fill-in-the-middle prompt
+    #[serde(default)]
+    pub suffix: Option<String>,
+    #[serde(default)]
+    pub system: Option<String>,
+    /// Base64 encoded images
+    #[serde(default)]
+    pub images: Vec<String>,
+    /// Send the prompt as is, without the chat template
+    #[serde(default)]
+    pub raw: bool,
+    #[serde(default = "default_stream")]
+    pub stream: bool,
+    #[serde(default)]
+    pub think: Option<bool>,
+    #[serde(default)]
+    pub options: OllamaOptions,
+}
+
+/// Chat request (Ollama compatible)
+#[derive(Debug, Clone, Deserialize)]
+pub struct OllamaChatRequest {
+    pub model: String,
+    pub messages: Vec<OllamaMessage>,
+    #[serde(default)]
+    pub tools: Vec<OllamaTool>,
+    #[serde(default = "default_stream")]
+    pub stream: bool,
+    #[serde(default)]
+    pub think: Option<bool>,
+    #[serde(default)]
+    pub options: OllamaOptions,
+}
+
+/// Ollama streams unless told otherwise
+fn default_stream() -> bool {
+    true
+}
+
+/// Sampling options, unknown options are ignored
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+pub struct OllamaOptions {
+    #[serde(default)]
+    pub temperature: Option<f32>,
+    /// Maximum tokens to generate, negative values generate up to the context window
+    #[serde(default)]
+    pub num_predict: Option<i64>,
+}
+
+/// Chat message
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct OllamaMessage {
+    pub role: String, // "system", "user", "assistant", "tool"
+    #[serde(default)]
+    pub content: String,
+    /// Reasoning of thinking models
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thinking: Option<String>,
+    /// Base64 encoded images
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub images: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<OllamaToolCall>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct OllamaToolCall {
+    pub function: OllamaFunctionCall,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct OllamaFunctionCall {
+    pub name: String,
+    /// Arguments as a JSON object, not an encoded string as in OpenAI
+    #[serde(default)]
+    pub arguments: serde_json::Value,
+}
+
+/// Tool the model may call, in OpenAI's function format
+#[derive(Debug, Clone, Deserialize)]
+pub struct OllamaTool {
+    pub function: OllamaFunction,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct OllamaFunction {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub parameters: serde_json::Value,
+}
+
+/// Request naming a single model, for show and delete
+#[derive(Debug, Clone, Deserialize)]
+pub struct OllamaModelRequest {
+    #[serde(alias = "name")]
+    pub model: String,
+}
+
+/// Pull request
+#[derive(Debug, Clone, Deserialize)]
+pub struct OllamaPullRequest {
+    #[serde(alias = "name")]
+    pub model: String,
+    #[serde(default = "default_stream")]
+    pub stream: bool,
+}
+
+/// Generate response, or one line of a streamed generation
+#[derive(Debug, Clone, Serialize)]
+pub struct OllamaGenerateResponse {
+    pub model: String,
+    pub created_at: String,
+    pub response: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thinking: Option<String>,
+    pub done: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub done_reason: Option<String>, // "stop", "length", "load"
+    #[serde(flatten)]
+    pub stats: Option<OllamaStats>,
+}
+
+/// Chat response, or one line of a streamed chat
+#[derive(Debug, Clone, Serialize)]
+pub struct OllamaChatResponse {
+    pub model: String,
+    pub created_at: String,
+    pub message: OllamaMessage,
+    pub done: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub done_reason: Option<String>,
+    #[serde(flatten)]
+    pub stats: Option<OllamaStats>,
+}
+
+/// Statistics of a finished generation, durations in nanoseconds
+#[derive(Debug, Clone, Serialize)]
+pub struct OllamaStats {
+    pub total_duration: u64,
+    pub load_duration: u64,
+    pub prompt_eval_count: usize,
+    pub prompt_eval_duration: u64,
+    pub eval_count: usize,
+    pub eval_duration: u64,
+}
+
+/// Local models, returned by `/api/tags`
+#[derive(Debug, Serialize)]
+pub struct OllamaModelList {
+    pub models: Vec<OllamaModel>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OllamaModel {
+    pub name: String,
+    pub model: String,
+    pub modified_at: String,
+    pub size: u64,
+    pub digest: String,
+    pub details: OllamaModelDetails,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OllamaModelDetails {
+    /// Base model of an adapter, empty for base models
+    pub parent_model: String,
+    pub format: String,
+    pub family: String,
+    pub families: Vec<String>,
+    pub parameter_size: String,
+    pub quantization_level: String,
+}
+
+/// Loaded models, returned by `/api/ps`
+#[derive(Debug, Serialize)]
+pub struct OllamaRunningModelList {
+    pub models: Vec<OllamaRunningModel>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OllamaRunningModel {
+    pub name: String,
+    pub model: String,
+    pub size: u64,
+    pub digest: String,
+    pub details: OllamaModelDetails,
+    pub size_vram: u64,
+}
+
+/// Model information, returned by `/api/show`
+#[derive(Debug, Serialize)]
+pub struct OllamaShowResponse {
+    pub license: String,
+    pub modelfile: String,
+    pub parameters: String,
+    pub template: String,
+    pub details: OllamaModelDetails,
+    pub model_info: serde_json::Map<String, serde_json::Value>,
+    pub capabilities: Vec<String>,
+    pub modified_at: String,
+}
+
+/// Progress of a pull
+#[derive(Debug, Serialize)]
+pub struct OllamaStatus {
+    pub status: String,
+}
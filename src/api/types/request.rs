@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 
+use crate::backend::vision::IMAGE_PLACEHOLDER;
+
 /// Chat completion request (OpenAI compatible)
 #[derive(Debug, Clone, Deserialize)]
 #[allow(dead_code)]
@@ -16,18 +18,111 @@ pub struct ChatCompletionRequest {
     pub n: Option<usize>,
     #[serde(default)]
     pub stream: bool,
+    /// Options for streaming responses, only used when `stream` is set
+    #[serde(default)]
+    pub stream_options: Option<StreamOptions>,
     pub stop: Option<StringOrArray>,
     #[serde(default)]
     pub presence_penalty: Option<f32>,
     #[serde(default)]
     pub frequency_penalty: Option<f32>,
+    /// What to do when the prompt plus max_tokens exceeds the context window
+    #[serde(default)]
+    pub truncation_strategy: TruncationStrategy,
+    /// Reasoning effort for thinking models, mapped to a thinking budget
+    #[serde(default)]
+    pub reasoning_effort: Option<ReasoningEffort>,
+    /// Maximum number of reasoning tokens for thinking models
+    #[serde(default)]
+    pub thinking_budget: Option<usize>,
+}
+
+/// Streaming options
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+pub struct StreamOptions {
+    /// Send a final chunk with token usage and empty choices before `[DONE]`
+    #[serde(default)]
+    pub include_usage: bool,
+}
+
+/// Reasoning effort for thinking models
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReasoningEffort {
+    Low,
+    Medium,
+    High,
 }
 
 /// Chat message
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ChatMessage {
     pub role: String, // "system", "user", "assistant"
-    pub content: String,
+    pub content: MessageContent,
+    /// Reasoning of thinking models, split out of `<think>` blocks
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reasoning_content: Option<String>,
+}
+
+/// Message content, either plain text or an array of content parts
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum MessageContent {
+    Text(String),
+    Parts(Vec<ContentPart>),
+}
+
+/// Content part of a multimodal message
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentPart {
+    Text { text: String },
+    ImageUrl { image_url: ImageUrl },
+}
+
+/// Image reference, a `data:` URL or a local file path
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ImageUrl {
+    pub url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>, // "auto", "low", "high"
+}
+
+impl MessageContent {
+    /// Render the content as prompt text, with a placeholder for each image
+    pub fn to_prompt_text(&self) -> String {
+        match self {
+            MessageContent::Text(text) => text.clone(),
+            MessageContent::Parts(parts) => parts
+                .iter()
+                .map(|part| match part {
+                    ContentPart::Text { text } => text.as_str(),
+                    ContentPart::ImageUrl { .. } => IMAGE_PLACEHOLDER,
+                })
+                .collect::<Vec<_>>()
+                .join(""),
+        }
+    }
+
+    /// Image URLs referenced by the content, in order
+    pub fn image_urls(&self) -> Vec<&str> {
+        match self {
+            MessageContent::Text(_) => Vec::new(),
+            MessageContent::Parts(parts) => parts
+                .iter()
+                .filter_map(|part| match part {
+                    ContentPart::ImageUrl { image_url } => Some(image_url.url.as_str()),
+                    ContentPart::Text { .. } => None,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<String> for MessageContent {
+    fn from(text: String) -> Self {
+        MessageContent::Text(text)
+    }
 }
 
 /// Legacy text completion request
@@ -52,6 +147,89 @@ pub struct CompletionRequest {
     pub presence_penalty: Option<f32>,
     #[serde(default)]
     pub frequency_penalty: Option<f32>,
+    /// Return the prompt in addition to the completion
+    #[serde(default)]
+    pub echo: bool,
+    /// Text after the completion, builds a fill-in-the-middle prompt
+    #[serde(default)]
+    pub suffix: Option<String>,
+    /// Generate this many candidates and return the n with the highest logprob
+    #[serde(default)]
+    pub best_of: Option<usize>,
+    /// What to do when the prompt plus max_tokens exceeds the context window.
+    /// `drop_oldest` only applies to chat messages.
+    #[serde(default)]
+    pub truncation_strategy: TruncationStrategy,
+}
+
+/// Strategy applied when a request does not fit in the model's context window
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TruncationStrategy {
+    /// Reject the request with `context_length_exceeded`
+    #[default]
+    Disabled,
+    /// Drop the oldest non-system messages until the request fits
+    DropOldest,
+    /// Lower max_tokens to the space left after the prompt
+    ClampMaxTokens,
+}
+
+/// Tokenize request, takes either a raw prompt or chat messages
+#[derive(Debug, Clone, Deserialize)]
+pub struct TokenizeRequest {
+    pub model: String,
+    #[serde(default)]
+    pub prompt: Option<String>,
+    /// Chat messages, rendered with the chat template before tokenizing
+    #[serde(default)]
+    pub messages: Option<Vec<ChatMessage>>,
+}
+
+/// Detokenize request
+#[derive(Debug, Clone, Deserialize)]
+pub struct DetokenizeRequest {
+    pub model: String,
+    pub tokens: Vec<u32>,
+}
+
+/// Rerank request (Cohere/Jina compatible)
+#[derive(Debug, Clone, Deserialize)]
+pub struct RerankRequest {
+    pub model: String,
+    pub query: String,
+    pub documents: Vec<RerankDocument>,
+    /// Only return the N most relevant documents
+    #[serde(default)]
+    pub top_n: Option<usize>,
+    /// Include the document text in each result
+    #[serde(default)]
+    pub return_documents: bool,
+}
+
+/// Request to pull a model from Hugging Face
+#[derive(Debug, Clone, Deserialize)]
+pub struct PullModelRequest {
+    pub model: String,
+    /// Stream download progress, otherwise answer once the model is registered
+    #[serde(default = "default_stream")]
+    pub stream: bool,
+}
+
+/// Document to rerank, either plain text or an object with a text field
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum RerankDocument {
+    Text(String),
+    Object { text: String },
+}
+
+impl RerankDocument {
+    pub fn text(&self) -> &str {
+        match self {
+            RerankDocument::Text(text) | RerankDocument::Object { text } => text,
+        }
+    }
 }
 
 /// Prompt can be string or array of strings
@@ -76,6 +254,10 @@ fn default_max_tokens() -> Option<usize> {
     Some(100)
 }
 
+fn default_stream() -> bool {
+    true
+}
+
 fn default_temperature() -> Option<f32> {
     Some(0.7)
 }
@@ -29,6 +29,9 @@ pub struct ChatCompletionChunk {
     pub created: i64,
     pub model: String,
     pub choices: Vec<ChatChoiceDelta>,
+    /// Only set on the final chunk when `stream_options.include_usage` is requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage: Option<Usage>,
 }
 
 /// Delta choice for streaming
@@ -47,6 +50,8 @@ pub struct ChatMessageDelta {
     pub role: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub content: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reasoning_content: Option<String>,
 }
 
 /// Legacy completion response
@@ -75,6 +80,47 @@ pub struct Usage {
     pub prompt_tokens: usize,
     pub completion_tokens: usize,
     pub total_tokens: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt_tokens_details: Option<PromptTokensDetails>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub completion_tokens_details: Option<CompletionTokensDetails>,
+    /// Performance timings (PUMA extension)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timings: Option<Timings>,
+}
+
+/// Per-request performance timings
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Timings {
+    /// Time spent waiting before generation started
+    pub queue_time_ms: f64,
+    /// Time from the start of generation to the first token
+    pub time_to_first_token_ms: f64,
+    /// Prompt processing (prefill) speed
+    pub prompt_tokens_per_second: f64,
+    /// Decode speed after the first token
+    pub decode_tokens_per_second: f64,
+    pub total_time_ms: f64,
+}
+
+/// Breakdown of prompt tokens
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PromptTokensDetails {
+    /// Prompt tokens restored from the prompt cache
+    pub cached_tokens: usize,
+}
+
+impl PromptTokensDetails {
+    /// Details are only reported when part of the prompt was cached
+    pub fn cached(cached_tokens: usize) -> Option<Self> {
+        (cached_tokens > 0).then_some(Self { cached_tokens })
+    }
+}
+
+/// Breakdown of completion tokens
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CompletionTokensDetails {
+    pub reasoning_tokens: usize,
 }
 
 /// Model list response
@@ -91,6 +137,62 @@ pub struct Model {
     pub object: String, // "model"
     pub created: i64,
     pub owned_by: String,
+    /// Base model for LoRA adapters
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
+}
+
+/// Response to deleting a model
+#[derive(Debug, Serialize)]
+pub struct ModelDeleted {
+    pub id: String,
+    pub object: String, // "model"
+    pub deleted: bool,
+}
+
+/// Tokenize response
+#[derive(Debug, Serialize)]
+pub struct TokenizeResponse {
+    pub count: usize,
+    /// Context window of the model, if known
+    pub max_model_len: Option<u32>,
+    pub tokens: Vec<u32>,
+    pub token_strs: Vec<String>,
+}
+
+/// Detokenize response
+#[derive(Debug, Serialize)]
+pub struct DetokenizeResponse {
+    pub prompt: String,
+}
+
+/// Rerank response, results sorted by descending relevance
+#[derive(Debug, Serialize)]
+pub struct RerankResponse {
+    pub id: String,
+    pub model: String,
+    pub results: Vec<RerankResult>,
+    pub usage: RerankUsage,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RerankResult {
+    /// Position of the document in the request
+    pub index: usize,
+    pub relevance_score: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub document: Option<RerankResultDocument>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RerankResultDocument {
+    pub text: String,
+}
+
+/// Rerank token usage
+#[derive(Debug, Serialize)]
+pub struct RerankUsage {
+    pub total_tokens: usize,
 }
 
 /// Error response
@@ -103,17 +205,6 @@ pub struct ErrorResponse {
 pub struct ErrorDetail {
     pub message: String,
     pub r#type: String,
+    pub param: Option<String>,
     pub code: Option<String>,
 }
-
-impl ErrorResponse {
-    pub fn new(message: String, error_type: String) -> Self {
-        Self {
-            error: ErrorDetail {
-                message,
-                r#type: error_type,
-                code: None,
-            },
-        }
-    }
-}
@@ -0,0 +1,253 @@
+use serde::{Deserialize, Serialize};
+
+use super::request::{ContentPart, ImageUrl, MessageContent, ReasoningEffort};
+
+/// Responses API request (OpenAI compatible)
+#[derive(Debug, Clone, Deserialize)]
+pub struct ResponsesRequest {
+    pub model: String,
+    pub input: ResponseInput,
+    /// System instructions, not carried over to responses chained after this one
+    #[serde(default)]
+    pub instructions: Option<String>,
+    /// Continue the conversation of a stored response
+    #[serde(default)]
+    pub previous_response_id: Option<String>,
+    #[serde(default)]
+    pub tools: Vec<ResponseTool>,
+    #[serde(default)]
+    pub max_output_tokens: Option<usize>,
+    #[serde(default)]
+    pub temperature: Option<f32>,
+    #[serde(default)]
+    pub stream: bool,
+    /// Store the response so it can be retrieved and chained
+    #[serde(default = "default_store")]
+    pub store: bool,
+    #[serde(default)]
+    pub reasoning: Option<ReasoningConfig>,
+    /// `auto` drops the oldest items when the conversation exceeds the context window
+    #[serde(default)]
+    pub truncation: Truncation,
+}
+
+fn default_store() -> bool {
+    true
+}
+
+/// Input is either plain text (a user message) or a list of items
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum ResponseInput {
+    Text(String),
+    Items(Vec<InputItem>),
+}
+
+impl ResponseInput {
+    pub fn into_items(self) -> Vec<InputItem> {
+        match self {
+            ResponseInput::Text(text) => vec![InputItem::Message(InputMessage {
+                role: "user".to_string(),
+                content: InputContent::Text(text),
+            })],
+            ResponseInput::Items(items) => items,
+        }
+    }
+}
+
+/// Input item, messages may omit their `type`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum InputItem {
+    Typed(TypedInputItem),
+    Message(InputMessage),
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TypedInputItem {
+    Message {
+        role: String,
+        content: InputContent,
+    },
+    FunctionCall {
+        call_id: String,
+        name: String,
+        arguments: String,
+    },
+    FunctionCallOutput {
+        call_id: String,
+        output: String,
+    },
+    /// Reasoning of a previous response, not replayed in the prompt
+    Reasoning {},
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct InputMessage {
+    pub role: String, // "system", "developer", "user", "assistant"
+    pub content: InputContent,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum InputContent {
+    Text(String),
+    Parts(Vec<InputContentPart>),
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum InputContentPart {
+    InputText {
+        text: String,
+    },
+    /// Text of assistant messages from previous responses
+    OutputText {
+        text: String,
+    },
+    InputImage {
+        image_url: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        detail: Option<String>,
+    },
+}
+
+impl From<InputContent> for MessageContent {
+    fn from(content: InputContent) -> Self {
+        match content {
+            InputContent::Text(text) => MessageContent::Text(text),
+            InputContent::Parts(parts) => MessageContent::Parts(
+                parts
+                    .into_iter()
+                    .map(|part| match part {
+                        InputContentPart::InputText { text }
+                        | InputContentPart::OutputText { text } => ContentPart::Text { text },
+                        InputContentPart::InputImage { image_url, detail } => {
+                            ContentPart::ImageUrl {
+                                image_url: ImageUrl {
+                                    url: image_url,
+                                    detail,
+                                },
+                            }
+                        }
+                    })
+                    .collect(),
+            ),
+        }
+    }
+}
+
+/// Tool the model may call, only function tools are supported
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ResponseTool {
+    Function {
+        name: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        description: Option<String>,
+        #[serde(default)]
+        parameters: serde_json::Value,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        strict: Option<bool>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct ReasoningConfig {
+    #[serde(default)]
+    pub effort: Option<ReasoningEffort>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Truncation {
+    Auto,
+    #[default]
+    Disabled,
+}
+
+/// Response object
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResponseObject {
+    pub id: String,
+    pub object: String, // "response"
+    pub created_at: i64,
+    pub status: String, // "in_progress", "completed", "failed"
+    pub model: String,
+    pub instructions: Option<String>,
+    pub previous_response_id: Option<String>,
+    pub output: Vec<OutputItem>,
+    pub tools: Vec<ResponseTool>,
+    pub max_output_tokens: Option<usize>,
+    pub temperature: Option<f32>,
+    pub store: bool,
+    pub usage: Option<ResponseUsage>,
+}
+
+/// Output item of a response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum OutputItem {
+    Message {
+        id: String,
+        status: String,
+        role: String,
+        content: Vec<OutputContent>,
+    },
+    FunctionCall {
+        id: String,
+        call_id: String,
+        name: String,
+        arguments: String,
+        status: String,
+    },
+    Reasoning {
+        id: String,
+        summary: Vec<serde_json::Value>,
+        content: Vec<ReasoningContent>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum OutputContent {
+    OutputText {
+        text: String,
+        annotations: Vec<serde_json::Value>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ReasoningContent {
+    ReasoningText { text: String },
+}
+
+/// Token usage of a response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResponseUsage {
+    pub input_tokens: usize,
+    pub input_tokens_details: InputTokensDetails,
+    pub output_tokens: usize,
+    pub output_tokens_details: OutputTokensDetails,
+    pub total_tokens: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InputTokensDetails {
+    pub cached_tokens: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutputTokensDetails {
+    pub reasoning_tokens: usize,
+}
+
+/// Response to deleting a stored response
+#[derive(Debug, Serialize)]
+pub struct ResponseDeleted {
+    pub id: String,
+    pub object: String, // "response"
+    pub deleted: bool,
+}
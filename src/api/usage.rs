@@ -0,0 +1,115 @@
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Request},
+    http::request::Parts,
+};
+use std::convert::Infallible;
+use std::sync::Arc;
+
+use crate::api::types::Timings;
+
+/// Usage of a single finished generation
+#[derive(Debug, Clone)]
+pub struct GenerationUsage {
+    pub model: String,
+    pub prompt_tokens: usize,
+    pub completion_tokens: usize,
+    /// Finish reason in the terms of the endpoint, e.g. `stop` or `end_turn`
+    pub finish_reason: String,
+    pub timings: Timings,
+}
+
+/// Notified of every generation finished while handling a request
+pub trait UsageObserver: Send + Sync {
+    fn observe(&self, usage: &GenerationUsage);
+}
+
+/// Reports the usage of a request's generations to the observers installed
+/// by middleware. Streamed generations report when the stream ends, after
+/// the middleware has returned, so usage is pushed rather than collected.
+#[derive(Clone, Default)]
+pub struct UsageRecorder {
+    observers: Vec<Arc<dyn UsageObserver>>,
+}
+
+impl UsageRecorder {
+    pub fn record(&self, usage: GenerationUsage) {
+        for observer in &self.observers {
+            observer.observe(&usage);
+        }
+    }
+
+    /// Add an observer to the recorder handed to the request's handler
+    pub fn observe(request: &mut Request, observer: Arc<dyn UsageObserver>) {
+        let mut recorder = request
+            .extensions()
+            .get::<UsageRecorder>()
+            .cloned()
+            .unwrap_or_default();
+        recorder.observers.push(observer);
+        request.extensions_mut().insert(recorder);
+    }
+}
+
+impl std::fmt::Debug for UsageRecorder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UsageRecorder")
+            .field("observers", &self.observers.len())
+            .finish()
+    }
+}
+
+/// Handlers take the recorder as an argument, requests without observers
+/// get one that records nothing
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for UsageRecorder {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(parts
+            .extensions
+            .get::<UsageRecorder>()
+            .cloned()
+            .unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct Tokens(Mutex<usize>);
+
+    impl UsageObserver for Tokens {
+        fn observe(&self, usage: &GenerationUsage) {
+            *self.0.lock().unwrap() += usage.prompt_tokens + usage.completion_tokens;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_recorder_notifies_every_observer() {
+        let first = Arc::new(Tokens::default());
+        let second = Arc::new(Tokens::default());
+        let mut request = Request::new(Body::empty());
+        UsageRecorder::observe(&mut request, first.clone());
+        UsageRecorder::observe(&mut request, second.clone());
+
+        let (mut parts, _) = request.into_parts();
+        let recorder = UsageRecorder::from_request_parts(&mut parts, &())
+            .await
+            .unwrap();
+        recorder.record(GenerationUsage {
+            model: "test-model".to_string(),
+            prompt_tokens: 5,
+            completion_tokens: 20,
+            finish_reason: "stop".to_string(),
+            timings: Timings::default(),
+        });
+
+        assert_eq!(*first.0.lock().unwrap(), 25);
+        assert_eq!(*second.0.lock().unwrap(), 25);
+    }
+}
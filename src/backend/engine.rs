@@ -1,28 +1,147 @@
 use std::io;
 use std::pin::Pin;
+use std::time::Duration;
 use tokio_stream::Stream;
 
+use super::vision::ImageInput;
+
 /// Inference engine trait
 pub trait InferenceEngine: Send + Sync {
     /// Generate text completion
     fn generate(
         &self,
-        model: &str,
-        prompt: &str,
-        max_tokens: usize,
-        temperature: f32,
+        request: &GenerateRequest,
     ) -> impl std::future::Future<Output = Result<GenerateResponse, io::Error>> + Send;
 
     /// Generate text with streaming
     fn generate_stream(
+        &self,
+        request: &GenerateRequest,
+    ) -> impl std::future::Future<Output = Result<GenerateStream, io::Error>> + Send;
+
+    /// Load a model's weights from its local path, reporting progress in [0, 1]
+    fn load_model(
+        &self,
+        model: &str,
+        path: &str,
+        progress: &(dyn Fn(f32) + Send + Sync),
+    ) -> impl std::future::Future<Output = Result<(), io::Error>> + Send;
+
+    /// Release a model's weights, and the adapters loaded on top of it
+    fn unload_model(
+        &self,
+        model: &str,
+    ) -> impl std::future::Future<Output = Result<(), io::Error>> + Send;
+
+    /// Load a LoRA adapter on top of an already served base model.
+    /// Loading the same adapter twice is a no-op.
+    fn load_adapter(
+        &self,
+        base_model: &str,
+        adapter: &str,
+        path: &str,
+    ) -> impl std::future::Future<Output = Result<(), io::Error>> + Send;
+
+    /// Tokenize text with the model's tokenizer
+    fn tokenize(
+        &self,
+        model: &str,
+        text: &str,
+    ) -> impl std::future::Future<Output = Result<Vec<Token>, io::Error>> + Send;
+
+    /// Convert token ids back into text
+    fn detokenize(
         &self,
         model: &str,
-        prompt: &str,
-        max_tokens: usize,
-        temperature: f32,
-    ) -> impl std::future::Future<
-        Output = Result<Pin<Box<dyn Stream<Item = String> + Send>>, io::Error>,
-    > + Send;
+        ids: &[u32],
+    ) -> impl std::future::Future<Output = Result<String, io::Error>> + Send;
+
+    /// Score each document's relevance to the query with a cross-encoder model.
+    /// Scores are in [0, 1] and returned in document order.
+    fn rerank(
+        &self,
+        model: &str,
+        query: &str,
+        documents: &[String],
+    ) -> impl std::future::Future<Output = Result<Vec<f32>, io::Error>> + Send;
+}
+
+/// A single token produced by the tokenizer
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token {
+    pub id: u32,
+    pub text: String,
+}
+
+/// A function the model may call
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolDefinition {
+    pub name: String,
+    pub description: Option<String>,
+    /// JSON schema of the arguments
+    pub parameters: serde_json::Value,
+}
+
+/// Generation request
+#[derive(Debug, Clone)]
+pub struct GenerateRequest {
+    /// Base model name
+    pub model: String,
+    /// Optional LoRA adapter applied on top of the base model
+    pub adapter: Option<String>,
+    pub prompt: String,
+    /// Images referenced by placeholders in the prompt, in order
+    pub images: Vec<ImageInput>,
+    pub max_tokens: usize,
+    /// Maximum number of reasoning tokens for thinking models
+    pub thinking_budget: Option<usize>,
+    /// Model revision, prompt KV state is only cached when it is known
+    pub revision: Option<String>,
+    /// Functions the model may call, rendered by the chat template
+    pub tools: Vec<ToolDefinition>,
+    #[allow(dead_code)]
+    pub temperature: f32,
+}
+
+impl GenerateRequest {
+    pub fn new(model: &str, prompt: &str, max_tokens: usize, temperature: f32) -> Self {
+        Self {
+            model: model.to_string(),
+            adapter: None,
+            prompt: prompt.to_string(),
+            images: Vec::new(),
+            max_tokens,
+            thinking_budget: None,
+            revision: None,
+            tools: Vec::new(),
+            temperature,
+        }
+    }
+
+    pub fn with_adapter(mut self, adapter: Option<String>) -> Self {
+        self.adapter = adapter;
+        self
+    }
+
+    pub fn with_images(mut self, images: Vec<ImageInput>) -> Self {
+        self.images = images;
+        self
+    }
+
+    pub fn with_thinking_budget(mut self, thinking_budget: Option<usize>) -> Self {
+        self.thinking_budget = thinking_budget;
+        self
+    }
+
+    pub fn with_revision(mut self, revision: Option<String>) -> Self {
+        self.revision = revision;
+        self
+    }
+
+    pub fn with_tools(mut self, tools: Vec<ToolDefinition>) -> Self {
+        self.tools = tools;
+        self
+    }
 }
 
 /// Generation response
@@ -30,5 +149,61 @@ pub trait InferenceEngine: Send + Sync {
 pub struct GenerateResponse {
     pub text: String,
     pub prompt_tokens: usize,
+    /// Prompt tokens whose KV state was restored from the prompt cache
+    pub cached_tokens: usize,
     pub completion_tokens: usize,
+    /// Time spent processing the prompt (prefill)
+    pub prompt_duration: Duration,
+    /// Time spent decoding completion tokens
+    pub decode_duration: Duration,
+    /// Sum of the log-probabilities of the completion tokens
+    pub logprob: f32,
+}
+
+/// Streaming generation, the prompt is processed before the stream is returned
+pub struct GenerateStream {
+    pub tokens: Pin<Box<dyn Stream<Item = String> + Send>>,
+    /// Prompt tokens, including those the images take
+    pub prompt_tokens: usize,
+    /// Prompt tokens whose KV state was restored from the prompt cache
+    pub cached_tokens: usize,
+}
+
+impl GenerateResponse {
+    /// Mean log-probability per completion token. Unlike the sum it doesn't
+    /// favor shorter completions when ranking candidates.
+    pub fn mean_logprob(&self) -> f32 {
+        if self.completion_tokens == 0 {
+            return 0.0;
+        }
+        self.logprob / self.completion_tokens as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(completion_tokens: usize, logprob: f32) -> GenerateResponse {
+        GenerateResponse {
+            text: String::new(),
+            prompt_tokens: 1,
+            cached_tokens: 0,
+            completion_tokens,
+            prompt_duration: Duration::ZERO,
+            decode_duration: Duration::ZERO,
+            logprob,
+        }
+    }
+
+    #[test]
+    fn test_mean_logprob() {
+        // A longer completion with likelier tokens ranks above a short unlikely one
+        let short = response(2, -2.0);
+        let long = response(10, -5.0);
+        assert!(long.logprob < short.logprob);
+        assert!(long.mean_logprob() > short.mean_logprob());
+
+        assert_eq!(response(0, 0.0).mean_logprob(), 0.0);
+    }
 }
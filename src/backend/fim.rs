@@ -0,0 +1,182 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::utils::file::list_files_recursive;
+
+/// Special tokens used to build fill-in-the-middle prompts
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FimTokens {
+    pub prefix: &'static str,
+    pub suffix: &'static str,
+    pub middle: &'static str,
+}
+
+/// Known FIM token sets, in the order they are looked up
+const KNOWN_FIM_TOKENS: &[FimTokens] = &[
+    // Qwen2.5-Coder, CodeGemma
+    FimTokens {
+        prefix: "<|fim_prefix|>",
+        suffix: "<|fim_suffix|>",
+        middle: "<|fim_middle|>",
+    },
+    // StarCoder, SantaCoder
+    FimTokens {
+        prefix: "<fim_prefix>",
+        suffix: "<fim_suffix>",
+        middle: "<fim_middle>",
+    },
+    // DeepSeek-Coder
+    FimTokens {
+        prefix: "<｜fim▁begin｜>",
+        suffix: "<｜fim▁hole｜>",
+        middle: "<｜fim▁end｜>",
+    },
+    // CodeLlama
+    FimTokens {
+        prefix: "<PRE>",
+        suffix: "<SUF>",
+        middle: "<MID>",
+    },
+];
+
+/// Tokenizer files that list a model's special tokens
+const TOKENIZER_FILES: &[&str] = &[
+    "tokenizer_config.json",
+    "tokenizer.json",
+    "special_tokens_map.json",
+    "added_tokens.json",
+];
+
+impl FimTokens {
+    /// Build a prefix-suffix-middle prompt, the model generates the middle
+    pub fn format(&self, prefix: &str, suffix: &str) -> String {
+        if self.prefix == "<PRE>" {
+            // CodeLlama expects spaces around its sentinel tokens
+            format!("<PRE> {} <SUF>{} <MID>", prefix, suffix)
+        } else {
+            format!(
+                "{}{}{}{}{}",
+                self.prefix, prefix, self.suffix, suffix, self.middle
+            )
+        }
+    }
+}
+
+/// Find the FIM tokens in the tokenizer files of a model, if it has any
+pub fn detect_fim_tokens(model_path: &Path) -> Option<FimTokens> {
+    let contents: Vec<String> = list_files_recursive(model_path)
+        .ok()?
+        .into_iter()
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| TOKENIZER_FILES.contains(&name))
+        })
+        .filter_map(|path| std::fs::read_to_string(path).ok())
+        .collect();
+
+    // Tokens appear as JSON strings in the tokenizer files
+    let has_token = |token: &str| {
+        let quoted = format!("\"{}\"", token);
+        contents.iter().any(|content| content.contains(&quoted))
+    };
+
+    KNOWN_FIM_TOKENS
+        .iter()
+        .find(|tokens| {
+            has_token(tokens.prefix) && has_token(tokens.suffix) && has_token(tokens.middle)
+        })
+        .copied()
+}
+
+/// FIM tokens detected per model revision, so suffix requests don't read the
+/// tokenizer files of a model again
+#[derive(Default)]
+pub struct FimCache {
+    tokens: Mutex<HashMap<(PathBuf, String), Option<FimTokens>>>,
+}
+
+impl FimCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// FIM tokens of the model at `model_path`, detected on first use of a
+    /// revision. A pulled revision has new tokenizer files and is detected again.
+    pub fn get(&self, model_path: &Path, revision: &str) -> Option<FimTokens> {
+        let key = (model_path.to_path_buf(), revision.to_string());
+        if let Some(tokens) = self.tokens.lock().unwrap().get(&key) {
+            return *tokens;
+        }
+
+        // Detect without holding the lock, a race only detects twice
+        let tokens = detect_fim_tokens(model_path);
+        self.tokens.lock().unwrap().insert(key, tokens);
+        tokens
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_detect_fim_tokens() {
+        let temp_dir = TempDir::new().unwrap();
+        let snapshot = temp_dir.path().join("snapshots").join("abc123");
+        std::fs::create_dir_all(&snapshot).unwrap();
+        std::fs::write(
+            snapshot.join("tokenizer_config.json"),
+            r#"{"additional_special_tokens": ["<fim_prefix>", "<fim_middle>", "<fim_suffix>"]}"#,
+        )
+        .unwrap();
+
+        let tokens = detect_fim_tokens(temp_dir.path()).unwrap();
+        assert_eq!(tokens.prefix, "<fim_prefix>");
+        assert_eq!(
+            tokens.format("def add(a, b):\n", "\n    return c"),
+            "<fim_prefix>def add(a, b):\n<fim_suffix>\n    return c<fim_middle>"
+        );
+    }
+
+    #[test]
+    fn test_detect_fim_tokens_missing() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::write(
+            temp_dir.path().join("tokenizer.json"),
+            r#"{"added_tokens": [{"content": "<|endoftext|>"}]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(detect_fim_tokens(temp_dir.path()), None);
+        assert_eq!(detect_fim_tokens(&temp_dir.path().join("missing")), None);
+    }
+
+    #[test]
+    fn test_fim_cache() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = temp_dir.path().join("tokenizer_config.json");
+        std::fs::write(
+            &config,
+            r#"{"additional_special_tokens": ["<fim_prefix>", "<fim_middle>", "<fim_suffix>"]}"#,
+        )
+        .unwrap();
+
+        let cache = FimCache::new();
+        let tokens = cache.get(temp_dir.path(), "abc123").unwrap();
+        assert_eq!(tokens.prefix, "<fim_prefix>");
+
+        // The tokenizer files are only read once per revision
+        std::fs::remove_file(&config).unwrap();
+        assert_eq!(cache.get(temp_dir.path(), "abc123"), Some(tokens));
+        assert_eq!(cache.get(temp_dir.path(), "def456"), None);
+    }
+
+    #[test]
+    fn test_codellama_format() {
+        let tokens = KNOWN_FIM_TOKENS[3];
+        assert_eq!(tokens.format("a", "b"), "<PRE> a <SUF>b <MID>");
+    }
+}
@@ -1,61 +1,205 @@
 use futures::stream::{self, StreamExt};
+use std::collections::HashMap;
 use std::io;
-use std::pin::Pin;
-use tokio_stream::Stream;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tracing::{debug, warn};
 
-use super::engine::{GenerateResponse, InferenceEngine};
+use super::engine::{GenerateRequest, GenerateResponse, GenerateStream, InferenceEngine, Token};
+use super::prompt_cache::{PromptCache, PROMPT_CACHE_BLOCK_TOKENS};
+use super::vision::ImageInput;
+use crate::utils::file::list_files_recursive;
+
+/// Number of prompt tokens each image is encoded into by the mock vision encoder
+const MOCK_TOKENS_PER_IMAGE: usize = 64;
+
+/// Reasoning emitted in a `<think>` block when a thinking budget is set
+const MOCK_REASONING: &str = "The user sent a prompt. Let me think about how to answer it.";
 
 /// Mock engine for testing (replace with MLX later)
 #[derive(Clone)]
-pub struct MockEngine;
+pub struct MockEngine {
+    // Loaded adapters, keyed by adapter name and pointing at their base model.
+    // Several adapters can share the same base model.
+    adapters: Arc<RwLock<HashMap<String, String>>>,
+    // Persistent prompt cache, disabled unless configured
+    prompt_cache: Option<Arc<PromptCache>>,
+}
 
 impl MockEngine {
     pub fn new() -> Self {
-        Self
+        Self {
+            adapters: Arc::new(RwLock::new(HashMap::new())),
+            prompt_cache: None,
+        }
+    }
+
+    /// Reuse prompt KV state across requests and restarts
+    pub fn with_prompt_cache(mut self, prompt_cache: Arc<PromptCache>) -> Self {
+        self.prompt_cache = Some(prompt_cache);
+        self
+    }
+
+    /// Mock prefill, returns the number of prompt tokens restored from the
+    /// prompt cache. The mock KV state is the prompt token ids themselves.
+    fn prefill(&self, request: &GenerateRequest) -> usize {
+        let (Some(cache), Some(revision)) = (&self.prompt_cache, &request.revision) else {
+            return 0;
+        };
+
+        // KV state depends on the exact weights, including the adapter
+        let namespace = match &request.adapter {
+            Some(adapter) => format!("{}@{}+{}", request.model, revision, adapter),
+            None => format!("{}@{}", request.model, revision),
+        };
+        let tokens: Vec<u32> = request.prompt.chars().map(|c| c as u32).collect();
+
+        let cached_tokens = cache
+            .lookup(&namespace, &tokens)
+            .map_or(0, |cached| cached.tokens);
+
+        // Store the prompt unless its full blocks were already cached
+        if cached_tokens < tokens.len() / PROMPT_CACHE_BLOCK_TOKENS * PROMPT_CACHE_BLOCK_TOKENS {
+            let state: Vec<u8> = tokens.iter().flat_map(|id| id.to_le_bytes()).collect();
+            if let Err(e) = cache.store(&namespace, &tokens, &state) {
+                warn!("Failed to store prompt cache entry: {}", e);
+            }
+        }
+        cached_tokens
+    }
+
+    /// Describe the model (and adapter, if any) a request runs on
+    fn target(&self, request: &GenerateRequest) -> Result<String, io::Error> {
+        let Some(adapter) = &request.adapter else {
+            return Ok(request.model.clone());
+        };
+
+        let adapters = self.adapters.read().unwrap();
+        match adapters.get(adapter) {
+            Some(base) if base == &request.model => Ok(format!("{}+{}", base, adapter)),
+            Some(base) => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "Adapter '{}' is loaded on '{}', not '{}'",
+                    adapter, base, request.model
+                ),
+            )),
+            None => Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("Adapter '{}' is not loaded", adapter),
+            )),
+        }
+    }
+
+    /// Mock reasoning, cut to the thinking budget (one token per char)
+    fn reasoning(&self, request: &GenerateRequest) -> Option<String> {
+        request
+            .thinking_budget
+            .map(|budget| MOCK_REASONING.chars().take(budget).collect())
+    }
+
+    /// Mock tool calling: call the first tool until a tool result is in the prompt
+    fn tool_call(&self, request: &GenerateRequest) -> Option<String> {
+        let tool = request.tools.first()?;
+        if request
+            .prompt
+            .lines()
+            .any(|line| line.starts_with("Tool: "))
+        {
+            return None;
+        }
+        Some(format!(
+            "<tool_call>\n{{\"name\": \"{}\", \"arguments\": {{}}}}\n</tool_call>",
+            tool.name
+        ))
+    }
+
+    /// Mock vision encoder, returns the number of prompt tokens the images take
+    fn encode_images(&self, images: &[ImageInput]) -> Result<usize, io::Error> {
+        for image in images {
+            if !image.mime_type.starts_with("image/") || image.data.is_empty() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Cannot encode image of type '{}'", image.mime_type),
+                ));
+            }
+        }
+        Ok(images.len() * MOCK_TOKENS_PER_IMAGE)
     }
 }
 
 impl InferenceEngine for MockEngine {
-    async fn generate(
-        &self,
-        model: &str,
-        prompt: &str,
-        max_tokens: usize,
-        _temperature: f32,
-    ) -> Result<GenerateResponse, io::Error> {
+    async fn generate(&self, request: &GenerateRequest) -> Result<GenerateResponse, io::Error> {
+        let target = self.target(request)?;
+        let image_tokens = self.encode_images(&request.images)?;
+        let cached_tokens = self.prefill(request);
+
+        let images = if request.images.is_empty() {
+            String::new()
+        } else {
+            format!(" with {} image(s)", request.images.len())
+        };
+
         // Mock response for testing
         let response_text = format!(
-            "This is a mock response from model '{}' for prompt: '{}' (max_tokens: {})",
-            model,
-            prompt.chars().take(50).collect::<String>(),
-            max_tokens
+            "This is a mock response from model '{}' for prompt: '{}'{} (max_tokens: {})",
+            target,
+            request.prompt.chars().take(50).collect::<String>(),
+            images,
+            request.max_tokens
         );
 
+        let response_text = self.tool_call(request).unwrap_or(response_text);
+        let response_text = match self.reasoning(request) {
+            Some(reasoning) => format!("<think>\n{}\n</think>\n\n{}", reasoning, response_text),
+            None => response_text,
+        };
+
+        let prompt_tokens = request.prompt.chars().count() + image_tokens;
+        let completion_tokens = 20;
+
+        // Mock timings: 0.1ms per uncached prompt token and 10ms per completion token
         Ok(GenerateResponse {
             text: response_text,
-            prompt_tokens: prompt.split_whitespace().count(),
-            completion_tokens: 20,
+            prompt_tokens,
+            cached_tokens,
+            completion_tokens,
+            prompt_duration: Duration::from_micros(100 * (prompt_tokens - cached_tokens) as u64),
+            decode_duration: Duration::from_millis(10 * completion_tokens as u64),
+            logprob: -0.5 * completion_tokens as f32,
         })
     }
 
     async fn generate_stream(
         &self,
-        model: &str,
-        _prompt: &str,
-        max_tokens: usize,
-        _temperature: f32,
-    ) -> Result<Pin<Box<dyn Stream<Item = String> + Send>>, io::Error> {
-        // Mock streaming response
-        let tokens = vec![
-            "This ".to_string(),
-            "is ".to_string(),
-            "a ".to_string(),
-            "mock ".to_string(),
-            "streaming ".to_string(),
-            "response ".to_string(),
-            format!("from model '{}' ", model),
-            format!("(max_tokens: {}).", max_tokens),
-        ];
+        request: &GenerateRequest,
+    ) -> Result<GenerateStream, io::Error> {
+        let target = self.target(request)?;
+        let image_tokens = self.encode_images(&request.images)?;
+        let cached_tokens = self.prefill(request);
+
+        // Mock streaming response, with a think block when a thinking budget is set
+        let mut tokens = Vec::new();
+        if let Some(reasoning) = self.reasoning(request) {
+            tokens.push("<think>\n".to_string());
+            tokens.extend(reasoning.split_inclusive(' ').map(str::to_string));
+            tokens.push("\n</think>\n\n".to_string());
+        }
+        if let Some(tool_call) = self.tool_call(request) {
+            tokens.push(tool_call);
+        } else {
+            tokens.extend([
+                "This ".to_string(),
+                "is ".to_string(),
+                "a ".to_string(),
+                "mock ".to_string(),
+                "streaming ".to_string(),
+                "response ".to_string(),
+                format!("from model '{}' ", target),
+                format!("(max_tokens: {}).", request.max_tokens),
+            ]);
+        }
 
         // Simulate delay between tokens
         let stream = stream::iter(tokens).then(|token| async move {
@@ -63,7 +207,112 @@ impl InferenceEngine for MockEngine {
             token
         });
 
-        Ok(Box::pin(stream))
+        Ok(GenerateStream {
+            tokens: Box::pin(stream),
+            prompt_tokens: request.prompt.chars().count() + image_tokens,
+            cached_tokens,
+        })
+    }
+
+    async fn load_model(
+        &self,
+        model: &str,
+        path: &str,
+        progress: &(dyn Fn(f32) + Send + Sync),
+    ) -> Result<(), io::Error> {
+        if !Path::new(path).exists() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("Model path '{}' does not exist", path),
+            ));
+        }
+
+        // Pretend to load every weight file under the model path
+        let files = list_files_recursive(Path::new(path))?;
+        for (i, file) in files.iter().enumerate() {
+            debug!("Mock loading {} for {}", file.display(), model);
+            progress((i + 1) as f32 / files.len() as f32);
+        }
+        progress(1.0);
+        Ok(())
+    }
+
+    async fn unload_model(&self, model: &str) -> Result<(), io::Error> {
+        debug!("Mock unloading {}", model);
+        self.adapters
+            .write()
+            .unwrap()
+            .retain(|_, base| base != model);
+        Ok(())
+    }
+
+    async fn load_adapter(
+        &self,
+        base_model: &str,
+        adapter: &str,
+        _path: &str,
+    ) -> Result<(), io::Error> {
+        self.adapters
+            .write()
+            .unwrap()
+            .insert(adapter.to_string(), base_model.to_string());
+        Ok(())
+    }
+
+    // The mock tokenizer is character level: every char is one token and its
+    // id is the Unicode scalar value, so detokenize is an exact inverse.
+    async fn tokenize(&self, _model: &str, text: &str) -> Result<Vec<Token>, io::Error> {
+        Ok(text
+            .chars()
+            .map(|c| Token {
+                id: c as u32,
+                text: c.to_string(),
+            })
+            .collect())
+    }
+
+    async fn detokenize(&self, _model: &str, ids: &[u32]) -> Result<String, io::Error> {
+        ids.iter()
+            .map(|&id| {
+                char::from_u32(id).ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("Invalid token id: {}", id),
+                    )
+                })
+            })
+            .collect()
+    }
+
+    async fn rerank(
+        &self,
+        _model: &str,
+        query: &str,
+        documents: &[String],
+    ) -> Result<Vec<f32>, io::Error> {
+        // Score by the share of query words that appear in the document
+        let words = |text: &str| -> Vec<String> {
+            text.split(|c: char| !c.is_alphanumeric())
+                .filter(|w| !w.is_empty())
+                .map(|w| w.to_lowercase())
+                .collect()
+        };
+
+        let query_words = words(query);
+        Ok(documents
+            .iter()
+            .map(|document| {
+                if query_words.is_empty() {
+                    return 0.0;
+                }
+                let document_words = words(document);
+                let matched = query_words
+                    .iter()
+                    .filter(|w| document_words.contains(w))
+                    .count();
+                matched as f32 / query_words.len() as f32
+            })
+            .collect())
     }
 }
 
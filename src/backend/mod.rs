@@ -1,4 +1,7 @@
 pub mod engine;
+pub mod fim;
 pub mod mock;
+pub mod prompt_cache;
+pub mod vision;
 
 pub use engine::*;
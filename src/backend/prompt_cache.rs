@@ -0,0 +1,209 @@
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::SystemTime;
+use tracing::{debug, warn};
+
+/// Prompts are cached in blocks of this many tokens, so a long shared prefix
+/// (e.g. a system prompt) is reused even when the rest of the prompt differs
+pub const PROMPT_CACHE_BLOCK_TOKENS: usize = 256;
+
+/// File extension of cached KV state entries
+const ENTRY_EXTENSION: &str = "kv";
+
+/// Persistent on-disk cache of prompt KV state, survives server restarts.
+///
+/// Entries are keyed by a chained hash of the namespace (model, revision and
+/// adapter) and every block of prompt tokens up to the cached prefix. The
+/// least recently used entries are evicted once the cache exceeds its size cap.
+#[derive(Debug)]
+pub struct PromptCache {
+    dir: PathBuf,
+    max_bytes: u64,
+}
+
+/// KV state restored for the longest cached prefix of a prompt
+#[derive(Debug, PartialEq)]
+pub struct CachedPrefix {
+    /// Number of prompt tokens covered by the state
+    pub tokens: usize,
+    pub state: Vec<u8>,
+}
+
+impl PromptCache {
+    pub fn new(dir: PathBuf, max_bytes: u64) -> io::Result<Self> {
+        fs::create_dir_all(&dir)?;
+        Ok(Self { dir, max_bytes })
+    }
+
+    /// Keys of every full block prefix of the prompt, shortest first
+    fn block_keys(namespace: &str, tokens: &[u32]) -> Vec<(usize, String)> {
+        let mut hasher = Sha256::new();
+        hasher.update(namespace.as_bytes());
+
+        tokens
+            .chunks_exact(PROMPT_CACHE_BLOCK_TOKENS)
+            .enumerate()
+            .map(|(i, block)| {
+                for id in block {
+                    hasher.update(id.to_le_bytes());
+                }
+                let key = format!("{:x}", hasher.clone().finalize());
+                ((i + 1) * PROMPT_CACHE_BLOCK_TOKENS, key)
+            })
+            .collect()
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.{}", key, ENTRY_EXTENSION))
+    }
+
+    /// Find the KV state of the longest cached prefix of the prompt
+    pub fn lookup(&self, namespace: &str, tokens: &[u32]) -> Option<CachedPrefix> {
+        Self::block_keys(namespace, tokens)
+            .into_iter()
+            .rev()
+            .find_map(|(len, key)| {
+                let path = self.entry_path(&key);
+                let state = fs::read(&path).ok()?;
+
+                // Mark as recently used for eviction
+                if let Err(e) = fs::File::options()
+                    .append(true)
+                    .open(&path)
+                    .and_then(|f| f.set_modified(SystemTime::now()))
+                {
+                    debug!(
+                        "Failed to touch prompt cache entry {}: {}",
+                        path.display(),
+                        e
+                    );
+                }
+                Some(CachedPrefix { tokens: len, state })
+            })
+    }
+
+    /// Store the KV state of the longest full-block prefix of the prompt.
+    /// Prompts shorter than one block are not cached.
+    pub fn store(&self, namespace: &str, tokens: &[u32], state: &[u8]) -> io::Result<()> {
+        let Some((_, key)) = Self::block_keys(namespace, tokens).pop() else {
+            return Ok(());
+        };
+
+        // Write to a temporary file first so readers never see partial entries
+        let path = self.entry_path(&key);
+        let tmp_path = self.dir.join(format!("{}.tmp", key));
+        fs::write(&tmp_path, state)?;
+        fs::rename(&tmp_path, &path)?;
+
+        self.evict()
+    }
+
+    /// Remove the least recently used entries until the cache fits its size cap
+    fn evict(&self) -> io::Result<()> {
+        let mut entries = Vec::new();
+        let mut total = 0;
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some(ENTRY_EXTENSION) {
+                continue;
+            }
+            let metadata = fs::metadata(&path)?;
+            total += metadata.len();
+            entries.push((metadata.modified()?, metadata.len(), path));
+        }
+
+        entries.sort_by_key(|(modified, _, _)| *modified);
+        for (_, size, path) in entries {
+            if total <= self.max_bytes {
+                break;
+            }
+            match fs::remove_file(&path) {
+                Ok(()) => total -= size,
+                Err(e) => warn!(
+                    "Failed to evict prompt cache entry {}: {}",
+                    path.display(),
+                    e
+                ),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn prompt(len: usize, seed: u32) -> Vec<u32> {
+        (0..len as u32).map(|i| i + seed).collect()
+    }
+
+    #[test]
+    fn test_store_and_lookup() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache = PromptCache::new(temp_dir.path().to_path_buf(), u64::MAX).unwrap();
+
+        let tokens = prompt(2 * PROMPT_CACHE_BLOCK_TOKENS + 10, 0);
+        assert_eq!(cache.lookup("model@rev", &tokens), None);
+
+        cache.store("model@rev", &tokens, b"state").unwrap();
+        let cached = cache.lookup("model@rev", &tokens).unwrap();
+        assert_eq!(cached.tokens, 2 * PROMPT_CACHE_BLOCK_TOKENS);
+        assert_eq!(cached.state, b"state");
+
+        // A new cache over the same directory still finds the entry
+        let reopened = PromptCache::new(temp_dir.path().to_path_buf(), u64::MAX).unwrap();
+        assert!(reopened.lookup("model@rev", &tokens).is_some());
+    }
+
+    #[test]
+    fn test_lookup_shared_prefix() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache = PromptCache::new(temp_dir.path().to_path_buf(), u64::MAX).unwrap();
+
+        // Cache a one block system prompt
+        let system = prompt(PROMPT_CACHE_BLOCK_TOKENS, 0);
+        cache.store("model@rev", &system, b"system").unwrap();
+
+        // A longer prompt starting with it reuses the block
+        let mut tokens = system.clone();
+        tokens.extend(prompt(PROMPT_CACHE_BLOCK_TOKENS, 10_000));
+        let cached = cache.lookup("model@rev", &tokens).unwrap();
+        assert_eq!(cached.tokens, PROMPT_CACHE_BLOCK_TOKENS);
+
+        // Different prefix, revision or short prompts miss
+        assert_eq!(
+            cache.lookup("model@rev", &prompt(PROMPT_CACHE_BLOCK_TOKENS, 1)),
+            None
+        );
+        assert_eq!(cache.lookup("model@other-rev", &tokens), None);
+        assert_eq!(cache.lookup("model@rev", &system[..10]), None);
+    }
+
+    #[test]
+    fn test_short_prompts_are_not_stored() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache = PromptCache::new(temp_dir.path().to_path_buf(), u64::MAX).unwrap();
+
+        cache.store("model@rev", &prompt(10, 0), b"state").unwrap();
+        assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache = PromptCache::new(temp_dir.path().to_path_buf(), 10).unwrap();
+
+        let first = prompt(PROMPT_CACHE_BLOCK_TOKENS, 0);
+        let second = prompt(PROMPT_CACHE_BLOCK_TOKENS, 1);
+        cache.store("model@rev", &first, b"aaaaaa").unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        cache.store("model@rev", &second, b"bbbbbb").unwrap();
+
+        assert_eq!(cache.lookup("model@rev", &first), None);
+        assert!(cache.lookup("model@rev", &second).is_some());
+    }
+}
@@ -0,0 +1,64 @@
+/// Placeholder marking where an image is inserted in a rendered prompt
+pub const IMAGE_PLACEHOLDER: &str = "<image>";
+
+/// Model series (config.json `model_type`) with a supported vision encoder
+const VISION_MODEL_SERIES: &[&str] = &[
+    "llava",
+    "llava_next",
+    "qwen2_vl",
+    "qwen2_5_vl",
+    "qwen3_vl",
+    "gemma3",
+    "idefics3",
+    "smolvlm",
+    "paligemma",
+    "mllama",
+];
+
+/// Decoded image passed to the engine alongside the prompt
+#[derive(Debug, Clone)]
+pub struct ImageInput {
+    pub data: Vec<u8>,
+    pub mime_type: String,
+}
+
+/// Whether the engine has a vision encoder for the given model series
+pub fn supports_vision(model_series: Option<&str>) -> bool {
+    model_series.is_some_and(|series| VISION_MODEL_SERIES.contains(&series))
+}
+
+/// Detect the MIME type of an image from its magic bytes
+pub fn sniff_mime_type(data: &[u8]) -> Option<&'static str> {
+    if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("image/png")
+    } else if data.starts_with(b"\xff\xd8\xff") {
+        Some("image/jpeg")
+    } else if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+        Some("image/gif")
+    } else if data.len() >= 12 && &data[..4] == b"RIFF" && &data[8..12] == b"WEBP" {
+        Some("image/webp")
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_supports_vision() {
+        assert!(supports_vision(Some("qwen2_vl")));
+        assert!(!supports_vision(Some("gpt2")));
+        assert!(!supports_vision(None));
+    }
+
+    #[test]
+    fn test_sniff_mime_type() {
+        assert_eq!(sniff_mime_type(b"\x89PNG\r\n\x1a\n...."), Some("image/png"));
+        assert_eq!(sniff_mime_type(b"\xff\xd8\xff\xe0"), Some("image/jpeg"));
+        assert_eq!(sniff_mime_type(b"GIF89a"), Some("image/gif"));
+        assert_eq!(sniff_mime_type(b"RIFF\0\0\0\0WEBPVP8 "), Some("image/webp"));
+        assert_eq!(sniff_mime_type(b"hello"), None);
+    }
+}
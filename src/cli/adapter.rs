@@ -0,0 +1,141 @@
+use std::path::Path;
+
+use crate::registry::model_registry::{AdapterInfo, ModelRegistry};
+
+/// Execute the ADAPTER ADD command logic
+pub fn execute_add(
+    registry: &ModelRegistry,
+    name: &str,
+    base_model: &str,
+    path: &str,
+) -> Result<AdapterInfo, String> {
+    // PEFT-style LoRA adapters ship an adapter_config.json next to the weights
+    let adapter_dir = Path::new(path);
+    if !adapter_dir.join("adapter_config.json").exists() {
+        return Err(format!(
+            "Invalid adapter path '{}': adapter_config.json not found",
+            path
+        ));
+    }
+
+    let path = adapter_dir
+        .canonicalize()
+        .map_err(|e| format!("Invalid adapter path '{}': {}", path, e))?;
+
+    let adapter = AdapterInfo {
+        name: name.to_lowercase(),
+        base_model: base_model.to_lowercase(),
+        path: path.to_string_lossy().to_string(),
+        created_at: chrono::Local::now().to_rfc3339(),
+    };
+
+    registry
+        .register_adapter(adapter.clone())
+        .map_err(|e| format!("Failed to register adapter: {}", e))?;
+
+    Ok(adapter)
+}
+
+/// Execute the ADAPTER LS command logic
+pub fn execute_ls(
+    registry: &ModelRegistry,
+    base_model: Option<&str>,
+) -> Result<Vec<AdapterInfo>, String> {
+    registry
+        .load_adapters(base_model)
+        .map_err(|e| format!("Failed to query adapters: {}", e))
+}
+
+/// Execute the ADAPTER RM command logic
+pub fn execute_rm(registry: &ModelRegistry, name: &str) -> Result<(), String> {
+    match registry.get_adapter(name) {
+        Ok(Some(_)) => registry
+            .unregister_adapter(name)
+            .map_err(|e| format!("Failed to remove adapter: {}", e)),
+        Ok(None) => Err(format!("Adapter not found: {}", name)),
+        Err(e) => Err(format!("Failed to load registry: {}", e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::model_registry::{CacheInfo, ModelInfo, ModelMetadata};
+    use tempfile::TempDir;
+
+    fn create_test_model(name: &str, uuid: &str) -> ModelInfo {
+        ModelInfo {
+            uuid: uuid.to_string(),
+            name: name.to_string(),
+            provider: "huggingface".to_string(),
+            author: Some("test-author".to_string()),
+            task: Some("text-generation".to_string()),
+            model_series: Some("llama".to_string()),
+            license: Some("mit".to_string()),
+            created_at: "2025-01-01T00:00:00Z".to_string(),
+            updated_at: "2025-01-01T00:00:00Z".to_string(),
+            metadata: ModelMetadata {
+                cache: CacheInfo {
+                    revision: uuid.to_string(),
+                    size: 1000,
+                    path: "/tmp/test".to_string(),
+                },
+                context_window: Some(2048),
+                safetensors: None,
+            },
+        }
+    }
+
+    fn create_adapter_dir(temp_dir: &TempDir) -> String {
+        let adapter_dir = temp_dir.path().join("sql-lora");
+        std::fs::create_dir_all(&adapter_dir).unwrap();
+        std::fs::write(adapter_dir.join("adapter_config.json"), "{\"r\": 8}").unwrap();
+        adapter_dir.to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn test_execute_adapter_add_ls_rm() {
+        let temp_dir = TempDir::new().unwrap();
+        let registry = ModelRegistry::new(Some(temp_dir.path().to_path_buf()));
+        registry
+            .register_model(create_test_model("test/base", "abc123"))
+            .unwrap();
+        let path = create_adapter_dir(&temp_dir);
+
+        let adapter = execute_add(&registry, "Test/SQL-LoRA", "test/base", &path).unwrap();
+        assert_eq!(adapter.name, "test/sql-lora");
+
+        let adapters = execute_ls(&registry, Some("test/base")).unwrap();
+        assert_eq!(adapters.len(), 1);
+        assert_eq!(adapters[0].base_model, "test/base");
+
+        execute_rm(&registry, "test/sql-lora").unwrap();
+        assert!(execute_ls(&registry, None).unwrap().is_empty());
+
+        // Removing an adapter leaves its files alone
+        assert!(std::path::Path::new(&path).exists());
+    }
+
+    #[test]
+    fn test_execute_adapter_add_invalid_path() {
+        let temp_dir = TempDir::new().unwrap();
+        let registry = ModelRegistry::new(Some(temp_dir.path().to_path_buf()));
+        registry
+            .register_model(create_test_model("test/base", "abc123"))
+            .unwrap();
+
+        let result = execute_add(&registry, "test/lora", "test/base", "/nonexistent/lora");
+        assert!(result
+            .unwrap_err()
+            .contains("adapter_config.json not found"));
+    }
+
+    #[test]
+    fn test_execute_adapter_rm_nonexistent() {
+        let temp_dir = TempDir::new().unwrap();
+        let registry = ModelRegistry::new(Some(temp_dir.path().to_path_buf()));
+
+        let result = execute_rm(&registry, "nonexistent/lora");
+        assert!(result.unwrap_err().contains("Adapter not found"));
+    }
+}
@@ -0,0 +1,68 @@
+use axum::body::{to_bytes, Body, Bytes};
+use axum::http::{header, Request, StatusCode};
+use hyper::client::conn::http1;
+use hyper_util::rt::TokioIo;
+use std::io;
+use std::path::{Path, PathBuf};
+use tokio::net::UnixStream;
+
+/// HTTP client of a server listening on a Unix domain socket
+pub struct SocketClient {
+    socket: PathBuf,
+    api_key: Option<String>,
+}
+
+impl SocketClient {
+    pub fn new(socket: &Path) -> Self {
+        Self {
+            socket: socket.to_path_buf(),
+            api_key: None,
+        }
+    }
+
+    /// Send this key as a bearer token, for servers requiring API keys
+    pub fn with_api_key(mut self, api_key: Option<String>) -> Self {
+        self.api_key = api_key;
+        self
+    }
+
+    /// Whether a server listens on the socket
+    pub async fn is_listening(&self) -> bool {
+        UnixStream::connect(&self.socket).await.is_ok()
+    }
+
+    /// GET `path`, returns the response status and body
+    pub async fn get(&self, path: &str) -> Result<(StatusCode, Bytes), String> {
+        self.send(path).await.map_err(|e| {
+            format!(
+                "Failed to reach the server at '{}': {}",
+                self.socket.display(),
+                e
+            )
+        })
+    }
+
+    async fn send(&self, path: &str) -> io::Result<(StatusCode, Bytes)> {
+        let stream = UnixStream::connect(&self.socket).await?;
+        let (mut sender, connection) = http1::handshake(TokioIo::new(stream))
+            .await
+            .map_err(io::Error::other)?;
+        tokio::spawn(connection);
+
+        let mut request = Request::get(path).header(header::HOST, "localhost");
+        if let Some(key) = &self.api_key {
+            request = request.header(header::AUTHORIZATION, format!("Bearer {}", key));
+        }
+        let request = request.body(Body::empty()).map_err(io::Error::other)?;
+        let response = sender
+            .send_request(request)
+            .await
+            .map_err(io::Error::other)?;
+
+        let status = response.status();
+        let body = to_bytes(Body::new(response.into_body()), usize::MAX)
+            .await
+            .map_err(io::Error::other)?;
+        Ok((status, body))
+    }
+}
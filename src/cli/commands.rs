@@ -1,12 +1,24 @@
 use clap::{Parser, Subcommand};
 use prettytable::{format, row, Table};
-
-use crate::cli::{inspect, ls, rm};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::api::cors::{self, CorsOptions};
+use crate::api::ratelimit::RateLimits;
+use crate::api::tls::TlsOptions;
+use crate::backend::mock::MockEngine;
+use crate::cli::client::SocketClient;
+use crate::cli::serve::ServeOptions;
+use crate::cli::{adapter, inspect, key, ls, ps, rm, tokenize, usage};
 use crate::downloader::downloader::Downloader;
 use crate::downloader::huggingface::HuggingFaceDownloader;
+use crate::downloader::progress::TerminalProgress;
 use crate::registry::model_registry::ModelRegistry;
+use crate::storage::{SqliteStorage, UsageGroup};
 use crate::system::system_info::SystemInfo;
-use crate::utils::format::{format_size_decimal, format_time_ago};
+use crate::utils::file;
+use crate::utils::format::{format_size_decimal, format_time_ago, parse_mode, parse_size};
 
 #[derive(Parser)]
 #[command(name = "PUMA")]
@@ -20,7 +32,7 @@ pub struct Cli {
 #[allow(clippy::upper_case_acronyms)]
 enum Commands {
     /// List running models
-    PS,
+    PS(PsArgs),
     /// List local models
     LS(LsArgs),
     /// Download a model from a model provider
@@ -38,7 +50,15 @@ enum Commands {
     /// Returns the version of PUMA.
     VERSION,
     /// Start the inference server
-    SERVE(ServeArgs),
+    SERVE(Box<ServeArgs>),
+    /// Manage LoRA adapters on top of local models
+    ADAPTER(AdapterArgs),
+    /// Tokenize text with a model's tokenizer
+    TOKENIZE(TokenizeArgs),
+    /// Manage API keys of the inference server
+    KEY(KeyArgs),
+    /// Summarize the requests recorded by the inference server
+    USAGE(UsageArgs),
 }
 
 #[derive(Parser)]
@@ -53,6 +73,122 @@ struct ServeArgs {
     /// Port to listen on
     #[arg(short, long, default_value = "8000")]
     port: u16,
+
+    /// Persist the KV state of long prompts on disk and reuse it across requests and restarts
+    #[arg(long)]
+    prompt_cache: bool,
+
+    /// Maximum size of the prompt cache (e.g., 512MB, 10GB)
+    #[arg(long, default_value = "10GB", value_parser = parse_size)]
+    prompt_cache_size: u64,
+
+    /// Require this API key as a bearer token (repeatable, comma-separated in PUMA_API_KEY)
+    #[arg(
+        long = "api-key",
+        env = "PUMA_API_KEY",
+        value_delimiter = ',',
+        hide_env_values = true
+    )]
+    api_keys: Vec<String>,
+
+    /// Require the API keys listed in this file, one per line
+    #[arg(long, value_name = "PATH")]
+    api_key_file: Option<String>,
+
+    /// Leave the /health endpoints open when API keys are required
+    #[arg(long)]
+    public_health: bool,
+
+    /// Default maximum requests per minute of each API key
+    #[arg(long)]
+    rate_limit_rpm: Option<u64>,
+
+    /// Default maximum prompt and completion tokens per minute of each API key
+    #[arg(long)]
+    rate_limit_tpm: Option<u64>,
+
+    /// Default maximum prompt and completion tokens per UTC day of each API key
+    #[arg(long)]
+    daily_token_quota: Option<u64>,
+
+    /// Maximum generations running at once, more requests wait in the queue
+    #[arg(long, default_value = "4", value_parser = clap::value_parser!(u64).range(1..))]
+    max_concurrency: u64,
+
+    /// Maximum requests waiting for a generation slot, more are rejected with 503
+    #[arg(long, default_value = "64")]
+    max_queue_depth: usize,
+
+    /// Keep the full request and response bodies in the audit log
+    #[arg(long)]
+    audit_bodies: bool,
+
+    /// Seconds given to in-flight requests to finish on Ctrl-C or SIGTERM
+    #[arg(long, value_name = "SECONDS", default_value = "30")]
+    shutdown_grace_period: u64,
+
+    /// Serve HTTPS with this PEM certificate chain, reloaded on SIGHUP
+    #[arg(long, value_name = "PATH", requires = "tls_key")]
+    tls_cert: Option<String>,
+
+    /// PEM private key of the TLS certificate
+    #[arg(long, value_name = "PATH", requires = "tls_cert")]
+    tls_key: Option<String>,
+
+    /// Require client certificates signed by these PEM CA certificates (mTLS)
+    #[arg(long, value_name = "PATH", requires = "tls_cert")]
+    tls_client_ca: Option<String>,
+
+    /// Also listen on a Unix domain socket, ~/.puma/puma.sock unless a path is given
+    #[arg(long, value_name = "PATH")]
+    socket: Option<Option<String>>,
+
+    /// Octal permissions of the socket file (e.g., 660 to share it with the group)
+    #[arg(long, value_name = "MODE", default_value = "600", value_parser = parse_mode)]
+    socket_mode: u32,
+
+    /// Listen on the socket only, without opening a network port
+    #[arg(long, requires = "socket")]
+    socket_only: bool,
+
+    /// Allow browser requests from this origin (repeatable, comma-separated, * for any)
+    #[arg(long = "cors-origin", value_name = "ORIGIN", value_delimiter = ',')]
+    cors_origins: Vec<String>,
+
+    /// Methods allowed in cross-origin requests (* for any)
+    #[arg(long, value_delimiter = ',', default_values = cors::DEFAULT_METHODS)]
+    cors_methods: Vec<String>,
+
+    /// Headers allowed in cross-origin requests (* for any)
+    #[arg(long, value_delimiter = ',', default_values = cors::DEFAULT_HEADERS)]
+    cors_headers: Vec<String>,
+
+    /// Allow cross-origin requests with cookies or HTTP authentication
+    #[arg(long, requires = "cors_origins")]
+    cors_credentials: bool,
+
+    /// Seconds browsers may cache the answers to CORS preflight requests
+    #[arg(long, value_name = "SECONDS")]
+    cors_max_age: Option<u64>,
+
+    /// Serve the model management endpoints (/admin, /api/pull, /api/delete) without API keys
+    #[arg(long)]
+    enable_admin: bool,
+
+    /// Let requests reference image files in this directory by path, instead of data: URLs only
+    #[arg(long, value_name = "PATH")]
+    image_dir: Option<String>,
+}
+
+#[derive(Parser)]
+struct PsArgs {
+    /// Unix domain socket of the server (default: ~/.puma/puma.sock)
+    #[arg(long, value_name = "PATH", env = "PUMA_SOCKET")]
+    socket: Option<String>,
+
+    /// API key of the server, when it requires one
+    #[arg(long, env = "PUMA_API_KEY", hide_env_values = true)]
+    api_key: Option<String>,
 }
 
 #[derive(Parser)]
@@ -91,6 +227,142 @@ struct InspectArgs {
     model: String,
 }
 
+#[derive(Parser)]
+struct TokenizeArgs {
+    /// Model whose tokenizer to use (e.g., inftyai/tiny-random-gpt2)
+    model: String,
+
+    /// Text to tokenize
+    text: String,
+
+    /// Apply the chat template, treating the text as a user message
+    #[arg(long)]
+    chat: bool,
+}
+
+#[derive(Parser)]
+struct AdapterArgs {
+    #[command(subcommand)]
+    command: AdapterCommands,
+}
+
+#[derive(Subcommand)]
+#[allow(clippy::upper_case_acronyms)]
+enum AdapterCommands {
+    /// Register a local LoRA adapter on top of a base model
+    ADD(AdapterAddArgs),
+    /// List registered adapters
+    LS(AdapterLsArgs),
+    /// Remove one adapter from the registry (adapter files are kept)
+    RM(AdapterRmArgs),
+}
+
+#[derive(Parser)]
+struct AdapterAddArgs {
+    /// Adapter name used as `model` in API requests (e.g., inftyai/sql-lora)
+    name: String,
+
+    /// Base model the adapter was trained on (e.g., inftyai/tiny-random-gpt2)
+    #[arg(short, long)]
+    base: String,
+
+    /// Local directory containing the adapter weights and adapter_config.json
+    #[arg(long)]
+    path: String,
+}
+
+#[derive(Parser)]
+struct AdapterLsArgs {
+    /// Only list adapters built on this base model
+    #[arg(short, long)]
+    base: Option<String>,
+}
+
+#[derive(Parser)]
+struct AdapterRmArgs {
+    /// Adapter name to remove
+    name: String,
+}
+
+#[derive(Parser)]
+struct KeyArgs {
+    #[command(subcommand)]
+    command: KeyCommands,
+}
+
+#[derive(Subcommand)]
+#[allow(clippy::upper_case_acronyms)]
+enum KeyCommands {
+    /// Create an API key, the key is only shown once
+    CREATE(KeyCreateArgs),
+    /// List API keys
+    #[command(alias = "ls")]
+    LIST,
+    /// Revoke an API key
+    REVOKE(KeyRevokeArgs),
+}
+
+#[derive(Parser)]
+struct KeyCreateArgs {
+    /// Name of the key (e.g., ci, alice)
+    name: String,
+
+    /// Maximum requests per minute, overrides the server default
+    #[arg(long)]
+    rpm: Option<u64>,
+
+    /// Maximum prompt and completion tokens per minute, overrides the server default
+    #[arg(long)]
+    tpm: Option<u64>,
+
+    /// Maximum prompt and completion tokens per UTC day, overrides the server default
+    #[arg(long)]
+    daily_tokens: Option<u64>,
+}
+
+#[derive(Parser)]
+struct KeyRevokeArgs {
+    /// Name or id of the key to revoke
+    key: String,
+}
+
+#[derive(Parser)]
+struct UsageArgs {
+    /// Columns to summarize by (e.g., model or day,key)
+    #[arg(
+        long,
+        value_enum,
+        value_delimiter = ',',
+        default_value = "day,model,key"
+    )]
+    by: Vec<UsageColumn>,
+
+    /// Only include requests of the last N days, today included
+    #[arg(long, default_value = "30")]
+    days: u32,
+
+    /// List the latest N requests instead of a summary
+    #[arg(long, value_name = "N")]
+    recent: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+enum UsageColumn {
+    Day,
+    Model,
+    Key,
+}
+
+impl From<UsageColumn> for UsageGroup {
+    fn from(column: UsageColumn) -> Self {
+        match column {
+            UsageColumn::Day => UsageGroup::Day,
+            UsageColumn::Model => UsageGroup::Model,
+            UsageColumn::Key => UsageGroup::Key,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Default, clap::ValueEnum)]
 pub enum Provider {
     #[default]
@@ -103,24 +375,21 @@ pub enum Provider {
 // Support commands like: pull, ls, run, ps, stop, rm, info, inspect, show.
 pub async fn run(cli: Cli) {
     match cli.command {
-        Commands::PS => {
-            let mut table = Table::new();
-            table.set_format(
-                format::FormatBuilder::new()
-                    .column_separator(' ')
-                    .padding(0, 1)
-                    .build(),
-            );
-            table.add_row(row!["NAME", "PROVIDER", "MODEL", "STATUS", "AGE"]);
-            table.add_row(row![
-                "deepseek-r1",
-                "huggingface",
-                "deepseek-ai/DeepSeek-R1",
-                "Running",
-                "8m",
-            ]);
-
-            table.printstd();
+        Commands::PS(args) => {
+            let registry = ModelRegistry::new(None);
+            let socket = args
+                .socket
+                .map(PathBuf::from)
+                .unwrap_or_else(file::socket_path);
+            let client = SocketClient::new(&socket).with_api_key(args.api_key);
+
+            match ps::execute(&registry, &client).await {
+                Ok(running) => ps::display(running.as_ref(), &socket),
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            }
         }
 
         Commands::LS(args) => {
@@ -173,7 +442,8 @@ pub async fn run(cli: Cli) {
 
         Commands::PULL(args) => match args.provider {
             Provider::Huggingface => {
-                let downloader = HuggingFaceDownloader::new();
+                let downloader =
+                    HuggingFaceDownloader::new().with_observer(Arc::new(TerminalProgress::new()));
                 // Make sure to use lowercase for model name to ensure consistent caching and registry entries.
                 if let Err(e) = downloader.download_model(&args.model.to_lowercase()).await {
                     eprintln!("❌ Error downloading model: {}", e);
@@ -224,6 +494,7 @@ pub async fn run(cli: Cli) {
         }
 
         Commands::SERVE(args) => {
+            let args = *args;
             // Verify model exists
             let registry = ModelRegistry::new(None);
             match registry.get_model(&args.model) {
@@ -241,11 +512,227 @@ pub async fn run(cli: Cli) {
                 }
             }
 
-            if let Err(e) = crate::cli::serve::execute(&args.host, args.port, &args.model).await {
+            let options = ServeOptions {
+                host: args.host,
+                port: args.port,
+                prompt_cache_size: args.prompt_cache.then_some(args.prompt_cache_size),
+                api_keys: args.api_keys,
+                api_key_file: args.api_key_file.map(PathBuf::from),
+                public_health: args.public_health,
+                rate_limits: RateLimits {
+                    requests_per_minute: args.rate_limit_rpm,
+                    tokens_per_minute: args.rate_limit_tpm,
+                    daily_tokens: args.daily_token_quota,
+                },
+                cors: CorsOptions {
+                    origins: args.cors_origins,
+                    methods: args.cors_methods,
+                    headers: args.cors_headers,
+                    credentials: args.cors_credentials,
+                    max_age: args.cors_max_age.map(Duration::from_secs),
+                },
+                max_concurrency: args.max_concurrency as usize,
+                max_queue_depth: args.max_queue_depth,
+                audit_bodies: args.audit_bodies,
+                shutdown_grace_period: Duration::from_secs(args.shutdown_grace_period),
+                tls: args
+                    .tls_cert
+                    .zip(args.tls_key)
+                    .map(|(cert, key)| TlsOptions {
+                        cert: PathBuf::from(cert),
+                        key: PathBuf::from(key),
+                        client_ca: args.tls_client_ca.map(PathBuf::from),
+                    }),
+                socket: args
+                    .socket
+                    .map(|path| path.map(PathBuf::from).unwrap_or_else(file::socket_path)),
+                socket_mode: args.socket_mode,
+                socket_only: args.socket_only,
+                enable_admin: args.enable_admin,
+                image_dir: args.image_dir.map(PathBuf::from),
+            };
+            if let Err(e) = crate::cli::serve::execute(&args.model, options).await {
                 eprintln!("Error starting server: {}", e);
                 std::process::exit(1);
             }
         }
+
+        Commands::TOKENIZE(args) => {
+            let registry = ModelRegistry::new(None);
+            // MockEngine for now, replace with MLX later
+            let engine = MockEngine::new();
+
+            match tokenize::execute(&engine, &registry, &args.model, &args.text, args.chat).await {
+                Ok(tokens) => tokenize::display(&tokens),
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+
+        Commands::ADAPTER(args) => {
+            let registry = ModelRegistry::new(None);
+
+            match args.command {
+                AdapterCommands::ADD(args) => {
+                    match adapter::execute_add(&registry, &args.name, &args.base, &args.path) {
+                        Ok(adapter) => println!(
+                            "✓ Registered adapter {} on {}",
+                            adapter.name, adapter.base_model
+                        ),
+                        Err(e) => {
+                            eprintln!("{}", e);
+                            std::process::exit(1);
+                        }
+                    }
+                }
+                AdapterCommands::LS(args) => {
+                    let adapters = match adapter::execute_ls(&registry, args.base.as_deref()) {
+                        Ok(adapters) => adapters,
+                        Err(e) => {
+                            eprintln!("{}", e);
+                            std::process::exit(1);
+                        }
+                    };
+
+                    let mut table = Table::new();
+                    table.set_format(
+                        format::FormatBuilder::new()
+                            .column_separator(' ')
+                            .padding(0, 1)
+                            .build(),
+                    );
+                    table.add_row(row!["ADAPTER", "BASE MODEL", "PATH", "CREATED"]);
+                    for adapter in adapters {
+                        table.add_row(row![
+                            adapter.name,
+                            adapter.base_model,
+                            adapter.path,
+                            format_time_ago(&adapter.created_at)
+                        ]);
+                    }
+
+                    table.printstd();
+                }
+                AdapterCommands::RM(args) => {
+                    if let Err(e) = adapter::execute_rm(&registry, &args.name) {
+                        eprintln!("{}", e);
+                        std::process::exit(1);
+                    }
+                    println!("✓ Removed adapter {}", args.name);
+                }
+            }
+        }
+
+        Commands::KEY(args) => {
+            let registry = ModelRegistry::new(None);
+            let storage = match SqliteStorage::new(registry.db_path().to_path_buf()) {
+                Ok(storage) => storage,
+                Err(e) => {
+                    eprintln!("Failed to open database: {}", e);
+                    std::process::exit(1);
+                }
+            };
+
+            match args.command {
+                KeyCommands::CREATE(args) => {
+                    let limits = RateLimits {
+                        requests_per_minute: args.rpm,
+                        tokens_per_minute: args.tpm,
+                        daily_tokens: args.daily_tokens,
+                    };
+                    match key::execute_create(&storage, &args.name, limits) {
+                        Ok((stored, key)) => {
+                            println!("✓ Created API key {} ({})", stored.name, stored.id);
+                            println!("{}", key);
+                            println!("Store it safely, it will not be shown again.");
+                        }
+                        Err(e) => {
+                            eprintln!("{}", e);
+                            std::process::exit(1);
+                        }
+                    }
+                }
+                KeyCommands::LIST => {
+                    let keys = match key::execute_list(&storage) {
+                        Ok(keys) => keys,
+                        Err(e) => {
+                            eprintln!("{}", e);
+                            std::process::exit(1);
+                        }
+                    };
+
+                    let mut table = Table::new();
+                    table.set_format(
+                        format::FormatBuilder::new()
+                            .column_separator(' ')
+                            .padding(0, 1)
+                            .build(),
+                    );
+                    table.add_row(row!["ID", "NAME", "KEY", "LIMITS", "STATUS", "CREATED"]);
+                    for key in keys {
+                        let status = match &key.revoked_at {
+                            Some(revoked_at) => format!("Revoked {}", format_time_ago(revoked_at)),
+                            None => "Active".to_string(),
+                        };
+                        table.add_row(row![
+                            key.id,
+                            key.name,
+                            format!("{}...", key.prefix),
+                            key::format_limits(&key),
+                            status,
+                            format_time_ago(&key.created_at)
+                        ]);
+                    }
+
+                    table.printstd();
+                }
+                KeyCommands::REVOKE(args) => {
+                    if let Err(e) = key::execute_revoke(&storage, &args.key) {
+                        eprintln!("{}", e);
+                        std::process::exit(1);
+                    }
+                    println!("✓ Revoked API key {}", args.key);
+                }
+            }
+        }
+
+        Commands::USAGE(args) => {
+            let registry = ModelRegistry::new(None);
+            let storage = match SqliteStorage::new(registry.db_path().to_path_buf()) {
+                Ok(storage) => storage,
+                Err(e) => {
+                    eprintln!("Failed to open database: {}", e);
+                    std::process::exit(1);
+                }
+            };
+
+            if let Some(limit) = args.recent {
+                match usage::execute_recent(&storage, limit) {
+                    Ok(entries) => usage::display_recent(&entries),
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        std::process::exit(1);
+                    }
+                }
+                return;
+            }
+
+            let mut group_by: Vec<UsageGroup> = Vec::new();
+            for column in args.by {
+                if !group_by.contains(&column.into()) {
+                    group_by.push(column.into());
+                }
+            }
+            match usage::execute_summary(&storage, &group_by, args.days) {
+                Ok(summaries) => usage::display_summary(&group_by, &summaries),
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
     }
 }
 
@@ -465,5 +952,55 @@ mod tests {
             "9000",
         ]);
         assert!(result.is_ok());
+
+        // Prompt cache is opt-in with a human-readable size cap
+        let result = Cli::try_parse_from(vec![
+            "puma",
+            "serve",
+            "test/model",
+            "--prompt-cache",
+            "--prompt-cache-size",
+            "512MB",
+        ]);
+        match result.unwrap().command {
+            Commands::SERVE(args) => {
+                assert!(args.prompt_cache);
+                assert_eq!(args.prompt_cache_size, 512_000_000);
+            }
+            _ => panic!("Expected SERVE command"),
+        }
+
+        // The socket path is optional, its permissions are octal
+        let result = Cli::try_parse_from(vec![
+            "puma",
+            "serve",
+            "test/model",
+            "--socket",
+            "--socket-mode",
+            "660",
+        ]);
+        match result.unwrap().command {
+            Commands::SERVE(args) => {
+                assert_eq!(args.socket, Some(None));
+                assert_eq!(args.socket_mode, 0o660);
+                assert!(!args.socket_only);
+            }
+            _ => panic!("Expected SERVE command"),
+        }
+        let result = Cli::try_parse_from(vec![
+            "puma",
+            "serve",
+            "test/model",
+            "--socket=/tmp/puma.sock",
+            "--socket-only",
+        ]);
+        match result.unwrap().command {
+            Commands::SERVE(args) => {
+                assert_eq!(args.socket, Some(Some("/tmp/puma.sock".to_string())));
+                assert!(args.socket_only);
+            }
+            _ => panic!("Expected SERVE command"),
+        }
+        assert!(Cli::try_parse_from(vec!["puma", "serve", "test/model", "--socket-only"]).is_err());
     }
 }
@@ -0,0 +1,105 @@
+use uuid::Uuid;
+
+use crate::api::auth::{api_key_prefix, generate_api_key, hash_api_key};
+use crate::api::ratelimit::RateLimits;
+use crate::storage::{ApiKeyStorage, StoredApiKey};
+
+/// Execute the KEY CREATE command logic, returns the stored key and the
+/// plaintext key, which is not kept anywhere
+pub fn execute_create(
+    storage: &impl ApiKeyStorage,
+    name: &str,
+    limits: RateLimits,
+) -> Result<(StoredApiKey, String), String> {
+    let exists = storage
+        .load_api_keys()
+        .map_err(|e| format!("Failed to load API keys: {}", e))?
+        .iter()
+        .any(|key| key.name == name);
+    if exists {
+        return Err(format!("API key '{}' already exists", name));
+    }
+
+    let key = generate_api_key();
+    let stored = StoredApiKey {
+        id: format!("key_{}", &Uuid::new_v4().simple().to_string()[..12]),
+        name: name.to_string(),
+        key_hash: hash_api_key(&key),
+        prefix: api_key_prefix(&key),
+        created_at: chrono::Local::now().to_rfc3339(),
+        revoked_at: None,
+        requests_per_minute: limits.requests_per_minute,
+        tokens_per_minute: limits.tokens_per_minute,
+        daily_token_quota: limits.daily_tokens,
+    };
+
+    storage
+        .create_api_key(stored.clone())
+        .map_err(|e| format!("Failed to store API key: {}", e))?;
+
+    Ok((stored, key))
+}
+
+/// Execute the KEY LIST command logic
+pub fn execute_list(storage: &impl ApiKeyStorage) -> Result<Vec<StoredApiKey>, String> {
+    storage
+        .load_api_keys()
+        .map_err(|e| format!("Failed to load API keys: {}", e))
+}
+
+/// Limits of a key for listings, e.g. `60 rpm, 1000000/day`
+pub fn format_limits(key: &StoredApiKey) -> String {
+    let limits = [
+        key.requests_per_minute.map(|n| format!("{} rpm", n)),
+        key.tokens_per_minute.map(|n| format!("{} tpm", n)),
+        key.daily_token_quota.map(|n| format!("{}/day", n)),
+    ];
+    let limits: Vec<String> = limits.into_iter().flatten().collect();
+    if limits.is_empty() {
+        "default".to_string()
+    } else {
+        limits.join(", ")
+    }
+}
+
+/// Execute the KEY REVOKE command logic
+pub fn execute_revoke(storage: &impl ApiKeyStorage, name_or_id: &str) -> Result<(), String> {
+    let revoked = storage
+        .revoke_api_key(name_or_id, &chrono::Local::now().to_rfc3339())
+        .map_err(|e| format!("Failed to revoke API key: {}", e))?;
+    if !revoked {
+        return Err(format!("Active API key not found: {}", name_or_id));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::SqliteStorage;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_key_lifecycle() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = SqliteStorage::new(temp_dir.path().join("test.db")).unwrap();
+
+        let limits = RateLimits {
+            requests_per_minute: Some(60),
+            ..Default::default()
+        };
+        let (stored, key) = execute_create(&storage, "ci", limits).unwrap();
+        assert_eq!(stored.key_hash, hash_api_key(&key));
+        assert_eq!(stored.requests_per_minute, Some(60));
+        assert!(key.starts_with(&stored.prefix));
+        assert!(execute_create(&storage, "ci", RateLimits::default()).is_err());
+
+        let keys = execute_list(&storage).unwrap();
+        assert_eq!(keys.len(), 1);
+        assert!(keys[0].revoked_at.is_none());
+
+        execute_revoke(&storage, "ci").unwrap();
+        assert!(execute_revoke(&storage, &stored.id).is_err());
+        assert!(execute_list(&storage).unwrap()[0].revoked_at.is_some());
+    }
+}
@@ -1,5 +1,11 @@
+pub mod adapter;
+pub mod client;
 pub mod commands;
 pub mod inspect;
+pub mod key;
 pub mod ls;
+pub mod ps;
 pub mod rm;
 pub mod serve;
+pub mod tokenize;
+pub mod usage;
@@ -0,0 +1,139 @@
+use axum::http::StatusCode;
+use prettytable::{format, row, Table};
+use std::path::Path;
+
+use crate::api::health::{ReadinessPhase, ReadinessStatus};
+use crate::cli::client::SocketClient;
+use crate::registry::model_registry::ModelRegistry;
+
+/// Model served by a running server
+pub struct RunningModel {
+    pub model: Option<String>,
+    pub provider: Option<String>,
+    pub status: ReadinessStatus,
+}
+
+/// Execute the PS command logic, asking the server listening on the socket
+/// what it serves. Returns `None` when no server is listening.
+pub async fn execute(
+    registry: &ModelRegistry,
+    client: &SocketClient,
+) -> Result<Option<RunningModel>, String> {
+    if !client.is_listening().await {
+        return Ok(None);
+    }
+
+    // 503 until the model is ready, with the same body
+    let (status, body) = client.get("/health/ready").await?;
+    match status {
+        StatusCode::OK | StatusCode::SERVICE_UNAVAILABLE => {}
+        StatusCode::UNAUTHORIZED => {
+            return Err("The server requires an API key, pass --api-key or set PUMA_API_KEY".into())
+        }
+        status => {
+            return Err(format!(
+                "Unexpected response from the server ({}): {}",
+                status,
+                String::from_utf8_lossy(&body)
+            ))
+        }
+    }
+    let status: ReadinessStatus = serde_json::from_slice(&body)
+        .map_err(|e| format!("Invalid response from the server: {}", e))?;
+
+    let provider = match &status.model {
+        Some(name) => registry
+            .get_model(name)
+            .map_err(|e| format!("Failed to check model: {}", e))?
+            .map(|model| model.provider),
+        None => None,
+    };
+    Ok(Some(RunningModel {
+        model: status.model.clone(),
+        provider,
+        status,
+    }))
+}
+
+/// Display the running model, or only the header when there is none
+pub fn display(running: Option<&RunningModel>, socket: &Path) {
+    let mut table = Table::new();
+    table.set_format(
+        format::FormatBuilder::new()
+            .column_separator(' ')
+            .padding(0, 1)
+            .build(),
+    );
+    table.add_row(row!["MODEL", "PROVIDER", "STATUS", "SOCKET"]);
+    if let Some(running) = running {
+        table.add_row(row![
+            running.model.as_deref().unwrap_or("-"),
+            running.provider.as_deref().unwrap_or("-"),
+            format_status(&running.status),
+            socket.display(),
+        ]);
+    }
+
+    table.printstd();
+}
+
+fn format_status(status: &ReadinessStatus) -> String {
+    match status.status {
+        ReadinessPhase::Starting => "Starting".to_string(),
+        ReadinessPhase::Loading => format!("Loading ({:.0}%)", status.progress * 100.0),
+        ReadinessPhase::WarmingUp => "Warming up".to_string(),
+        ReadinessPhase::Ready => "Running".to_string(),
+        ReadinessPhase::Failed => "Failed".to_string(),
+        ReadinessPhase::Draining => "Stopping".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::auth::ApiKeys;
+    use crate::api::health::Readiness;
+    use crate::api::routes::{create_router, AppState};
+    use crate::api::server::{self, bind_unix};
+    use crate::backend::mock::MockEngine;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_ps_over_socket() {
+        let temp_dir = TempDir::new().unwrap();
+        let socket = temp_dir.path().join("puma.sock");
+        let registry = Arc::new(ModelRegistry::new(Some(temp_dir.path().to_path_buf())));
+        let client = SocketClient::new(&socket);
+
+        // Nothing listening yet
+        assert!(execute(&registry, &client).await.unwrap().is_none());
+
+        let readiness = Arc::new(Readiness::new());
+        readiness.set_model("test-model");
+        readiness.set_phase(ReadinessPhase::Loading, 0.5);
+        let state = AppState::new(Arc::new(MockEngine::new()), registry.clone())
+            .with_readiness(readiness.clone())
+            .with_auth(Arc::new(ApiKeys::new(vec!["secret".to_string()])));
+        let listener = bind_unix(&socket, 0o600).await.unwrap();
+        tokio::spawn(server::serve(
+            vec![listener],
+            create_router(state),
+            std::future::pending(),
+            Duration::from_secs(1),
+        ));
+
+        let error = execute(&registry, &client).await.err().unwrap();
+        assert!(error.contains("API key"));
+
+        let client = client.with_api_key(Some("secret".to_string()));
+        let running = execute(&registry, &client).await.unwrap().unwrap();
+        assert_eq!(running.model.as_deref(), Some("test-model"));
+        assert_eq!(format_status(&running.status), "Loading (50%)");
+
+        readiness.set_phase(ReadinessPhase::Ready, 1.0);
+        let running = execute(&registry, &client).await.unwrap().unwrap();
+        assert_eq!(format_status(&running.status), "Running");
+    }
+}
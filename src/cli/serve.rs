@@ -1,16 +1,71 @@
 use colored::Colorize;
+use std::io::{self, Write};
+use std::path::PathBuf;
 use std::sync::Arc;
-use tracing::{debug, info};
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::signal::unix::{signal, SignalKind};
+use tracing::{debug, info, warn};
 
-use crate::api::routes::create_router;
+use crate::api::audit::AuditLog;
+use crate::api::auth::{load_keys_file, ApiKeys};
+use crate::api::cors::CorsOptions;
+use crate::api::health::{warm_up, Readiness};
+use crate::api::queue::RequestQueue;
+use crate::api::ratelimit::{RateLimiter, RateLimits};
+use crate::api::routes::{create_router, AppState};
+use crate::api::server::{self, Listener};
+use crate::api::tls::{reload_on_sighup, TlsConfig, TlsOptions};
 use crate::backend::mock::MockEngine;
+use crate::backend::prompt_cache::PromptCache;
+use crate::backend::InferenceEngine;
 use crate::registry::model_registry::ModelRegistry;
+use crate::storage::{ApiKeyStorage, SqliteStorage};
+use crate::utils::file;
+use crate::utils::format::format_size_decimal;
+
+/// Options of the serve command
+pub struct ServeOptions {
+    pub host: String,
+    pub port: u16,
+    /// Size cap of the persistent prompt cache, disabled when unset
+    pub prompt_cache_size: Option<u64>,
+    /// API keys required as bearer tokens, in addition to the database keys
+    pub api_keys: Vec<String>,
+    /// File listing more API keys, one per line
+    pub api_key_file: Option<PathBuf>,
+    /// Leave the health endpoints open when keys are required
+    pub public_health: bool,
+    /// Default limits of API keys without limits of their own
+    pub rate_limits: RateLimits,
+    /// Cross-origin requests allowed from browsers
+    pub cors: CorsOptions,
+    /// Generations running at once
+    pub max_concurrency: usize,
+    /// Requests waiting for a generation slot before new ones are rejected
+    pub max_queue_depth: usize,
+    /// Keep full request and response bodies in the audit log
+    pub audit_bodies: bool,
+    /// Time given to in-flight requests to finish on shutdown
+    pub shutdown_grace_period: Duration,
+    /// Terminate TLS with these certificates instead of serving plain HTTP
+    pub tls: Option<TlsOptions>,
+    /// Unix domain socket to listen on
+    pub socket: Option<PathBuf>,
+    /// Permissions of the socket file, e.g. `0o600`
+    pub socket_mode: u32,
+    /// Listen on the socket only, without binding a TCP port
+    pub socket_only: bool,
+    /// Serve the model management endpoints without API keys
+    pub enable_admin: bool,
+    /// Directory image inputs may be read from by path
+    pub image_dir: Option<PathBuf>,
+}
 
 /// Execute the serve command
 pub async fn execute(
-    host: &str,
-    port: u16,
     model_name: &str,
+    options: ServeOptions,
 ) -> Result<(), Box<dyn std::error::Error>> {
     println!(
         "{}",
@@ -30,7 +85,17 @@ pub async fn execute(
     info!("Starting PUMA to serve model: {}", model_name);
 
     // Initialize backend (MockEngine for now, replace with MLX later)
-    let engine = Arc::new(MockEngine::new());
+    let mut engine = MockEngine::new();
+    if let Some(max_bytes) = options.prompt_cache_size {
+        let dir = file::prompt_cache_dir();
+        info!(
+            "Prompt cache enabled at {} (max {})",
+            dir.display(),
+            format_size_decimal(max_bytes)
+        );
+        engine = engine.with_prompt_cache(Arc::new(PromptCache::new(dir, max_bytes)?));
+    }
+    let engine = Arc::new(engine);
     info!("Inference engine initialized");
     debug!("Using MockEngine backend");
 
@@ -38,25 +103,204 @@ pub async fn execute(
     let registry = Arc::new(ModelRegistry::new(None));
     info!("Model registry loaded");
 
-    // Create router
-    let app = create_router(engine, registry);
+    let model = registry
+        .get_model(model_name)?
+        .ok_or_else(|| format!("Model '{}' not found in registry", model_name))?;
 
-    // Bind address
-    let addr = format!("{}:{}", host, port);
-    let listener = tokio::net::TcpListener::bind(&addr).await?;
+    // Create router, not ready until the model is loaded and warmed up
+    let readiness = Arc::new(Readiness::new());
+    info!(
+        "Request queue: {} concurrent generations, {} queued at most",
+        options.max_concurrency, options.max_queue_depth
+    );
+    let queue = RequestQueue::new(options.max_concurrency, options.max_queue_depth);
+    let mut state = AppState::new(engine.clone(), registry)
+        .with_readiness(readiness.clone())
+        .with_queue(Arc::new(queue));
+    let audit = Arc::new(AuditLog::new(state.storage.clone()).with_bodies(options.audit_bodies));
+    state = state.with_audit(audit.clone());
+    if options.audit_bodies {
+        info!("Audit log records full request and response bodies");
+    }
+    match api_keys(&options, state.storage.clone())? {
+        Some(keys) => {
+            let limiter = RateLimiter::new(options.rate_limits, state.storage.clone());
+            state = state
+                .with_auth(Arc::new(keys))
+                .with_rate_limiter(Arc::new(limiter));
+        }
+        None if !options.rate_limits.is_empty() => {
+            warn!("Rate limits apply per API key and are ignored without authentication");
+        }
+        None => {}
+    }
+    if options.cors.origins.is_empty() {
+        info!("CORS: cross-origin requests are refused, allow origins with --cors-origin");
+    } else {
+        info!("CORS: allowing origins {}", options.cors.origins.join(", "));
+        if options.cors.origins.iter().any(|origin| origin == "*") && state.auth.is_none() {
+            warn!("Any web page can call the server: CORS allows any origin and no API key is required");
+        }
+    }
+    state = state.with_cors(options.cors.layer()?);
+    if let Some(dir) = &options.image_dir {
+        let dir = dir
+            .canonicalize()
+            .map_err(|e| format!("Invalid image directory '{}': {}", dir.display(), e))?;
+        if !dir.is_dir() {
+            return Err(format!("Image directory '{}' is not a directory", dir.display()).into());
+        }
+        info!("Image inputs may reference files in {}", dir.display());
+        state = state.with_image_dir(dir);
+    }
+    // Pulling and deleting models is only open to anyone when asked for
+    if state.auth.is_some() || options.enable_admin {
+        if state.auth.is_none() {
+            warn!("Model management is enabled and no API key is required");
+        }
+        info!("Model management endpoints enabled under /admin, /api/pull and /api/delete");
+        state = state.with_admin();
+    } else {
+        info!("Model management endpoints disabled, enable them with API keys or --enable-admin");
+    }
+    let metrics = state.metrics.clone();
+    let queue = state.queue.clone();
+    let app = create_router(state);
 
-    info!("Server listening on http://{}", addr);
+    // Bind addresses
+    let mut listeners = Vec::new();
+    if let Some(path) = &options.socket {
+        listeners.push(
+            server::bind_unix(path, options.socket_mode)
+                .await
+                .map_err(|e| format!("Failed to bind socket '{}': {}", path.display(), e))?,
+        );
+        info!(
+            "Server listening on unix:{} (mode {:o})",
+            path.display(),
+            options.socket_mode
+        );
+    }
+    if !options.socket_only {
+        let addr = format!("{}:{}", options.host, options.port);
+        let listener = TcpListener::bind(&addr).await?;
+        listeners.push(match &options.tls {
+            Some(tls) => {
+                let config = Arc::new(TlsConfig::load(tls.clone())?);
+                if config.requires_client_cert() {
+                    info!("TLS client certificates required");
+                }
+                tokio::spawn(reload_on_sighup(config.clone()));
+                info!("Server listening on https://{}", addr);
+                Listener::Tls(listener, config)
+            }
+            None => {
+                info!("Server listening on http://{}", addr);
+                Listener::Tcp(listener)
+            }
+        });
+    }
     info!("Available endpoints:");
     info!("  POST /v1/chat/completions");
     info!("  POST /v1/completions");
+    info!("  POST /v1/responses");
+    info!("  GET  /v1/responses/:id");
+    info!("  POST /v1/messages");
+    info!("  POST /api/generate, /api/chat (Ollama)");
+    info!("  POST /v1/rerank");
     info!("  GET  /v1/models");
     info!("  GET  /v1/models/:model");
+    info!("  POST /tokenize");
+    info!("  POST /detokenize");
     info!("  GET  /health");
+    info!("  GET  /health/live");
+    info!("  GET  /health/ready");
+    info!("  GET  /metrics");
+
+    // Load and warm up the model in the background, /health/ready reports progress
+    tokio::spawn({
+        let engine = engine.clone();
+        let model = model.clone();
+        let readiness = readiness.clone();
+        async move { warm_up(engine.as_ref(), &model, &readiness).await }
+    });
 
-    // Start server
+    // Start server, until Ctrl-C or SIGTERM
     debug!("Starting axum server");
-    axum::serve(listener, app).await?;
+    let grace_period = options.shutdown_grace_period;
+    let shutdown = async move {
+        shutdown_signal().await;
+        readiness.drain();
+        info!(
+            "Shutting down, waiting up to {}s for {} in-flight generations",
+            grace_period.as_secs(),
+            queue.stats().in_flight
+        );
 
-    info!("Server shutdown");
+        // A second signal skips the grace period
+        tokio::spawn(async {
+            shutdown_signal().await;
+            warn!("Forced shutdown, in-flight requests are dropped");
+            std::process::exit(130);
+        });
+    };
+    if !server::serve(listeners, app, shutdown, grace_period).await? {
+        warn!("Grace period elapsed, in-flight requests were dropped");
+    }
+
+    audit.flush().await;
+
+    if let Err(e) = engine.unload_model(&model.name).await {
+        warn!("Failed to unload model {}: {}", model.name, e);
+    }
+    info!(
+        "Server shutdown after {} requests",
+        metrics.requests_total()
+    );
+    io::stdout().flush()?;
     Ok(())
 }
+
+/// Resolve on the first Ctrl-C or SIGTERM
+async fn shutdown_signal() {
+    let mut terminate =
+        signal(SignalKind::terminate()).expect("failed to install the SIGTERM handler");
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => {}
+        _ = terminate.recv() => {}
+    }
+}
+
+/// Keys required by the server, `None` when no key is configured or stored
+fn api_keys(
+    options: &ServeOptions,
+    storage: Arc<SqliteStorage>,
+) -> Result<Option<ApiKeys>, Box<dyn std::error::Error>> {
+    let mut keys = options.api_keys.clone();
+    if let Some(path) = &options.api_key_file {
+        keys.extend(
+            load_keys_file(path)
+                .map_err(|e| format!("Failed to read API key file '{}': {}", path.display(), e))?,
+        );
+    }
+
+    let stored = storage.has_active_api_keys()?;
+    if keys.is_empty() && !stored {
+        warn!("No API keys configured, the server accepts unauthenticated requests");
+        return Ok(None);
+    }
+
+    let keys = ApiKeys::new(keys)
+        .with_storage(storage)
+        .with_public_health(options.public_health);
+    info!(
+        "API key authentication enabled ({} configured keys{})",
+        keys.configured_count(),
+        if stored {
+            ", plus keys from `puma key`"
+        } else {
+            ""
+        }
+    );
+    Ok(Some(keys))
+}
@@ -0,0 +1,124 @@
+use prettytable::{format, row, Table};
+
+use crate::api::chat::format_chat_messages;
+use crate::api::types::ChatMessage;
+use crate::backend::{InferenceEngine, Token};
+use crate::registry::model_registry::ModelRegistry;
+
+/// Execute the TOKENIZE command logic
+pub async fn execute<E: InferenceEngine>(
+    engine: &E,
+    registry: &ModelRegistry,
+    model_name: &str,
+    text: &str,
+    chat: bool,
+) -> Result<Vec<Token>, String> {
+    let model = match registry.resolve_model(model_name) {
+        Ok(Some(resolved)) => resolved.base,
+        Ok(None) => return Err(format!("Model not found: {}", model_name)),
+        Err(e) => return Err(format!("Failed to load registry: {}", e)),
+    };
+
+    // Render the text as a single user turn with the chat template
+    let text = if chat {
+        format_chat_messages(&[ChatMessage {
+            role: "user".to_string(),
+            content: text.to_string().into(),
+            reasoning_content: None,
+        }])
+    } else {
+        text.to_string()
+    };
+
+    engine
+        .tokenize(&model.name, &text)
+        .await
+        .map_err(|e| format!("Failed to tokenize: {}", e))
+}
+
+/// Display the tokens
+pub fn display(tokens: &[Token]) {
+    let mut table = Table::new();
+    table.set_format(
+        format::FormatBuilder::new()
+            .column_separator(' ')
+            .padding(0, 1)
+            .build(),
+    );
+    table.add_row(row!["ID", "TOKEN"]);
+    for token in tokens {
+        table.add_row(row![token.id, format!("{:?}", token.text)]);
+    }
+
+    table.printstd();
+    println!("count: {}", tokens.len());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::mock::MockEngine;
+    use crate::registry::model_registry::{CacheInfo, ModelInfo, ModelMetadata};
+    use tempfile::TempDir;
+
+    fn create_test_model(name: &str, uuid: &str) -> ModelInfo {
+        ModelInfo {
+            uuid: uuid.to_string(),
+            name: name.to_string(),
+            provider: "huggingface".to_string(),
+            author: Some("test-author".to_string()),
+            task: Some("text-generation".to_string()),
+            model_series: Some("gpt2".to_string()),
+            license: Some("mit".to_string()),
+            created_at: "2025-01-01T00:00:00Z".to_string(),
+            updated_at: "2025-01-01T00:00:00Z".to_string(),
+            metadata: ModelMetadata {
+                cache: CacheInfo {
+                    revision: uuid.to_string(),
+                    size: 1000,
+                    path: "/tmp/test".to_string(),
+                },
+                context_window: Some(2048),
+                safetensors: None,
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn test_execute_tokenize() {
+        let temp_dir = TempDir::new().unwrap();
+        let registry = ModelRegistry::new(Some(temp_dir.path().to_path_buf()));
+        registry
+            .register_model(create_test_model("test/model", "abc123"))
+            .unwrap();
+        let engine = MockEngine::new();
+
+        let tokens = execute(&engine, &registry, "test/model", "Hi", false)
+            .await
+            .unwrap();
+        assert_eq!(tokens.len(), 2);
+        assert_eq!(tokens[0].text, "H");
+
+        // The chat template adds the role prefix
+        let chat_tokens = execute(&engine, &registry, "test/model", "Hi", true)
+            .await
+            .unwrap();
+        assert!(chat_tokens.len() > tokens.len());
+    }
+
+    #[tokio::test]
+    async fn test_execute_tokenize_nonexistent() {
+        let temp_dir = TempDir::new().unwrap();
+        let registry = ModelRegistry::new(Some(temp_dir.path().to_path_buf()));
+
+        let result = execute(
+            &MockEngine::new(),
+            &registry,
+            "nonexistent/model",
+            "Hi",
+            false,
+        )
+        .await;
+        assert!(result.unwrap_err().contains("Model not found"));
+    }
+}
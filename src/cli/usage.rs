@@ -0,0 +1,169 @@
+use chrono::{Duration, Utc};
+use prettytable::{format, Cell, Row, Table};
+
+use crate::storage::{AuditEntry, AuditStorage, UsageGroup, UsageSummary};
+use crate::utils::format::format_time_ago;
+
+/// Execute the USAGE command logic, summarizing the requests of the last days
+pub fn execute_summary(
+    storage: &impl AuditStorage,
+    group_by: &[UsageGroup],
+    days: u32,
+) -> Result<Vec<UsageSummary>, String> {
+    storage
+        .summarize_usage(group_by, Some(&since_day(days)))
+        .map_err(|e| format!("Failed to load usage: {}", e))
+}
+
+/// Execute the USAGE --recent command logic
+pub fn execute_recent(
+    storage: &impl AuditStorage,
+    limit: usize,
+) -> Result<Vec<AuditEntry>, String> {
+    storage
+        .recent_requests(limit)
+        .map_err(|e| format!("Failed to load requests: {}", e))
+}
+
+/// First UTC day of the last `days` days, today included
+fn since_day(days: u32) -> String {
+    let first = Utc::now() - Duration::days(i64::from(days.max(1)) - 1);
+    first.format("%Y-%m-%d").to_string()
+}
+
+fn new_table() -> Table {
+    let mut table = Table::new();
+    table.set_format(
+        format::FormatBuilder::new()
+            .column_separator(' ')
+            .padding(0, 1)
+            .build(),
+    );
+    table
+}
+
+/// Display the summary, with a column per grouped column
+pub fn display_summary(group_by: &[UsageGroup], summaries: &[UsageSummary]) {
+    let mut header: Vec<&str> = group_by
+        .iter()
+        .map(|group| match group {
+            UsageGroup::Day => "DAY",
+            UsageGroup::Model => "MODEL",
+            UsageGroup::Key => "KEY",
+        })
+        .collect();
+    header.extend(["REQUESTS", "ERRORS", "PROMPT", "COMPLETION", "AVG LATENCY"]);
+
+    let mut table = new_table();
+    table.add_row(Row::new(header.into_iter().map(Cell::new).collect()));
+    for summary in summaries {
+        let mut cells: Vec<String> = group_by
+            .iter()
+            .map(|group| {
+                let value = match group {
+                    UsageGroup::Day => &summary.day,
+                    UsageGroup::Model => &summary.model,
+                    UsageGroup::Key => &summary.key,
+                };
+                value.clone().unwrap_or_else(|| "-".to_string())
+            })
+            .collect();
+        cells.extend([
+            summary.requests.to_string(),
+            summary.errors.to_string(),
+            summary.prompt_tokens.to_string(),
+            summary.completion_tokens.to_string(),
+            format_latency(summary.avg_latency_ms),
+        ]);
+        table.add_row(Row::new(cells.iter().map(|cell| Cell::new(cell)).collect()));
+    }
+
+    table.printstd();
+}
+
+/// Display the latest requests, newest first
+pub fn display_recent(entries: &[AuditEntry]) {
+    let mut table = new_table();
+    table.add_row(Row::new(
+        [
+            "TIME", "METHOD", "PATH", "MODEL", "KEY", "TOKENS", "LATENCY", "STATUS", "FINISH",
+        ]
+        .into_iter()
+        .map(Cell::new)
+        .collect(),
+    ));
+    for entry in entries {
+        let cells = [
+            format_time_ago(&entry.timestamp),
+            entry.method.clone(),
+            entry.path.clone(),
+            entry.model.clone().unwrap_or_else(|| "-".to_string()),
+            entry.key_name.clone().unwrap_or_else(|| "-".to_string()),
+            format!("{}/{}", entry.prompt_tokens, entry.completion_tokens),
+            format_latency(entry.latency_ms),
+            entry.status.to_string(),
+            entry
+                .finish_reason
+                .clone()
+                .unwrap_or_else(|| "-".to_string()),
+        ];
+        table.add_row(Row::new(cells.iter().map(|cell| Cell::new(cell)).collect()));
+    }
+
+    table.printstd();
+}
+
+/// Latency for listings, e.g. `850ms` or `2.4s`
+fn format_latency(ms: f64) -> String {
+    if ms < 1000.0 {
+        format!("{:.0}ms", ms)
+    } else {
+        format!("{:.1}s", ms / 1000.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::SqliteStorage;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_usage_summary() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = SqliteStorage::new(temp_dir.path().join("test.db")).unwrap();
+
+        let entry = |timestamp: String| AuditEntry {
+            timestamp,
+            method: "POST".to_string(),
+            path: "/v1/completions".to_string(),
+            model: Some("test-model".to_string()),
+            prompt_tokens: 5,
+            completion_tokens: 20,
+            status: 200,
+            ..Default::default()
+        };
+        storage
+            .record_request(entry(Utc::now().to_rfc3339()))
+            .unwrap();
+        storage
+            .record_request(entry((Utc::now() - Duration::days(3)).to_rfc3339()))
+            .unwrap();
+
+        let today = execute_summary(&storage, &[UsageGroup::Model], 1).unwrap();
+        assert_eq!(today.len(), 1);
+        assert_eq!(today[0].requests, 1);
+
+        let week = execute_summary(&storage, &[UsageGroup::Day], 7).unwrap();
+        assert_eq!(week.len(), 2);
+        assert_eq!(week[1].day.as_deref(), Some(since_day(1).as_str()));
+
+        assert_eq!(execute_recent(&storage, 10).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_format_latency() {
+        assert_eq!(format_latency(850.4), "850ms");
+        assert_eq!(format_latency(2400.0), "2.4s");
+    }
+}
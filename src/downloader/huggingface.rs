@@ -1,11 +1,12 @@
-use colored::Colorize;
+use std::sync::Arc;
 use tracing::debug;
 
 use hf_hub::api::tokio::{ApiBuilder, Progress};
-use indicatif::{ProgressBar, ProgressStyle};
 
 use crate::downloader::downloader::{DownloadError, Downloader};
-use crate::downloader::progress::{DownloadProgressManager, FileProgress};
+use crate::downloader::progress::{
+    DownloadEvent, DownloadObserver, DownloadProgress, FileProgress, ManifestFile,
+};
 use crate::registry::model_registry::{CacheInfo, ModelInfo, ModelMetadata, ModelRegistry};
 use crate::utils::file::{self, format_model_name};
 
@@ -29,11 +30,22 @@ impl Progress for HfProgressAdapter {
     }
 }
 
-pub struct HuggingFaceDownloader;
+pub struct HuggingFaceDownloader {
+    observer: Arc<dyn DownloadObserver>,
+}
 
 impl HuggingFaceDownloader {
+    /// Downloader reporting no progress
     pub fn new() -> Self {
-        Self
+        Self {
+            observer: Arc::new(|_: &DownloadEvent| {}),
+        }
+    }
+
+    /// Report the progress of downloads to `observer`
+    pub fn with_observer(mut self, observer: Arc<dyn DownloadObserver>) -> Self {
+        self.observer = observer;
+        self
     }
 
     async fn fetch_metadata_from_api(
@@ -114,15 +126,10 @@ impl Downloader for HuggingFaceDownloader {
                 DownloadError::ApiError(format!("Failed to initialize Hugging Face API: {}", e))
             })?;
 
-        // Create a simple spinner for manifest pulling
-        let manifest_spinner = ProgressBar::new_spinner();
-        manifest_spinner.set_style(
-            ProgressStyle::default_spinner()
-                .tick_chars("⠋⠙⠹⠸⠼⠴⠦⠧⠇⠏")
-                .template("pulling manifest {spinner:.white}")
-                .unwrap(),
-        );
-        manifest_spinner.enable_steady_tick(std::time::Duration::from_millis(80));
+        let progress = DownloadProgress::new(self.observer.clone());
+        progress.report(DownloadEvent::PullingManifest {
+            model: name.to_string(),
+        });
 
         // Download the entire model repository using snapshot download
         let repo = api.model(name.to_string());
@@ -141,25 +148,8 @@ impl Downloader for HuggingFaceDownloader {
             }
         })?;
 
-        // Stop manifest spinner and print clean message
-        manifest_spinner.finish_and_clear();
-        println!("pulling manifest");
-
         debug!("Model info for {}: {:?}", name, model_info);
 
-        // Calculate the longest filename for proper alignment
-        let max_filename_len = model_info
-            .siblings
-            .iter()
-            .map(|s| s.rfilename.len())
-            .max()
-            .unwrap_or(30);
-
-        // Add extra space for "pulling " prefix
-        let max_filename_len = max_filename_len + 8;
-        // Create progress manager
-        let progress_manager = DownloadProgressManager::new(max_filename_len);
-
         // Calculate cache paths
         let model_cache_path = cache_dir.join(format_model_name(name));
         let sha = model_info.sha.clone();
@@ -170,6 +160,17 @@ impl Downloader for HuggingFaceDownloader {
             .siblings
             .iter()
             .all(|sibling| snapshot_path.join(&sibling.rfilename).exists());
+        progress.report(DownloadEvent::Manifest {
+            model: name.to_string(),
+            files: model_info
+                .siblings
+                .iter()
+                .map(|sibling| ManifestFile {
+                    name: sibling.rfilename.clone(),
+                    cached: snapshot_path.join(&sibling.rfilename).exists(),
+                })
+                .collect(),
+        });
 
         // Process all files in manifest order (cached files show as instantly complete)
         let mut tasks = Vec::new();
@@ -178,7 +179,7 @@ impl Downloader for HuggingFaceDownloader {
             let api_clone = api.clone();
             let model_name = name.to_string();
             let filename = sibling.rfilename.clone();
-            let progress_manager_clone = progress_manager.clone();
+            let progress = progress.clone();
             let snapshot_path_clone = snapshot_path.clone();
 
             let task = tokio::spawn(async move {
@@ -189,13 +190,9 @@ impl Downloader for HuggingFaceDownloader {
                 if cached_file_path.exists() {
                     debug!("File {} found in cache, showing as complete", filename);
 
-                    // Create progress bar for cached file (no speed display)
-                    let display_name = format!("pulling {}", filename);
-                    let mut file_progress =
-                        progress_manager_clone.create_cached_file_progress(&display_name);
+                    let mut file_progress = progress.file(&filename);
                     let file_size = cached_file_path.metadata().map(|m| m.len()).unwrap_or(0);
                     file_progress.init(file_size);
-                    file_progress.update(file_size);
                     file_progress.finish();
 
                     return Ok(());
@@ -203,13 +200,11 @@ impl Downloader for HuggingFaceDownloader {
 
                 // File not in cache, download with progress
                 debug!("Downloading: {}", filename);
-                let display_name = format!("pulling {}", filename);
-                let file_progress = progress_manager_clone.create_file_progress(&display_name);
-                let progress = HfProgressAdapter {
-                    progress: file_progress,
+                let adapter = HfProgressAdapter {
+                    progress: progress.file(&filename),
                 };
 
-                repo.download_with_progress(&filename, progress)
+                repo.download_with_progress(&filename, adapter)
                     .await
                     .map_err(|e| {
                         DownloadError::NetworkError(format!(
@@ -224,27 +219,12 @@ impl Downloader for HuggingFaceDownloader {
             tasks.push(task);
         }
 
-        // Give tasks a moment to start and create their progress bars
-        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
-
-        // Show spinner at the bottom after all progress bars are created (only if not fully cached)
-        let spinner = if !model_totally_cached {
-            Some(progress_manager.create_spinner())
-        } else {
-            None
-        };
-
         // Wait for all downloads to complete
         for task in tasks {
             task.await
                 .map_err(|e| DownloadError::ApiError(format!("Task join error: {}", e)))??;
         }
 
-        // Finish spinner after downloads complete
-        if let Some(spinner) = &spinner {
-            spinner.finish_and_clear();
-        }
-
         let elapsed_time = start_time.elapsed();
         let model_cache_path = cache_dir.join(format_model_name(name));
 
@@ -281,8 +261,7 @@ impl Downloader for HuggingFaceDownloader {
             };
 
             // Use storage from API, fallback to accumulated download size
-            let model_size =
-                storage_from_api.unwrap_or_else(|| progress_manager.total_downloaded_bytes());
+            let model_size = storage_from_api.unwrap_or_else(|| progress.total_downloaded_bytes());
 
             let cache = CacheInfo {
                 revision: sha.clone(),
@@ -316,15 +295,10 @@ impl Downloader for HuggingFaceDownloader {
                 .map_err(|e| DownloadError::ApiError(format!("Failed to register model: {}", e)))?;
         }
 
-        // Print success message
-        println!(
-            "{} {} {} {} {:.2?}",
-            "✓".green().bold(),
-            "Successfully downloaded model".bright_white(),
-            name.cyan().bold(),
-            "in".bright_white(),
-            elapsed_time
-        );
+        progress.report(DownloadEvent::Success {
+            model: name.to_string(),
+            elapsed_ms: elapsed_time.as_millis() as u64,
+        });
 
         Ok(())
     }
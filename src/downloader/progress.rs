@@ -1,146 +1,397 @@
+use colored::Colorize;
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use serde::Serialize;
+use std::collections::HashMap;
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
 
-/// Manages multi-file download progress tracking
+/// Step of a model download, reported to a [`DownloadObserver`]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum DownloadEvent {
+    /// Fetching the list of files of the model
+    PullingManifest { model: String },
+    /// The files of the model, in manifest order
+    Manifest {
+        model: String,
+        files: Vec<ManifestFile>,
+    },
+    /// Bytes of a file downloaded so far
+    Downloading {
+        file: String,
+        completed: u64,
+        total: u64,
+    },
+    /// A file is complete on disk, downloaded or found in the cache
+    Downloaded { file: String, total: u64 },
+    /// Every file is on disk and the model is registered
+    Success { model: String, elapsed_ms: u64 },
+}
+
+/// File listed in a model's manifest
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ManifestFile {
+    pub name: String,
+    /// Already in the cache, not downloaded again
+    pub cached: bool,
+}
+
+/// Receives the progress of model downloads
+pub trait DownloadObserver: Send + Sync {
+    fn observe(&self, event: &DownloadEvent);
+}
+
+impl<F: Fn(&DownloadEvent) + Send + Sync> DownloadObserver for F {
+    fn observe(&self, event: &DownloadEvent) {
+        self(event)
+    }
+}
+
+/// Progress of one model download, shared by its file downloads
 ///
 /// # Example
 /// ```rust
-/// use puma::downloader::progress::DownloadProgressManager;
+/// use puma::downloader::progress::{DownloadEvent, DownloadProgress};
+/// use std::sync::Arc;
 ///
-/// let progress_manager = DownloadProgressManager::new(30);
-/// let mut file_progress = progress_manager.create_file_progress("model.bin");
+/// let progress = DownloadProgress::new(Arc::new(|event: &DownloadEvent| println!("{:?}", event)));
+/// let mut file_progress = progress.file("model.bin");
 ///
 /// file_progress.init(1024 * 1024); // 1 MB
 /// file_progress.update(512 * 1024); // Downloaded 512 KB
 /// file_progress.finish();
 ///
-/// let total = progress_manager.total_downloaded_bytes();
+/// let total = progress.total_downloaded_bytes();
 /// ```
 #[derive(Clone)]
-pub struct DownloadProgressManager {
-    multi_progress: Arc<MultiProgress>,
+pub struct DownloadProgress {
+    observer: Arc<dyn DownloadObserver>,
     total_size: Arc<AtomicU64>,
-    style: ProgressStyle,
-    cached_style: ProgressStyle,
 }
 
-impl DownloadProgressManager {
-    /// Create a new progress manager with aligned file names
-    pub fn new(max_filename_len: usize) -> Self {
-        let multi_progress = Arc::new(MultiProgress::new());
-
-        let template = format!(
-            "{{msg:<{width}}} [{{elapsed_precise}}] {{bar:60.white}} {{bytes}}/{{total_bytes}} {{bytes_per_sec}}",
-            width = max_filename_len
-        );
-        let style = ProgressStyle::default_bar()
-            .template(&template)
-            .unwrap()
-            .progress_chars("▇▆▅▄▃▂▁ ");
-
-        // Cached file style without speed
-        let cached_template = format!(
-            "{{msg:<{width}}} [{{elapsed_precise}}] {{bar:60.white}} {{bytes}}/{{total_bytes}}",
-            width = max_filename_len
-        );
-        let cached_style = ProgressStyle::default_bar()
-            .template(&cached_template)
-            .unwrap()
-            .progress_chars("▇▆▅▄▃▂▁ ");
-
+impl DownloadProgress {
+    pub fn new(observer: Arc<dyn DownloadObserver>) -> Self {
         Self {
-            multi_progress,
+            observer,
             total_size: Arc::new(AtomicU64::new(0)),
-            style,
-            cached_style,
         }
     }
 
-    /// Create a new progress bar for a file download
-    pub fn create_file_progress(&self, filename: &str) -> FileProgress {
-        let pb = self.multi_progress.add(ProgressBar::hidden());
-        pb.set_style(self.style.clone());
-        pb.set_message(filename.to_string());
-
-        FileProgress {
-            pb,
-            total_size: Arc::clone(&self.total_size),
-        }
+    /// Report a step of the download
+    pub fn report(&self, event: DownloadEvent) {
+        self.observer.observe(&event);
     }
 
-    /// Create a new progress bar for a cached file (no speed display)
-    pub fn create_cached_file_progress(&self, filename: &str) -> FileProgress {
-        let pb = self.multi_progress.add(ProgressBar::hidden());
-        pb.set_style(self.cached_style.clone());
-        pb.set_message(filename.to_string());
-
+    /// Track the progress of one file
+    pub fn file(&self, name: &str) -> FileProgress {
         FileProgress {
-            pb,
-            total_size: Arc::clone(&self.total_size),
+            name: name.to_string(),
+            completed: 0,
+            total: 0,
+            progress: self.clone(),
         }
     }
 
-    /// Get the total accumulated download size
+    /// Get the total size of the files, downloaded or cached
     pub fn total_downloaded_bytes(&self) -> u64 {
         self.total_size.load(Ordering::Relaxed)
     }
-
-    /// Create a spinner progress bar (for post-download operations)
-    pub fn create_spinner(&self) -> ProgressBar {
-        let pb = self.multi_progress.add(ProgressBar::new_spinner());
-        pb.set_style(
-            ProgressStyle::default_spinner()
-                .tick_chars("⠋⠙⠹⠸⠼⠴⠦⠧⠇⠏")
-                .template("{spinner} ")
-                .unwrap(),
-        );
-        pb.enable_steady_tick(std::time::Duration::from_millis(80));
-        pb
-    }
 }
 
 /// Tracks progress for a single file download
 #[derive(Clone)]
 pub struct FileProgress {
-    pb: ProgressBar,
-    total_size: Arc<AtomicU64>,
+    name: String,
+    completed: u64,
+    total: u64,
+    progress: DownloadProgress,
 }
 
 impl FileProgress {
-    /// Initialize progress bar with file size
+    /// Start the file with its size
     pub fn init(&mut self, size: u64) {
-        self.pb.set_length(size);
-        self.pb.reset();
-        self.pb.tick();
-        self.total_size.fetch_add(size, Ordering::Relaxed);
+        self.completed = 0;
+        self.total = size;
+        self.progress.total_size.fetch_add(size, Ordering::Relaxed);
+        self.report_downloading();
     }
 
     /// Update progress with downloaded bytes
     pub fn update(&mut self, bytes: u64) {
-        self.pb.inc(bytes);
+        self.completed += bytes;
+        self.report_downloading();
     }
 
     /// Mark download as complete
     pub fn finish(&mut self) {
-        self.pb.finish();
+        self.progress.report(DownloadEvent::Downloaded {
+            file: self.name.clone(),
+            total: self.total,
+        });
+    }
+
+    fn report_downloading(&self) {
+        self.progress.report(DownloadEvent::Downloading {
+            file: self.name.clone(),
+            completed: self.completed,
+            total: self.total,
+        });
+    }
+}
+
+/// Renders download progress in the terminal, a progress bar per file
+pub struct TerminalProgress {
+    multi_progress: MultiProgress,
+    state: Mutex<TerminalState>,
+}
+
+#[derive(Default)]
+struct TerminalState {
+    manifest_spinner: Option<ProgressBar>,
+    bars: HashMap<String, ProgressBar>,
+    spinner: Option<ProgressBar>,
+}
+
+impl TerminalProgress {
+    pub fn new() -> Self {
+        Self {
+            multi_progress: MultiProgress::new(),
+            state: Mutex::new(TerminalState::default()),
+        }
+    }
+
+    /// Bar style with aligned file names, cached files show no speed
+    fn bar_style(max_filename_len: usize, cached: bool) -> ProgressStyle {
+        let speed = if cached { "" } else { " {bytes_per_sec}" };
+        let template = format!(
+            "{{msg:<{width}}} [{{elapsed_precise}}] {{bar:60.white}} {{bytes}}/{{total_bytes}}{speed}",
+            width = max_filename_len
+        );
+        ProgressStyle::default_bar()
+            .template(&template)
+            .unwrap()
+            .progress_chars("▇▆▅▄▃▂▁ ")
+    }
+
+    fn spinner(template: &str) -> ProgressBar {
+        let pb = ProgressBar::new_spinner();
+        pb.set_style(
+            ProgressStyle::default_spinner()
+                .tick_chars("⠋⠙⠹⠸⠼⠴⠦⠧⠇⠏")
+                .template(template)
+                .unwrap(),
+        );
+        pb.enable_steady_tick(Duration::from_millis(80));
+        pb
+    }
+}
+
+impl Default for TerminalProgress {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DownloadObserver for TerminalProgress {
+    fn observe(&self, event: &DownloadEvent) {
+        let mut state = self.state.lock().unwrap();
+        match event {
+            DownloadEvent::PullingManifest { .. } => {
+                state.manifest_spinner = Some(Self::spinner("pulling manifest {spinner:.white}"));
+            }
+            DownloadEvent::Manifest { files, .. } => {
+                if let Some(spinner) = state.manifest_spinner.take() {
+                    spinner.finish_and_clear();
+                }
+                println!("pulling manifest");
+
+                // Add extra space for "pulling " prefix
+                let max_filename_len = files.iter().map(|f| f.name.len()).max().unwrap_or(30) + 8;
+                for file in files {
+                    let pb = self.multi_progress.add(ProgressBar::hidden());
+                    pb.set_style(Self::bar_style(max_filename_len, file.cached));
+                    pb.set_message(format!("pulling {}", file.name));
+                    state.bars.insert(file.name.clone(), pb);
+                }
+
+                // Spinner at the bottom while files download
+                if files.iter().any(|f| !f.cached) {
+                    state.spinner = Some(self.multi_progress.add(Self::spinner("{spinner} ")));
+                }
+            }
+            DownloadEvent::Downloading {
+                file,
+                completed,
+                total,
+            } => {
+                if let Some(pb) = state.bars.get(file) {
+                    if pb.length() != Some(*total) {
+                        pb.set_length(*total);
+                        pb.reset();
+                    }
+                    pb.set_position(*completed);
+                }
+            }
+            DownloadEvent::Downloaded { file, total } => {
+                if let Some(pb) = state.bars.get(file) {
+                    pb.set_length(*total);
+                    pb.set_position(*total);
+                    pb.finish();
+                }
+            }
+            DownloadEvent::Success { model, elapsed_ms } => {
+                if let Some(spinner) = state.spinner.take() {
+                    spinner.finish_and_clear();
+                }
+                println!(
+                    "{} {} {} {} {:.2?}",
+                    "✓".green().bold(),
+                    "Successfully downloaded model".bright_white(),
+                    model.cyan().bold(),
+                    "in".bright_white(),
+                    Duration::from_millis(*elapsed_ms)
+                );
+            }
+        }
+    }
+}
+
+/// Forwards download events to a channel, for streaming them to API clients.
+/// Progress of a file is forwarded at most once per `interval`, the start and
+/// end of every file always are.
+pub struct ChannelObserver {
+    tx: mpsc::UnboundedSender<DownloadEvent>,
+    interval: Duration,
+    last_sent: Mutex<HashMap<String, Instant>>,
+}
+
+impl ChannelObserver {
+    pub fn new(tx: mpsc::UnboundedSender<DownloadEvent>, interval: Duration) -> Self {
+        Self {
+            tx,
+            interval,
+            last_sent: Mutex::new(HashMap::new()),
+        }
     }
+}
+
+impl DownloadObserver for ChannelObserver {
+    fn observe(&self, event: &DownloadEvent) {
+        if let DownloadEvent::Downloading {
+            file, completed, ..
+        } = event
+        {
+            let mut last_sent = self.last_sent.lock().unwrap();
+            let now = Instant::now();
+            let due = last_sent
+                .get(file)
+                .is_none_or(|sent| now.duration_since(*sent) >= self.interval);
+            if *completed > 0 && !due {
+                return;
+            }
+            last_sent.insert(file.clone(), now);
+        }
 
-    /// Mark download as failed
-    #[allow(dead_code)]
-    pub fn abandon(&mut self) {
-        self.pb.abandon();
+        // The receiver is gone once the client disconnects, the download goes on
+        let _ = self.tx.send(event.clone());
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    /// Get the inner progress bar (for provider-specific adapters)
-    #[allow(dead_code)]
-    pub fn progress_bar(&self) -> &ProgressBar {
-        &self.pb
+    fn collect() -> (Arc<Mutex<Vec<DownloadEvent>>>, DownloadProgress) {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let observer = {
+            let events = events.clone();
+            move |event: &DownloadEvent| events.lock().unwrap().push(event.clone())
+        };
+        (events, DownloadProgress::new(Arc::new(observer)))
     }
 
-    /// Get the total size tracker (for provider-specific adapters)
-    #[allow(dead_code)]
-    pub fn total_size_tracker(&self) -> Arc<AtomicU64> {
-        Arc::clone(&self.total_size)
+    #[test]
+    fn test_file_progress_events() {
+        let (events, progress) = collect();
+        let mut file = progress.file("model.safetensors");
+        file.init(100);
+        file.update(40);
+        file.update(60);
+        file.finish();
+        progress.file("config.json").init(10);
+
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 5);
+        assert_eq!(
+            events[2],
+            DownloadEvent::Downloading {
+                file: "model.safetensors".to_string(),
+                completed: 100,
+                total: 100,
+            }
+        );
+        assert_eq!(
+            events[3],
+            DownloadEvent::Downloaded {
+                file: "model.safetensors".to_string(),
+                total: 100,
+            }
+        );
+        assert_eq!(progress.total_downloaded_bytes(), 110);
+    }
+
+    #[test]
+    fn test_download_event_json() {
+        let event = DownloadEvent::Downloading {
+            file: "config.json".to_string(),
+            completed: 5,
+            total: 10,
+        };
+        assert_eq!(
+            serde_json::to_value(&event).unwrap(),
+            serde_json::json!({
+                "status": "downloading",
+                "file": "config.json",
+                "completed": 5,
+                "total": 10
+            })
+        );
+
+        let event = DownloadEvent::Manifest {
+            model: "inftyai/tiny-random-gpt2".to_string(),
+            files: vec![ManifestFile {
+                name: "config.json".to_string(),
+                cached: true,
+            }],
+        };
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["status"], "manifest");
+        assert_eq!(json["files"][0]["cached"], true);
+    }
+
+    #[test]
+    fn test_channel_observer_throttles_progress() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let progress =
+            DownloadProgress::new(Arc::new(ChannelObserver::new(tx, Duration::from_secs(60))));
+
+        let mut file = progress.file("model.safetensors");
+        file.init(100);
+        file.update(10);
+        file.update(10);
+        file.finish();
+        progress.report(DownloadEvent::Success {
+            model: "test".to_string(),
+            elapsed_ms: 1,
+        });
+
+        // The start is forwarded, progress within the interval is not
+        let mut statuses = Vec::new();
+        while let Ok(event) = rx.try_recv() {
+            statuses.push(serde_json::to_value(&event).unwrap()["status"].clone());
+        }
+        assert_eq!(statuses, ["downloading", "downloaded", "success"]);
     }
 }
@@ -2,7 +2,7 @@ use colored::Colorize;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use crate::storage::{ModelStorage, SqliteStorage};
 use crate::utils::file;
@@ -40,8 +40,25 @@ pub struct ModelInfo {
     pub updated_at: String,
 }
 
+/// A LoRA adapter registered on top of a local base model
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AdapterInfo {
+    pub name: String,
+    pub base_model: String,
+    pub path: String,
+    pub created_at: String,
+}
+
+/// A model name resolved to the base model to run and the adapter (if any) to apply
+#[derive(Debug, Clone)]
+pub struct ResolvedModel {
+    pub base: ModelInfo,
+    pub adapter: Option<AdapterInfo>,
+}
+
 pub struct ModelRegistry {
     storage: Box<dyn ModelStorage>,
+    db_path: PathBuf,
 }
 
 impl ModelRegistry {
@@ -50,13 +67,19 @@ impl ModelRegistry {
         fs::create_dir_all(&home_dir).ok();
 
         let db_path = home_dir.join("models.db");
-        let storage = SqliteStorage::new(db_path).expect("Failed to initialize storage");
+        let storage = SqliteStorage::new(db_path.clone()).expect("Failed to initialize storage");
 
         Self {
             storage: Box::new(storage),
+            db_path,
         }
     }
 
+    /// Path of the SQLite database shared with the API server's other stores
+    pub fn db_path(&self) -> &Path {
+        &self.db_path
+    }
+
     pub fn load_models(
         &self,
         filters: Option<&HashMap<String, String>>,
@@ -76,6 +99,69 @@ impl ModelRegistry {
         self.storage.get_model(name)
     }
 
+    /// Register a LoRA adapter. The base model must already be registered
+    /// and the adapter name must not shadow a model.
+    pub fn register_adapter(&self, adapter: AdapterInfo) -> Result<(), std::io::Error> {
+        if self.get_model(&adapter.name)?.is_some() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::AlreadyExists,
+                format!("A model named '{}' already exists", adapter.name),
+            ));
+        }
+
+        if self.get_model(&adapter.base_model)?.is_none() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("Base model not found: {}", adapter.base_model),
+            ));
+        }
+
+        self.storage.register_adapter(adapter)
+    }
+
+    pub fn unregister_adapter(&self, name: &str) -> Result<(), std::io::Error> {
+        self.storage.unregister_adapter(name)
+    }
+
+    pub fn get_adapter(&self, name: &str) -> Result<Option<AdapterInfo>, std::io::Error> {
+        self.storage.get_adapter(name)
+    }
+
+    pub fn load_adapters(
+        &self,
+        base_model: Option<&str>,
+    ) -> Result<Vec<AdapterInfo>, std::io::Error> {
+        self.storage.load_adapters(base_model)
+    }
+
+    /// Resolve a model or adapter name to the base model it runs on
+    pub fn resolve_model(&self, name: &str) -> Result<Option<ResolvedModel>, std::io::Error> {
+        if let Some(model) = self.get_model(name)? {
+            return Ok(Some(ResolvedModel {
+                base: model,
+                adapter: None,
+            }));
+        }
+
+        let Some(adapter) = self.get_adapter(name)? else {
+            return Ok(None);
+        };
+
+        match self.get_model(&adapter.base_model)? {
+            Some(base) => Ok(Some(ResolvedModel {
+                base,
+                adapter: Some(adapter),
+            })),
+            None => Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!(
+                    "Base model '{}' of adapter '{}' not found",
+                    adapter.base_model, adapter.name
+                ),
+            )),
+        }
+    }
+
     pub fn remove_model(&self, name: &str) -> Result<(), std::io::Error> {
         // Get model info first
         let model_info = self.get_model(name)?;
@@ -87,7 +173,11 @@ impl ModelRegistry {
                 fs::remove_dir_all(cache_path)?;
             }
 
-            // Remove from registry
+            // Remove from registry, along with the adapters built on it.
+            // Adapter files are user-provided, so they are left on disk.
+            for adapter in self.load_adapters(Some(name))? {
+                self.unregister_adapter(&adapter.name)?;
+            }
             self.unregister_model(name)?;
 
             println!(
@@ -256,6 +346,82 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    fn create_test_adapter(name: &str, base_model: &str) -> AdapterInfo {
+        AdapterInfo {
+            name: name.to_string(),
+            base_model: base_model.to_string(),
+            path: "/tmp/adapter".to_string(),
+            created_at: "2025-01-01T00:00:00Z".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_register_and_resolve_adapter() {
+        let temp_dir = TempDir::new().unwrap();
+        let registry = ModelRegistry::new(Some(temp_dir.path().to_path_buf()));
+
+        registry
+            .register_model(create_test_model("test/base", "abc123"))
+            .unwrap();
+        registry
+            .register_adapter(create_test_adapter("test/sql-lora", "test/base"))
+            .unwrap();
+        registry
+            .register_adapter(create_test_adapter("test/chat-lora", "test/base"))
+            .unwrap();
+
+        // Adapters share the same base model
+        let resolved = registry.resolve_model("test/sql-lora").unwrap().unwrap();
+        assert_eq!(resolved.base.name, "test/base");
+        assert_eq!(resolved.adapter.unwrap().name, "test/sql-lora");
+        assert_eq!(registry.load_adapters(Some("test/base")).unwrap().len(), 2);
+
+        // Plain models resolve to themselves
+        let resolved = registry.resolve_model("test/base").unwrap().unwrap();
+        assert_eq!(resolved.base.name, "test/base");
+        assert!(resolved.adapter.is_none());
+
+        assert!(registry.resolve_model("nonexistent").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_register_adapter_validation() {
+        let temp_dir = TempDir::new().unwrap();
+        let registry = ModelRegistry::new(Some(temp_dir.path().to_path_buf()));
+
+        registry
+            .register_model(create_test_model("test/base", "abc123"))
+            .unwrap();
+
+        // Base model must exist
+        let err = registry
+            .register_adapter(create_test_adapter("test/lora", "test/missing"))
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+
+        // Adapter cannot shadow a model
+        let err = registry
+            .register_adapter(create_test_adapter("test/base", "test/base"))
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::AlreadyExists);
+    }
+
+    #[test]
+    fn test_remove_model_unregisters_adapters() {
+        let temp_dir = TempDir::new().unwrap();
+        let registry = ModelRegistry::new(Some(temp_dir.path().to_path_buf()));
+
+        let mut model = create_test_model("test/base", "abc123");
+        model.metadata.cache.path = temp_dir.path().join("cache").display().to_string();
+        registry.register_model(model).unwrap();
+        registry
+            .register_adapter(create_test_adapter("test/lora", "test/base"))
+            .unwrap();
+
+        registry.remove_model("test/base").unwrap();
+        assert!(registry.get_adapter("test/lora").unwrap().is_none());
+    }
+
     #[test]
     fn test_inspect_model_with_full_spec() {
         let temp_dir = TempDir::new().unwrap();
@@ -2,4 +2,7 @@ pub mod sqlite;
 pub mod storage_trait;
 
 pub use sqlite::SqliteStorage;
-pub use storage_trait::ModelStorage;
+pub use storage_trait::{
+    ApiKeyStorage, AuditEntry, AuditStorage, ModelStorage, ResponseStorage, StoredApiKey,
+    StoredResponse, UsageGroup, UsageSummary,
+};
@@ -1,5 +1,8 @@
-use crate::registry::model_registry::{ModelInfo, ModelMetadata};
-use crate::storage::ModelStorage;
+use crate::registry::model_registry::{AdapterInfo, ModelInfo, ModelMetadata};
+use crate::storage::{
+    ApiKeyStorage, AuditEntry, AuditStorage, ModelStorage, ResponseStorage, StoredApiKey,
+    StoredResponse, UsageGroup, UsageSummary,
+};
 use rusqlite::{params, Connection, Result as SqlResult};
 use rusqlite_migration::{Migrations, M};
 use std::collections::HashMap;
@@ -42,6 +45,68 @@ impl SqliteStorage {
                 CREATE INDEX idx_license ON models(license);
                 CREATE INDEX idx_created_at ON models(created_at);",
             ),
+            M::up(
+                "CREATE TABLE adapters (
+                    name TEXT PRIMARY KEY,
+                    base_model TEXT NOT NULL,
+                    path TEXT NOT NULL,
+                    created_at TEXT NOT NULL
+                );
+                CREATE INDEX idx_adapters_base_model ON adapters(base_model);",
+            ),
+            M::up(
+                "CREATE TABLE responses (
+                    id TEXT PRIMARY KEY,
+                    previous_response_id TEXT,
+                    model TEXT NOT NULL,
+                    input JSON NOT NULL,
+                    response JSON NOT NULL,
+                    created_at TEXT NOT NULL,
+                    CHECK(json_valid(input)),
+                    CHECK(json_valid(response))
+                );",
+            ),
+            M::up(
+                "CREATE TABLE api_keys (
+                    id TEXT PRIMARY KEY,
+                    name TEXT NOT NULL UNIQUE,
+                    key_hash TEXT NOT NULL UNIQUE,
+                    prefix TEXT NOT NULL,
+                    created_at TEXT NOT NULL,
+                    revoked_at TEXT
+                );",
+            ),
+            M::up(
+                "ALTER TABLE api_keys ADD COLUMN requests_per_minute INTEGER;
+                ALTER TABLE api_keys ADD COLUMN tokens_per_minute INTEGER;
+                ALTER TABLE api_keys ADD COLUMN daily_token_quota INTEGER;
+                CREATE TABLE api_key_daily_usage (
+                    key_id TEXT NOT NULL,
+                    day TEXT NOT NULL,
+                    tokens INTEGER NOT NULL,
+                    PRIMARY KEY (key_id, day)
+                );",
+            ),
+            M::up(
+                "CREATE TABLE audit_log (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    timestamp TEXT NOT NULL,
+                    method TEXT NOT NULL,
+                    path TEXT NOT NULL,
+                    model TEXT,
+                    key_id TEXT,
+                    key_name TEXT,
+                    prompt_tokens INTEGER NOT NULL,
+                    completion_tokens INTEGER NOT NULL,
+                    latency_ms REAL NOT NULL,
+                    status INTEGER NOT NULL,
+                    finish_reason TEXT,
+                    request_body TEXT,
+                    response_body TEXT
+                );
+                CREATE INDEX idx_audit_log_timestamp ON audit_log(timestamp);",
+            ),
+            M::up("ALTER TABLE responses ADD COLUMN key_id TEXT;"),
             // Future migrations go here
         ]);
 
@@ -220,6 +285,420 @@ impl ModelStorage for SqliteStorage {
             Err(e) => Err(io::Error::other(e)),
         }
     }
+
+    fn register_adapter(&self, adapter: AdapterInfo) -> Result<(), io::Error> {
+        let conn = self.get_connection()?;
+
+        // Normalize names to lowercase, same as models
+        conn.execute(
+            "INSERT INTO adapters (name, base_model, path, created_at)
+             VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(name) DO UPDATE SET
+                base_model = excluded.base_model,
+                path = excluded.path",
+            params![
+                adapter.name.to_lowercase(),
+                adapter.base_model.to_lowercase(),
+                &adapter.path,
+                &adapter.created_at,
+            ],
+        )
+        .map_err(io::Error::other)?;
+
+        Ok(())
+    }
+
+    fn unregister_adapter(&self, name: &str) -> Result<(), io::Error> {
+        let conn = self.get_connection()?;
+
+        conn.execute(
+            "DELETE FROM adapters WHERE name = ?1",
+            params![name.to_lowercase()],
+        )
+        .map_err(io::Error::other)?;
+
+        Ok(())
+    }
+
+    fn get_adapter(&self, name: &str) -> Result<Option<AdapterInfo>, io::Error> {
+        let conn = self.get_connection()?;
+
+        let result = conn.query_row(
+            "SELECT name, base_model, path, created_at FROM adapters WHERE name = ?1",
+            params![name.to_lowercase()],
+            |row| {
+                Ok(AdapterInfo {
+                    name: row.get(0)?,
+                    base_model: row.get(1)?,
+                    path: row.get(2)?,
+                    created_at: row.get(3)?,
+                })
+            },
+        );
+
+        match result {
+            Ok(adapter) => Ok(Some(adapter)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(io::Error::other(e)),
+        }
+    }
+
+    fn load_adapters(&self, base_model: Option<&str>) -> Result<Vec<AdapterInfo>, io::Error> {
+        let conn = self.get_connection()?;
+
+        let mut stmt = conn
+            .prepare(
+                "SELECT name, base_model, path, created_at FROM adapters
+                 WHERE ?1 IS NULL OR base_model = ?1
+                 ORDER BY name",
+            )
+            .map_err(io::Error::other)?;
+
+        let adapters = stmt
+            .query_map(params![base_model.map(|b| b.to_lowercase())], |row| {
+                Ok(AdapterInfo {
+                    name: row.get(0)?,
+                    base_model: row.get(1)?,
+                    path: row.get(2)?,
+                    created_at: row.get(3)?,
+                })
+            })
+            .map_err(io::Error::other)?
+            .collect::<SqlResult<Vec<_>>>()
+            .map_err(io::Error::other)?;
+
+        Ok(adapters)
+    }
+}
+
+impl ResponseStorage for SqliteStorage {
+    fn store_response(&self, response: StoredResponse) -> Result<(), io::Error> {
+        let conn = self.get_connection()?;
+
+        conn.execute(
+            "INSERT OR REPLACE INTO responses
+                (id, previous_response_id, model, input, response, created_at, key_id)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                &response.id,
+                &response.previous_response_id,
+                &response.model,
+                response.input.to_string(),
+                response.response.to_string(),
+                &response.created_at,
+                &response.key_id,
+            ],
+        )
+        .map_err(io::Error::other)?;
+
+        Ok(())
+    }
+
+    fn get_response(
+        &self,
+        id: &str,
+        key_id: Option<&str>,
+    ) -> Result<Option<StoredResponse>, io::Error> {
+        let conn = self.get_connection()?;
+
+        // `IS` matches NULL too, responses created without a key stay unscoped
+        let result = conn.query_row(
+            "SELECT id, previous_response_id, model, input, response, created_at, key_id
+             FROM responses WHERE id = ?1 AND key_id IS ?2",
+            params![id, key_id],
+            |row| {
+                let input: String = row.get(3)?;
+                let response: String = row.get(4)?;
+                Ok(StoredResponse {
+                    id: row.get(0)?,
+                    previous_response_id: row.get(1)?,
+                    model: row.get(2)?,
+                    input: serde_json::from_str(&input)
+                        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?,
+                    response: serde_json::from_str(&response)
+                        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?,
+                    created_at: row.get(5)?,
+                    key_id: row.get(6)?,
+                })
+            },
+        );
+
+        match result {
+            Ok(response) => Ok(Some(response)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(io::Error::other(e)),
+        }
+    }
+
+    fn delete_response(&self, id: &str, key_id: Option<&str>) -> Result<bool, io::Error> {
+        let conn = self.get_connection()?;
+
+        let deleted = conn
+            .execute(
+                "DELETE FROM responses WHERE id = ?1 AND key_id IS ?2",
+                params![id, key_id],
+            )
+            .map_err(io::Error::other)?;
+
+        Ok(deleted > 0)
+    }
+}
+
+impl ApiKeyStorage for SqliteStorage {
+    fn create_api_key(&self, key: StoredApiKey) -> Result<(), io::Error> {
+        let conn = self.get_connection()?;
+
+        conn.execute(
+            "INSERT INTO api_keys (id, name, key_hash, prefix, created_at, revoked_at,
+                requests_per_minute, tokens_per_minute, daily_token_quota)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                &key.id,
+                &key.name,
+                &key.key_hash,
+                &key.prefix,
+                &key.created_at,
+                &key.revoked_at,
+                &key.requests_per_minute,
+                &key.tokens_per_minute,
+                &key.daily_token_quota,
+            ],
+        )
+        .map_err(io::Error::other)?;
+
+        Ok(())
+    }
+
+    fn load_api_keys(&self) -> Result<Vec<StoredApiKey>, io::Error> {
+        let conn = self.get_connection()?;
+
+        let mut stmt = conn
+            .prepare(
+                "SELECT id, name, key_hash, prefix, created_at, revoked_at,
+                    requests_per_minute, tokens_per_minute, daily_token_quota
+                 FROM api_keys ORDER BY created_at",
+            )
+            .map_err(io::Error::other)?;
+        let keys = stmt
+            .query_map([], api_key_from_row)
+            .map_err(io::Error::other)?
+            .collect::<SqlResult<Vec<_>>>()
+            .map_err(io::Error::other)?;
+
+        Ok(keys)
+    }
+
+    fn find_api_key(&self, key_hash: &str) -> Result<Option<StoredApiKey>, io::Error> {
+        let conn = self.get_connection()?;
+
+        let result = conn.query_row(
+            "SELECT id, name, key_hash, prefix, created_at, revoked_at,
+                requests_per_minute, tokens_per_minute, daily_token_quota
+             FROM api_keys WHERE key_hash = ?1 AND revoked_at IS NULL",
+            params![key_hash],
+            api_key_from_row,
+        );
+
+        match result {
+            Ok(key) => Ok(Some(key)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(io::Error::other(e)),
+        }
+    }
+
+    fn has_active_api_keys(&self) -> Result<bool, io::Error> {
+        let conn = self.get_connection()?;
+
+        conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM api_keys WHERE revoked_at IS NULL)",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(io::Error::other)
+    }
+
+    fn revoke_api_key(&self, name_or_id: &str, revoked_at: &str) -> Result<bool, io::Error> {
+        let conn = self.get_connection()?;
+
+        let revoked = conn
+            .execute(
+                "UPDATE api_keys SET revoked_at = ?1
+                 WHERE (name = ?2 OR id = ?2) AND revoked_at IS NULL",
+                params![revoked_at, name_or_id],
+            )
+            .map_err(io::Error::other)?;
+
+        Ok(revoked > 0)
+    }
+
+    fn add_daily_tokens(&self, key_id: &str, day: &str, tokens: u64) -> Result<(), io::Error> {
+        let conn = self.get_connection()?;
+
+        conn.execute(
+            "INSERT INTO api_key_daily_usage (key_id, day, tokens) VALUES (?1, ?2, ?3)
+             ON CONFLICT (key_id, day) DO UPDATE SET tokens = tokens + excluded.tokens",
+            params![key_id, day, tokens],
+        )
+        .map_err(io::Error::other)?;
+
+        Ok(())
+    }
+
+    fn daily_tokens(&self, key_id: &str, day: &str) -> Result<u64, io::Error> {
+        let conn = self.get_connection()?;
+
+        let result = conn.query_row(
+            "SELECT tokens FROM api_key_daily_usage WHERE key_id = ?1 AND day = ?2",
+            params![key_id, day],
+            |row| row.get(0),
+        );
+
+        match result {
+            Ok(tokens) => Ok(tokens),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(0),
+            Err(e) => Err(io::Error::other(e)),
+        }
+    }
+}
+
+impl AuditStorage for SqliteStorage {
+    fn record_request(&self, entry: AuditEntry) -> Result<(), io::Error> {
+        let conn = self.get_connection()?;
+
+        conn.execute(
+            "INSERT INTO audit_log (timestamp, method, path, model, key_id, key_name,
+                prompt_tokens, completion_tokens, latency_ms, status, finish_reason,
+                request_body, response_body)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+            params![
+                &entry.timestamp,
+                &entry.method,
+                &entry.path,
+                &entry.model,
+                &entry.key_id,
+                &entry.key_name,
+                &entry.prompt_tokens,
+                &entry.completion_tokens,
+                &entry.latency_ms,
+                &entry.status,
+                &entry.finish_reason,
+                &entry.request_body,
+                &entry.response_body,
+            ],
+        )
+        .map_err(io::Error::other)?;
+
+        Ok(())
+    }
+
+    fn recent_requests(&self, limit: usize) -> Result<Vec<AuditEntry>, io::Error> {
+        let conn = self.get_connection()?;
+
+        let mut stmt = conn
+            .prepare(
+                "SELECT timestamp, method, path, model, key_id, key_name, prompt_tokens,
+                    completion_tokens, latency_ms, status, finish_reason, request_body,
+                    response_body
+                 FROM audit_log ORDER BY id DESC LIMIT ?1",
+            )
+            .map_err(io::Error::other)?;
+        let entries = stmt
+            .query_map(params![limit as i64], |row| {
+                Ok(AuditEntry {
+                    timestamp: row.get(0)?,
+                    method: row.get(1)?,
+                    path: row.get(2)?,
+                    model: row.get(3)?,
+                    key_id: row.get(4)?,
+                    key_name: row.get(5)?,
+                    prompt_tokens: row.get(6)?,
+                    completion_tokens: row.get(7)?,
+                    latency_ms: row.get(8)?,
+                    status: row.get(9)?,
+                    finish_reason: row.get(10)?,
+                    request_body: row.get(11)?,
+                    response_body: row.get(12)?,
+                })
+            })
+            .map_err(io::Error::other)?
+            .collect::<SqlResult<Vec<_>>>()
+            .map_err(io::Error::other)?;
+
+        Ok(entries)
+    }
+
+    fn summarize_usage(
+        &self,
+        group_by: &[UsageGroup],
+        since: Option<&str>,
+    ) -> Result<Vec<UsageSummary>, io::Error> {
+        let conn = self.get_connection()?;
+
+        // Every grouped column is selected, the others are NULL
+        let column = |group: UsageGroup| match group {
+            UsageGroup::Day => "substr(timestamp, 1, 10)",
+            UsageGroup::Model => "model",
+            UsageGroup::Key => "key_name",
+        };
+        let selected = [UsageGroup::Day, UsageGroup::Model, UsageGroup::Key]
+            .map(|group| match group_by.contains(&group) {
+                true => column(group),
+                false => "NULL",
+            })
+            .join(", ");
+        let grouped: Vec<&str> = group_by.iter().map(|group| column(*group)).collect();
+        let mut sql = format!(
+            "SELECT {}, COUNT(*), SUM(status >= 400), SUM(prompt_tokens),
+                SUM(completion_tokens), AVG(latency_ms)
+             FROM audit_log WHERE timestamp >= ?1",
+            selected
+        );
+        if !grouped.is_empty() {
+            sql.push_str(&format!(
+                " GROUP BY {} ORDER BY {}",
+                grouped.join(", "),
+                grouped.join(", ")
+            ));
+        }
+
+        let mut stmt = conn.prepare(&sql).map_err(io::Error::other)?;
+        let summaries = stmt
+            .query_map(params![since.unwrap_or("")], |row| {
+                Ok(UsageSummary {
+                    day: row.get(0)?,
+                    model: row.get(1)?,
+                    key: row.get(2)?,
+                    requests: row.get(3)?,
+                    errors: row.get::<_, Option<u64>>(4)?.unwrap_or_default(),
+                    prompt_tokens: row.get::<_, Option<u64>>(5)?.unwrap_or_default(),
+                    completion_tokens: row.get::<_, Option<u64>>(6)?.unwrap_or_default(),
+                    avg_latency_ms: row.get::<_, Option<f64>>(7)?.unwrap_or_default(),
+                })
+            })
+            .map_err(io::Error::other)?
+            .collect::<SqlResult<Vec<_>>>()
+            .map_err(io::Error::other)?;
+
+        // Without requests, the ungrouped summary is a single empty row
+        Ok(summaries
+            .into_iter()
+            .filter(|summary| summary.requests > 0)
+            .collect())
+    }
+}
+
+fn api_key_from_row(row: &rusqlite::Row) -> SqlResult<StoredApiKey> {
+    Ok(StoredApiKey {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        key_hash: row.get(2)?,
+        prefix: row.get(3)?,
+        created_at: row.get(4)?,
+        revoked_at: row.get(5)?,
+        requests_per_minute: row.get(6)?,
+        tokens_per_minute: row.get(7)?,
+        daily_token_quota: row.get(8)?,
+    })
 }
 
 #[cfg(test)]
@@ -437,6 +916,40 @@ mod tests {
         assert!(retrieved3.is_some());
     }
 
+    #[test]
+    fn test_sqlite_adapters() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+        let storage = SqliteStorage::new(db_path).unwrap();
+
+        for (name, base) in [
+            ("test/Adapter-A", "test/Base"),
+            ("test/adapter-b", "test/base"),
+            ("test/adapter-c", "test/other"),
+        ] {
+            storage
+                .register_adapter(AdapterInfo {
+                    name: name.to_string(),
+                    base_model: base.to_string(),
+                    path: "/tmp/adapter".to_string(),
+                    created_at: "2025-01-01T00:00:00Z".to_string(),
+                })
+                .unwrap();
+        }
+
+        let adapter = storage.get_adapter("TEST/ADAPTER-A").unwrap().unwrap();
+        assert_eq!(adapter.name, "test/adapter-a");
+        assert_eq!(adapter.base_model, "test/base");
+
+        assert_eq!(storage.load_adapters(None).unwrap().len(), 3);
+        let shared = storage.load_adapters(Some("test/base")).unwrap();
+        assert_eq!(shared.len(), 2);
+
+        storage.unregister_adapter("test/adapter-a").unwrap();
+        assert!(storage.get_adapter("test/adapter-a").unwrap().is_none());
+        assert_eq!(storage.load_adapters(Some("test/base")).unwrap().len(), 1);
+    }
+
     #[test]
     fn test_author_filter_case_sensitive() {
         let temp_dir = TempDir::new().unwrap();
@@ -457,4 +970,170 @@ mod tests {
         filters.insert("author".to_string(), "InftyAI".to_string());
         assert_eq!(storage.load_models(Some(&filters)).unwrap().len(), 0);
     }
+
+    #[test]
+    fn test_sqlite_responses() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+        let storage = SqliteStorage::new(db_path).unwrap();
+
+        let response = StoredResponse {
+            id: "resp_123".to_string(),
+            previous_response_id: Some("resp_122".to_string()),
+            model: "test/model".to_string(),
+            input: serde_json::json!([{"role": "user", "content": "Hello"}]),
+            response: serde_json::json!({"id": "resp_123", "output": []}),
+            created_at: "2025-01-01T00:00:00Z".to_string(),
+            key_id: None,
+        };
+        storage.store_response(response.clone()).unwrap();
+
+        assert_eq!(
+            storage.get_response("resp_123", None).unwrap(),
+            Some(response.clone())
+        );
+        assert!(storage.get_response("resp_404", None).unwrap().is_none());
+
+        assert!(storage.delete_response("resp_123", None).unwrap());
+        assert!(!storage.delete_response("resp_123", None).unwrap());
+        assert!(storage.get_response("resp_123", None).unwrap().is_none());
+
+        // Responses of a key are only visible to that key
+        let response = StoredResponse {
+            id: "resp_124".to_string(),
+            key_id: Some("key_1".to_string()),
+            ..response
+        };
+        storage.store_response(response.clone()).unwrap();
+        assert!(storage.get_response("resp_124", None).unwrap().is_none());
+        assert!(storage
+            .get_response("resp_124", Some("key_2"))
+            .unwrap()
+            .is_none());
+        assert!(!storage.delete_response("resp_124", Some("key_2")).unwrap());
+        assert_eq!(
+            storage.get_response("resp_124", Some("key_1")).unwrap(),
+            Some(response)
+        );
+        assert!(storage.delete_response("resp_124", Some("key_1")).unwrap());
+    }
+
+    #[test]
+    fn test_sqlite_api_keys() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+        let storage = SqliteStorage::new(db_path).unwrap();
+        assert!(!storage.has_active_api_keys().unwrap());
+
+        let key = StoredApiKey {
+            id: "key_1".to_string(),
+            name: "ci".to_string(),
+            key_hash: "abc".to_string(),
+            prefix: "puma-12".to_string(),
+            created_at: "2025-01-01T00:00:00Z".to_string(),
+            revoked_at: None,
+            requests_per_minute: Some(60),
+            tokens_per_minute: None,
+            daily_token_quota: Some(1_000_000),
+        };
+        storage.create_api_key(key.clone()).unwrap();
+        assert!(storage.create_api_key(key.clone()).is_err());
+
+        assert!(storage.has_active_api_keys().unwrap());
+        assert_eq!(storage.find_api_key("abc").unwrap(), Some(key));
+        assert!(storage.find_api_key("def").unwrap().is_none());
+
+        assert!(storage
+            .revoke_api_key("ci", "2025-01-02T00:00:00Z")
+            .unwrap());
+        assert!(!storage
+            .revoke_api_key("key_1", "2025-01-03T00:00:00Z")
+            .unwrap());
+        assert!(storage.find_api_key("abc").unwrap().is_none());
+        assert!(!storage.has_active_api_keys().unwrap());
+
+        let keys = storage.load_api_keys().unwrap();
+        assert_eq!(keys.len(), 1);
+        assert_eq!(keys[0].revoked_at.as_deref(), Some("2025-01-02T00:00:00Z"));
+    }
+
+    #[test]
+    fn test_sqlite_daily_tokens() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+        let storage = SqliteStorage::new(db_path).unwrap();
+
+        assert_eq!(storage.daily_tokens("key_1", "2025-01-01").unwrap(), 0);
+        storage
+            .add_daily_tokens("key_1", "2025-01-01", 100)
+            .unwrap();
+        storage.add_daily_tokens("key_1", "2025-01-01", 20).unwrap();
+        storage.add_daily_tokens("key_1", "2025-01-02", 5).unwrap();
+
+        assert_eq!(storage.daily_tokens("key_1", "2025-01-01").unwrap(), 120);
+        assert_eq!(storage.daily_tokens("key_1", "2025-01-02").unwrap(), 5);
+        assert_eq!(storage.daily_tokens("key_2", "2025-01-01").unwrap(), 0);
+    }
+
+    #[test]
+    fn test_sqlite_audit_log() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+        let storage = SqliteStorage::new(db_path).unwrap();
+        assert!(storage
+            .summarize_usage(&[UsageGroup::Model], None)
+            .unwrap()
+            .is_empty());
+
+        let entry = |timestamp: &str, model: &str, key: Option<&str>, status: u16| AuditEntry {
+            timestamp: timestamp.to_string(),
+            method: "POST".to_string(),
+            path: "/v1/chat/completions".to_string(),
+            model: Some(model.to_string()),
+            key_name: key.map(str::to_string),
+            prompt_tokens: 10,
+            completion_tokens: 20,
+            latency_ms: 100.0,
+            status,
+            finish_reason: Some("stop".to_string()),
+            ..Default::default()
+        };
+        storage
+            .record_request(entry("2025-01-01T10:00:00Z", "a", Some("ci"), 200))
+            .unwrap();
+        storage
+            .record_request(entry("2025-01-01T11:00:00Z", "b", Some("ci"), 200))
+            .unwrap();
+        storage
+            .record_request(entry("2025-01-02T10:00:00Z", "a", None, 500))
+            .unwrap();
+
+        let recent = storage.recent_requests(2).unwrap();
+        assert_eq!(recent.len(), 2);
+        assert_eq!(recent[0], entry("2025-01-02T10:00:00Z", "a", None, 500));
+
+        let by_model = storage.summarize_usage(&[UsageGroup::Model], None).unwrap();
+        assert_eq!(by_model.len(), 2);
+        assert_eq!(by_model[0].model.as_deref(), Some("a"));
+        assert_eq!(by_model[0].day, None);
+        assert_eq!(
+            (
+                by_model[0].requests,
+                by_model[0].errors,
+                by_model[0].completion_tokens
+            ),
+            (2, 1, 40)
+        );
+
+        let by_day_and_key = storage
+            .summarize_usage(&[UsageGroup::Day, UsageGroup::Key], Some("2025-01-02"))
+            .unwrap();
+        assert_eq!(by_day_and_key.len(), 1);
+        assert_eq!(by_day_and_key[0].day.as_deref(), Some("2025-01-02"));
+        assert_eq!(by_day_and_key[0].key, None);
+
+        let total = storage.summarize_usage(&[], None).unwrap();
+        assert_eq!(total[0].requests, 3);
+        assert_eq!(total[0].prompt_tokens, 30);
+    }
 }
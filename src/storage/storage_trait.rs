@@ -1,4 +1,4 @@
-use crate::registry::model_registry::ModelInfo;
+use crate::registry::model_registry::{AdapterInfo, ModelInfo};
 use std::io;
 
 use std::collections::HashMap;
@@ -19,4 +19,152 @@ pub trait ModelStorage: Send + Sync {
 
     /// Get a single model by name
     fn get_model(&self, name: &str) -> Result<Option<ModelInfo>, io::Error>;
+
+    /// Register (insert or update) a LoRA adapter
+    fn register_adapter(&self, adapter: AdapterInfo) -> Result<(), io::Error>;
+
+    /// Unregister (delete) an adapter by name
+    fn unregister_adapter(&self, name: &str) -> Result<(), io::Error>;
+
+    /// Get a single adapter by name
+    fn get_adapter(&self, name: &str) -> Result<Option<AdapterInfo>, io::Error>;
+
+    /// Load adapters, optionally only those built on the given base model
+    fn load_adapters(&self, base_model: Option<&str>) -> Result<Vec<AdapterInfo>, io::Error>;
+}
+
+/// A response stored for `previous_response_id` chaining in the Responses API.
+/// Items are kept as JSON so storage does not depend on API types.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredResponse {
+    pub id: String,
+    pub previous_response_id: Option<String>,
+    pub model: String,
+    /// Input items of this turn, without items of previous responses
+    pub input: serde_json::Value,
+    /// The full response object
+    pub response: serde_json::Value,
+    pub created_at: String,
+    /// API key that created the response, unset without authentication
+    pub key_id: Option<String>,
+}
+
+/// Trait for Responses API storage backends
+pub trait ResponseStorage: Send + Sync {
+    /// Store (insert or replace) a response
+    fn store_response(&self, response: StoredResponse) -> Result<(), io::Error>;
+
+    /// Get a single response by id, if it was created by the given key
+    fn get_response(
+        &self,
+        id: &str,
+        key_id: Option<&str>,
+    ) -> Result<Option<StoredResponse>, io::Error>;
+
+    /// Delete a response by id if it was created by the given key, returns
+    /// whether it existed
+    fn delete_response(&self, id: &str, key_id: Option<&str>) -> Result<bool, io::Error>;
+}
+
+/// An API key of the server. Only the SHA-256 hash of the key is stored,
+/// with a short prefix to tell keys apart in listings.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredApiKey {
+    pub id: String,
+    pub name: String,
+    pub key_hash: String,
+    pub prefix: String,
+    pub created_at: String,
+    pub revoked_at: Option<String>,
+    /// Limits of the key, the server defaults apply when unset
+    pub requests_per_minute: Option<u64>,
+    pub tokens_per_minute: Option<u64>,
+    pub daily_token_quota: Option<u64>,
+}
+
+/// Trait for API key storage backends
+pub trait ApiKeyStorage: Send + Sync {
+    /// Store a new key, names must be unique
+    fn create_api_key(&self, key: StoredApiKey) -> Result<(), io::Error>;
+
+    /// Load all keys, including revoked ones, oldest first
+    fn load_api_keys(&self) -> Result<Vec<StoredApiKey>, io::Error>;
+
+    /// Get the active (not revoked) key with the given hash
+    fn find_api_key(&self, key_hash: &str) -> Result<Option<StoredApiKey>, io::Error>;
+
+    /// Whether any key is active
+    fn has_active_api_keys(&self) -> Result<bool, io::Error>;
+
+    /// Revoke an active key by name or id, returns whether it was active
+    fn revoke_api_key(&self, name_or_id: &str, revoked_at: &str) -> Result<bool, io::Error>;
+
+    /// Add to the tokens used by a key on a day (UTC, `YYYY-MM-DD`)
+    fn add_daily_tokens(&self, key_id: &str, day: &str, tokens: u64) -> Result<(), io::Error>;
+
+    /// Tokens used by a key on a day (UTC, `YYYY-MM-DD`)
+    fn daily_tokens(&self, key_id: &str, day: &str) -> Result<u64, io::Error>;
+}
+
+/// A request handled by the server, kept in the audit log
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct AuditEntry {
+    /// Arrival time, RFC 3339 in UTC
+    pub timestamp: String,
+    pub method: String,
+    pub path: String,
+    /// Model that generated, unset for requests without generation
+    pub model: Option<String>,
+    /// Key that authenticated the request
+    pub key_id: Option<String>,
+    pub key_name: Option<String>,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    /// Time until the response was fully sent, streams included
+    pub latency_ms: f64,
+    pub status: u16,
+    pub finish_reason: Option<String>,
+    /// Full bodies, only captured when enabled
+    pub request_body: Option<String>,
+    pub response_body: Option<String>,
+}
+
+/// Columns the audit log can be summarized by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UsageGroup {
+    /// UTC day, `YYYY-MM-DD`
+    Day,
+    Model,
+    Key,
+}
+
+/// Usage of a group of requests. Only the columns grouped by are set.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct UsageSummary {
+    pub day: Option<String>,
+    pub model: Option<String>,
+    pub key: Option<String>,
+    pub requests: u64,
+    /// Requests answered with a 4xx or 5xx status
+    pub errors: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub avg_latency_ms: f64,
+}
+
+/// Trait for audit log storage backends
+pub trait AuditStorage: Send + Sync {
+    /// Append a request to the log
+    fn record_request(&self, entry: AuditEntry) -> Result<(), io::Error>;
+
+    /// Latest requests, newest first
+    fn recent_requests(&self, limit: usize) -> Result<Vec<AuditEntry>, io::Error>;
+
+    /// Usage by the given columns of requests since a time (RFC 3339 or
+    /// `YYYY-MM-DD`), ordered by the same columns
+    fn summarize_usage(
+        &self,
+        group_by: &[UsageGroup],
+        since: Option<&str>,
+    ) -> Result<Vec<UsageSummary>, io::Error>;
 }
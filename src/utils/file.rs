@@ -22,6 +22,16 @@ pub fn cache_dir() -> PathBuf {
     root_home().join("cache")
 }
 
+/// Directory of the persistent prompt (KV state) cache
+pub fn prompt_cache_dir() -> PathBuf {
+    cache_dir().join("prompts")
+}
+
+/// Default Unix domain socket of the server
+pub fn socket_path() -> PathBuf {
+    root_home().join("puma.sock")
+}
+
 pub fn huggingface_cache_dir() -> PathBuf {
     cache_dir().join("huggingface")
 }
@@ -38,7 +48,6 @@ pub fn format_model_name(name: &str) -> String {
 }
 
 /// List all files recursively in a directory
-#[allow(dead_code)]
 pub fn list_files_recursive(dir: &std::path::Path) -> std::io::Result<Vec<PathBuf>> {
     let mut files = Vec::new();
     if dir.is_dir() {
@@ -34,6 +34,42 @@ pub fn format_size_decimal(bytes: u64) -> String {
     }
 }
 
+/// Parse a human-readable byte size (e.g. 512MB, 10GB, 1GiB or plain bytes)
+pub fn parse_size(size: &str) -> Result<u64, String> {
+    let size = size.trim();
+    let split = size
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(size.len());
+    let (number, unit) = size.split_at(split);
+
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("Invalid size '{}'", size))?;
+    let multiplier: u64 = match unit.trim().to_uppercase().as_str() {
+        "" | "B" => 1,
+        "KB" | "K" => 1_000,
+        "MB" | "M" => 1_000_000,
+        "GB" | "G" => 1_000_000_000,
+        "KIB" => 1 << 10,
+        "MIB" => 1 << 20,
+        "GIB" => 1 << 30,
+        _ => return Err(format!("Invalid size unit in '{}'", size)),
+    };
+    Ok((number * multiplier as f64) as u64)
+}
+
+/// Parse octal file permissions (e.g. 600 or 0o660)
+pub fn parse_mode(mode: &str) -> Result<u32, String> {
+    let digits = mode.trim().trim_start_matches("0o");
+    match u32::from_str_radix(digits, 8) {
+        Ok(parsed) if parsed <= 0o777 => Ok(parsed),
+        _ => Err(format!(
+            "Invalid permissions '{}', expected octal like 600",
+            mode
+        )),
+    }
+}
+
 /// Format parameter count to human-readable format (K, M, B)
 pub fn format_parameters(count: u64) -> String {
     const K: f64 = 1_000.0;
@@ -106,6 +142,27 @@ pub fn format_time_ago(timestamp: &str) -> String {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("1024"), Ok(1024));
+        assert_eq!(parse_size("512MB"), Ok(512_000_000));
+        assert_eq!(parse_size("10GB"), Ok(10_000_000_000));
+        assert_eq!(parse_size("1.5 GiB"), Ok(1_610_612_736));
+        assert_eq!(parse_size("2g"), Ok(2_000_000_000));
+        assert!(parse_size("ten").is_err());
+        assert!(parse_size("10XB").is_err());
+    }
+
+    #[test]
+    fn test_parse_mode() {
+        assert_eq!(parse_mode("600"), Ok(0o600));
+        assert_eq!(parse_mode("0660"), Ok(0o660));
+        assert_eq!(parse_mode("0o777"), Ok(0o777));
+        assert!(parse_mode("800").is_err());
+        assert!(parse_mode("1777").is_err());
+        assert!(parse_mode("rw").is_err());
+    }
+
     #[test]
     fn test_format_size_bytes() {
         assert_eq!(format_size(0), "0 B");
@@ -140,10 +140,12 @@ fn test_ps_command() {
     let output = run_puma(home, &["ps"]);
     assert!(output.status.success());
 
+    // No server listening on the socket, only the header is shown
     let stdout = String::from_utf8_lossy(&output.stdout);
-    assert!(stdout.contains("NAME"));
-    assert!(stdout.contains("PROVIDER"));
     assert!(stdout.contains("MODEL"));
+    assert!(stdout.contains("PROVIDER"));
+    assert!(stdout.contains("SOCKET"));
+    assert!(!stdout.contains("Running"));
 }
 
 #[test]
@@ -284,3 +286,52 @@ fn test_inspect_help() {
     let stdout = String::from_utf8_lossy(&output.stdout);
     assert!(stdout.contains("Return detailed information about a model"));
 }
+
+#[test]
+fn test_key_lifecycle() {
+    let temp_dir = TempDir::new().unwrap();
+    let home = temp_dir.path().to_str().unwrap();
+
+    let output = run_puma(home, &["key", "create", "ci"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let key = stdout
+        .lines()
+        .find(|line| line.starts_with("puma-"))
+        .expect("key is printed once");
+
+    // Names are unique
+    let output = run_puma(home, &["key", "create", "ci"]);
+    assert!(!output.status.success());
+    assert!(output_contains(&output, "already exists"));
+
+    // Listings only show the start of the key
+    let output = run_puma(home, &["key", "list"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("ci"));
+    assert!(stdout.contains("Active"));
+    assert!(!stdout.contains(key));
+
+    let output = run_puma(home, &["key", "revoke", "ci"]);
+    assert!(output.status.success());
+    let output = run_puma(home, &["key", "list"]);
+    assert!(output_contains(&output, "Revoked"));
+
+    let output = run_puma(home, &["key", "revoke", "ci"]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_serve_tls_requires_cert_and_key() {
+    let temp_dir = TempDir::new().unwrap();
+    let home = temp_dir.path().to_str().unwrap();
+
+    let output = run_puma(home, &["serve", "some-model", "--tls-cert", "server.crt"]);
+    assert!(!output.status.success());
+    assert!(output_contains(&output, "--tls-key"));
+
+    let output = run_puma(home, &["serve", "some-model", "--tls-client-ca", "ca.crt"]);
+    assert!(!output.status.success());
+    assert!(output_contains(&output, "--tls-cert"));
+}